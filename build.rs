@@ -0,0 +1,61 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// With the embedded-assets feature on, lists every file under assets/ as an include_bytes! so
+// the game can ship as a single binary. See src/asset_source.rs.
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    if std::env::var_os("CARGO_FEATURE_EMBEDDED_ASSETS").is_none() {
+        return;
+    }
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+    println!("cargo:rerun-if-changed={}", root.display());
+
+    let mut files = Vec::new();
+
+    // The screenshots at the top level are only there for the README.
+    for entry in fs::read_dir(&root).expect("assets folder is missing") {
+        let path = entry.unwrap().path();
+
+        if path.is_dir() {
+            collect_files(&path, &mut files);
+        }
+    }
+
+    files.sort();
+
+    let mut generated = String::from("pub const EMBEDDED_ASSETS: &[(&str, &[u8])] = &[\n");
+
+    for file in files.iter() {
+        let asset_path = file
+            .strip_prefix(&root)
+            .unwrap()
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        generated.push_str(&format!(
+            "    ({:?}, include_bytes!({:?})),\n",
+            asset_path,
+            file.display().to_string()
+        ));
+    }
+
+    generated.push_str("];\n");
+
+    let out_path = PathBuf::from(std::env::var("OUT_DIR").unwrap()).join("embedded_assets.rs");
+    fs::write(out_path, generated).unwrap();
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
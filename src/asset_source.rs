@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+
+use bevy::asset::io::file::FileAssetReader;
+#[cfg(feature = "embedded-assets")]
+use bevy::asset::io::memory::{Dir, MemoryAssetReader};
+use bevy::asset::io::{AssetSource, AssetSourceId};
+use bevy::prelude::*;
+
+// Decides where the asset server reads from. In order:
+// - a folder given with `--assets <dir>` or the WED_ASSETS environment variable,
+// - the copy baked into the binary, when built with the embedded-assets feature,
+// - Bevy's usual `assets` folder next to the executable (or the crate, under cargo run).
+// Has to be added before DefaultPlugins, the asset server is built as soon as they are.
+pub struct AssetSourcePlugin;
+
+impl Plugin for AssetSourcePlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(root) = get_asset_root() {
+            println!("Loading assets from {}", root.display());
+
+            if !root.is_dir() {
+                println!("Asset folder {} doesn't exist", root.display());
+            }
+
+            app.register_asset_source(
+                AssetSourceId::Default,
+                AssetSource::build().with_reader(move || Box::new(FileAssetReader::new(&root))),
+            );
+        } else if cfg!(feature = "embedded-assets") {
+            println!("Loading assets embedded in the binary");
+
+            add_embedded_source(app);
+        }
+    }
+}
+
+const ASSETS_FLAG: &str = "--assets";
+const ASSETS_ENV_VAR: &str = "WED_ASSETS";
+
+// The flag wins over the environment variable. Relative paths are from the current directory,
+// not the executable's.
+fn get_asset_root() -> Option<PathBuf> {
+    let mut args = std::env::args().skip(1);
+    let mut root = None;
+
+    while let Some(arg) = args.next() {
+        if arg == ASSETS_FLAG {
+            root = args.next();
+        } else if let Some(value) = arg.strip_prefix("--assets=") {
+            root = Some(value.to_string());
+        }
+    }
+
+    let root = PathBuf::from(root.or_else(|| std::env::var(ASSETS_ENV_VAR).ok())?);
+
+    if root.is_absolute() {
+        Some(root)
+    } else {
+        std::env::current_dir().ok().map(|dir| dir.join(root))
+    }
+}
+
+#[cfg(feature = "embedded-assets")]
+mod embedded {
+    include!(concat!(env!("OUT_DIR"), "/embedded_assets.rs"));
+}
+
+#[cfg(feature = "embedded-assets")]
+fn add_embedded_source(app: &mut App) {
+    let root = Dir::default();
+
+    for (path, bytes) in embedded::EMBEDDED_ASSETS.iter() {
+        root.insert_asset(std::path::Path::new(path), *bytes);
+    }
+
+    app.register_asset_source(
+        AssetSourceId::Default,
+        AssetSource::build()
+            .with_reader(move || Box::new(MemoryAssetReader { root: root.clone() })),
+    );
+}
+
+#[cfg(not(feature = "embedded-assets"))]
+fn add_embedded_source(_app: &mut App) {}
@@ -1,14 +1,24 @@
-use crate::game::weapons::{
-    get_energy_weapon, get_guitar_weapon, get_horse_weapon, get_lightning_weapon,
-    get_splash_weapon, Weapon,
-};
-use crate::main_menu::{MyMusic, BLUE, BORDER_COLOR, DARK_PURPLE, LIGHT_TEAL, PURPLE};
+use crate::game::damage_numbers::DamageNumberSettings;
+use crate::game::level::MapMode;
+use crate::game::projectile_spawner::DamageType;
+use crate::game::weapons::WeaponsEnum;
+use crate::loading::LoadingScene;
+use crate::main_menu::{MyMusic, BLUE, BORDER_COLOR, DARK_PURPLE, LIGHT_TEAL, PURPLE, WHITE};
+use crate::meta::{MetaProgress, MetaUpgrade, META_MAX_LEVEL};
 
+use crate::sprite::AnimationIndices;
+use crate::sprite_sheets::{SheetGrid, SpriteSheets};
 use crate::util_fade::FadeState;
 use crate::GameState;
 use bevy::app::Plugin;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext, LoadState};
 use bevy::prelude::*;
-use bevy::utils::HashMap;
+use bevy::reflect::TypePath;
+use bevy::utils::BoxedFuture;
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
 
 pub struct CharacterSelectPlugin;
 
@@ -16,28 +26,56 @@ pub struct CharacterSelectPlugin;
 pub enum CharacterSelectState {
     #[default]
     Unloaded,
-    Init, // Usually used for nested plugins to manage state - leave for now
+    Init, // Waiting on the character roster to finish loading
     Started,
 }
 
-#[derive(States, PartialEq, Eq, Default, Debug, Clone, Hash)]
-pub enum SelectedCharacterState {
-    Ailsa,
-    #[default]
-    Lisa,
+// Id of the roster entry picked on the character select screen. In co-op, player two plays
+// that character's partner.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct SelectedCharacter {
+    pub id: String,
+    pub coop: bool,
 }
 
+pub const COOP_TOGGLE_KEY: KeyCode = KeyCode::C;
+pub const ENDLESS_TOGGLE_KEY: KeyCode = KeyCode::F;
+pub const DAMAGE_NUMBERS_TOGGLE_KEY: KeyCode = KeyCode::N;
+
+pub const CHARACTER_ROSTER_PATH: &str = "characters/characters.roster.ron";
+
 impl Plugin for CharacterSelectPlugin {
     fn build(&self, app: &mut App) {
+        let registry = CharacterRegistry::default();
+
         app.add_state::<CharacterSelectState>()
+            .init_asset::<CharacterRoster>()
+            .register_asset_loader(CharacterRosterLoader {
+                error: registry.loader_error.clone(),
+            })
+            .init_resource::<SelectedCharacter>()
+            .insert_resource(registry)
+            .add_systems(Startup, load_character_roster)
+            .add_systems(Update, update_character_registry)
             .add_systems(
                 OnEnter(GameState::CharacterSelect),
-                (reset_camera, spawn_select_scene),
+                (reset_camera, init_select_scene),
             )
             .add_systems(OnExit(GameState::CharacterSelect), despawn_select_scene)
             .add_systems(
                 Update,
-                (character_select_system).run_if(in_state(GameState::CharacterSelect)),
+                spawn_select_scene.run_if(in_state(CharacterSelectState::Init)),
+            )
+            .add_systems(
+                Update,
+                (
+                    character_select_system,
+                    coop_toggle_system,
+                    endless_toggle_system,
+                    damage_numbers_toggle_system,
+                    meta_upgrade_system,
+                )
+                    .run_if(in_state(GameState::CharacterSelect)),
             );
     }
 }
@@ -57,55 +95,347 @@ pub struct CharacterTitle;
 #[derive(Debug, Component)]
 pub struct CharacterDesc;
 
-#[derive(Debug, Component, Clone)]
+#[derive(Debug, Component)]
+pub struct CoopToggleButton;
+
+#[derive(Debug, Component)]
+pub struct CoopToggleText;
+
+#[derive(Debug, Component)]
+pub struct EndlessToggleButton;
+
+#[derive(Debug, Component)]
+pub struct EndlessToggleText;
+
+#[derive(Debug, Component)]
+pub struct DamageNumbersToggleButton;
+
+#[derive(Debug, Component)]
+pub struct DamageNumbersToggleText;
+
+// One per meta upgrade on sale.
+#[derive(Debug, Component)]
+pub struct MetaUpgradeButton(MetaUpgrade);
+
+#[derive(Debug, Component)]
+pub struct MetaUpgradeText(MetaUpgrade);
+
+#[derive(Debug, Component)]
+pub struct MetaGoldText;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CharacterPortrait {
+    pub sprite: String,
+    pub index: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CharacterSheet {
+    pub sprite: String,
+    pub width: f32,
+    pub height: f32,
+    pub cols: usize,
+    pub rows: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CharacterAnimations {
+    pub idle: AnimationIndices,
+    pub run_down: AnimationIndices,
+    pub run_down_horiz: AnimationIndices,
+    pub run_horizontal: AnimationIndices,
+    pub run_up_horiz: AnimationIndices,
+    pub run_up: AnimationIndices,
+}
+
+// Might, cooldown and area are multipliers - 1.0 is the unmodified weapon. Regen is health
+// back per second, and armor is taken off every hit.
+#[derive(Debug, Component, Clone, Deserialize)]
+#[serde(default)]
+pub struct CharacterStats {
+    pub max_health: f32,
+    pub regen: f32,
+    pub armor: f32,
+    pub speed: f32,
+    pub might: f32,
+    pub cooldown: f32,
+    pub area: f32,
+}
+
+impl Default for CharacterStats {
+    fn default() -> Self {
+        Self {
+            max_health: 100.,
+            regen: 0.,
+            armor: 0.,
+            speed: 100.,
+            might: 1.,
+            cooldown: 1.,
+            area: 1.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum PassiveEffect {
+    None,
+    Regeneration {
+        per_second: f32,
+    },
+    DamageTypeBonus {
+        damage_type: DamageType,
+        multiplier: f32,
+    },
+}
+
+#[derive(Debug, Component, Clone, Deserialize)]
+pub struct CharacterPassive {
+    pub name: String,
+    pub desc: String,
+    pub effect: PassiveEffect,
+}
+
+impl Default for CharacterPassive {
+    fn default() -> Self {
+        Self {
+            name: "None".into(),
+            desc: "".into(),
+            effect: PassiveEffect::None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum UltimateEffect {
+    // Damages every enemy within the radius of the player.
+    Shockwave {
+        damage: f32,
+        radius: f32,
+        damage_type: DamageType,
+    },
+    // Heals the player and makes them untouchable for a while.
+    Blessing {
+        heal: f32,
+        invulnerable_seconds: f32,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CharacterUltimate {
+    pub name: String,
+    pub desc: String,
+    pub kills_required: u32,
+    pub effect: UltimateEffect,
+}
+
+impl Default for CharacterUltimate {
+    fn default() -> Self {
+        Self {
+            name: "Shockwave".into(),
+            desc: "".into(),
+            kills_required: 50,
+            effect: UltimateEffect::Shockwave {
+                damage: 50.,
+                radius: 250.,
+                damage_type: DamageType::Normal,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Component, Clone, Deserialize)]
 pub struct CharacterBlock {
+    pub id: String,
     pub name: String,
-    pub pic_sprite: &'static str,
     pub desc: String,
-    pub selected_character_state: SelectedCharacterState,
-    pub starting_weapon: Weapon,
+    pub portrait: CharacterPortrait,
+    pub sheet: CharacterSheet,
+    pub animations: CharacterAnimations,
+    #[serde(default)]
+    pub stats: CharacterStats,
+    #[serde(default)]
+    pub passive: CharacterPassive,
+    #[serde(default)]
+    pub ultimate: CharacterUltimate,
+    pub starting_weapon: WeaponsEnum,
+    // Roster id of the character waiting at the house after the game is won.
+    #[serde(default)]
+    pub partner: Option<String>,
 }
 
-pub fn get_ailsa_character() -> CharacterBlock {
-    CharacterBlock {
-        name: "Ailsa".to_string(),
-        desc: "A bard who uses magic and a guitar for her instrument of choice, the most deadly of weapon combinations! The guitar is used to make sure they're dead afterwards - she doesn't actually need it for the magic part..."
-            .to_string(),
-        pic_sprite: "sprites/player/ailsa.png",
-        selected_character_state: SelectedCharacterState::Ailsa,
-        starting_weapon: get_guitar_weapon(),
+#[derive(Asset, TypePath, Debug, Deserialize)]
+pub struct CharacterRoster {
+    pub characters: Vec<CharacterBlock>,
+}
+
+// Bevy only keeps LoadState::Failed for an asset that fails to load, so the loader hands the
+// actual error over to the CharacterRegistry for the select screen to show.
+pub struct CharacterRosterLoader {
+    error: Arc<Mutex<Option<String>>>,
+}
+
+#[derive(Debug, Error)]
+pub enum CharacterRosterLoaderError {
+    #[error("Could not read character roster: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse character roster: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for CharacterRosterLoader {
+    type Asset = CharacterRoster;
+    type Settings = ();
+    type Error = CharacterRosterLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let result: Result<CharacterRoster, Self::Error> = async {
+                let mut bytes = Vec::new();
+                reader.read_to_end(&mut bytes).await?;
+
+                Ok(ron::de::from_bytes::<CharacterRoster>(&bytes)?)
+            }
+            .await;
+
+            if let Err(error) = &result {
+                *self.error.lock().unwrap() = Some(error.to_string());
+            }
+
+            result
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["roster.ron"]
     }
 }
 
-pub fn get_lisa_character() -> CharacterBlock {
-    CharacterBlock {
-        name: "Lisa".to_string(),
-        desc: "A friendly cleric who smites those not worthy of the grace of Paul Simon's greatness! A disarming smile and calm demeanor belie the terrifying badass within.".to_string(),
-        pic_sprite: "sprites/player/lisa.png",
-        selected_character_state: SelectedCharacterState::Lisa,
-        starting_weapon: get_horse_weapon(),
+// Copy of the loaded roster, kept in roster file order.
+#[derive(Resource, Debug, Default)]
+pub struct CharacterRegistry {
+    pub handle: Handle<CharacterRoster>,
+    pub characters: Vec<CharacterBlock>,
+    // Why the roster couldn't be used - it failed to load or parse, or has no characters in it.
+    pub error: Option<String>,
+    loader_error: Arc<Mutex<Option<String>>>,
+}
+
+impl CharacterRegistry {
+    pub fn is_loaded(&self) -> bool {
+        !self.characters.is_empty()
+    }
+
+    // Falls back to the first character so a stale selection still spawns someone. None only
+    // if the roster is empty.
+    pub fn get(&self, id: &str) -> Option<&CharacterBlock> {
+        self.characters
+            .iter()
+            .find(|character| character.id == id)
+            .or_else(|| self.characters.first())
+    }
+
+    // The partner from the roster entry if set, otherwise the next character along.
+    pub fn get_partner(&self, id: &str) -> Option<&CharacterBlock> {
+        let character = self.get(id)?;
+
+        if let Some(partner_id) = &character.partner {
+            return self.get(partner_id);
+        }
+
+        let index = self
+            .characters
+            .iter()
+            .position(|other| other.id == character.id)
+            .unwrap_or(0);
+
+        self.characters.get((index + 1) % self.characters.len())
     }
 }
 
 pub const PLAYER_WIDTH: f32 = 23.;
 pub const PLAYER_HEIGHT: f32 = 36.;
 
-fn get_character_blocks() -> HashMap<SelectedCharacterState, CharacterBlock> {
-    let mut characters = HashMap::new();
+fn load_character_roster(mut registry: ResMut<CharacterRegistry>, assets: Res<AssetServer>) {
+    registry.handle = assets.load(CHARACTER_ROSTER_PATH);
+}
+
+fn update_character_registry(
+    mut events: EventReader<AssetEvent<CharacterRoster>>,
+    rosters: Res<Assets<CharacterRoster>>,
+    assets: Res<AssetServer>,
+    mut registry: ResMut<CharacterRegistry>,
+) {
+    if registry.error.is_none()
+        && assets.get_load_state(&registry.handle) == Some(LoadState::Failed)
+    {
+        let error = registry.loader_error.lock().unwrap().take();
+        let error = error.unwrap_or_else(|| "Could not load character roster".into());
+
+        println!("{}", error);
+        registry.error = Some(format!("{}\n\nassets/{}", error, CHARACTER_ROSTER_PATH));
+    }
 
-    characters.insert(SelectedCharacterState::Ailsa, get_ailsa_character());
+    for event in events.read() {
+        match event {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } => {
+                if *id != registry.handle.id() {
+                    continue;
+                }
 
-    characters.insert(SelectedCharacterState::Lisa, get_lisa_character());
+                if let Some(roster) = rosters.get(*id) {
+                    println!("Loaded {} characters", roster.characters.len());
+                    registry.characters = roster.characters.clone();
+                    registry.error = if roster.characters.is_empty() {
+                        Some(format!(
+                            "Character roster has no characters\n\nassets/{}",
+                            CHARACTER_ROSTER_PATH
+                        ))
+                    } else {
+                        None
+                    };
+                }
+            }
+            _ => {}
+        }
+    }
+}
 
-    characters
+fn init_select_scene(mut next_state: ResMut<NextState<CharacterSelectState>>) {
+    next_state.set(CharacterSelectState::Init);
 }
 
 fn spawn_select_scene(
     mut commands: Commands,
     mut next_state: ResMut<NextState<CharacterSelectState>>,
     assets: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut sheets: SpriteSheets,
+    registry: Res<CharacterRegistry>,
+    selected_character: Res<SelectedCharacter>,
+    map_mode: Res<MapMode>,
+    damage_numbers: Res<DamageNumberSettings>,
+    meta: Res<MetaProgress>,
 ) {
+    // Say what's wrong with the roster rather than sitting on a blank screen.
+    if let Some(error) = &registry.error {
+        commands
+            .spawn(get_roster_error_container())
+            .with_children(|commands| {
+                commands.spawn(get_roster_error_text(error, &assets));
+            });
+
+        next_state.set(CharacterSelectState::Started);
+        return;
+    }
+
+    if !registry.is_loaded() {
+        return;
+    }
+
     println!("Loading character select plugin");
 
     let menu_parent = (
@@ -123,6 +453,8 @@ fn spawn_select_scene(
                 top: Val::Px(0.0),
                 position_type: PositionType::Absolute,
                 flex_direction: FlexDirection::Row,
+                flex_wrap: FlexWrap::Wrap,
+                align_content: AlignContent::Center,
                 // align_items: AlignItems::Center,
                 // justify_content: JustifyContent::Center,
                 ..default()
@@ -134,59 +466,93 @@ fn spawn_select_scene(
         SelectMenuUi,
     );
 
-    let characters = get_character_blocks();
+    let num_characters = registry.characters.len();
 
-    let ailsa_block = characters
-        .get(&SelectedCharacterState::Ailsa)
-        .unwrap()
-        .clone();
+    commands.spawn(menu_parent).with_children(|commands| {
+        for character in registry.characters.iter() {
+            let pic = get_character_pic(character, &mut sheets);
+            let title = get_character_title(character, &assets);
+            let desc = get_character_desc(character, &assets);
+            let stats = get_character_stats(character, &assets);
 
-    let lisa_block = characters
-        .get(&SelectedCharacterState::Lisa)
-        .unwrap()
-        .clone();
+            commands
+                .spawn(get_character_container(num_characters))
+                .with_children(|commands| {
+                    commands
+                        .spawn(get_character_inner_container(
+                            character.clone(),
+                            num_characters,
+                        ))
+                        .with_children(|commands| {
+                            commands.spawn(pic);
+                            commands.spawn(title);
+                            commands.spawn(desc);
+                            commands.spawn(stats);
+                        });
+                });
+        }
 
-    let ailsa_pic = get_character_pic(&ailsa_block, &mut texture_atlases, &assets);
-    let lisa_pic = get_character_pic(&lisa_block, &mut texture_atlases, &assets);
-    let ailsa_title = get_character_title(&ailsa_block, &assets);
-    let lisa_title = get_character_title(&lisa_block, &assets);
-    let ailsa_desc = get_character_desc(&ailsa_block, &assets);
-    let lisa_desc = get_character_desc(&lisa_block, &assets);
+        commands
+            .spawn(get_coop_toggle_container())
+            .with_children(|commands| {
+                commands
+                    .spawn(get_coop_toggle_button())
+                    .with_children(|commands| {
+                        commands.spawn(get_coop_toggle_text(selected_character.coop, &assets));
+                    });
 
-    let ailsa_container = get_character_container();
-    let lisa_container = get_character_container();
+                commands
+                    .spawn(get_toggle_button(EndlessToggleButton))
+                    .with_children(|commands| {
+                        commands.spawn(get_endless_toggle_text(&map_mode, &assets));
+                    });
 
-    let ailsa_inner_container = get_character_inner_container(ailsa_block);
-    let lisa_inner_container = get_character_inner_container(lisa_block);
+                commands
+                    .spawn(get_toggle_button(DamageNumbersToggleButton))
+                    .with_children(|commands| {
+                        commands.spawn(get_damage_numbers_toggle_text(
+                            damage_numbers.enabled,
+                            &assets,
+                        ));
+                    });
+            });
 
-    commands.spawn(menu_parent).with_children(|commands| {
-        commands.spawn(ailsa_container).with_children(|commands| {
-            commands
-                .spawn(ailsa_inner_container)
-                .with_children(|commands| {
-                    commands.spawn(ailsa_pic);
-                    commands.spawn(ailsa_title);
-                    commands.spawn(ailsa_desc);
-                });
-        });
-        commands.spawn(lisa_container).with_children(|commands| {
-            commands
-                .spawn(lisa_inner_container)
-                .with_children(|commands| {
-                    commands.spawn(lisa_pic);
-                    commands.spawn(lisa_title);
-                    commands.spawn(lisa_desc);
-                });
-        });
+        // Meta upgrades, bought with gold from earlier runs.
+        commands
+            .spawn(get_coop_toggle_container())
+            .with_children(|commands| {
+                commands.spawn((
+                    get_toggle_text(get_meta_gold_label(&meta), &assets),
+                    MetaGoldText,
+                ));
+
+                for upgrade in MetaUpgrade::ALL {
+                    commands
+                        .spawn(get_toggle_button(MetaUpgradeButton(upgrade)))
+                        .with_children(|commands| {
+                            commands.spawn((
+                                get_toggle_text(get_meta_upgrade_label(upgrade, &meta), &assets),
+                                MetaUpgradeText(upgrade),
+                            ));
+                        });
+                }
+            });
     });
 
     next_state.set(CharacterSelectState::Started);
 }
 
-fn get_character_container() -> impl Bundle {
+// Two per row fills the screen like the original pair of cards - more than that wraps onto
+// extra rows, up to four cards wide.
+fn get_characters_per_row(num_characters: usize) -> usize {
+    num_characters.clamp(2, 4)
+}
+
+fn get_character_container(num_characters: usize) -> impl Bundle {
     (NodeBundle {
         style: Style {
-            flex_basis: Val::Percent(50.),
+            flex_basis: Val::Percent(100. / get_characters_per_row(num_characters) as f32),
+            padding: UiRect::all(Val::Px(10.)),
             align_items: AlignItems::Center,
             justify_content: JustifyContent::Center,
             ..default()
@@ -195,32 +561,221 @@ fn get_character_container() -> impl Bundle {
     },)
 }
 
+fn get_roster_error_container() -> impl Bundle {
+    (
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            background_color: DARK_PURPLE.into(),
+            ..default()
+        },
+        SelectMenuUi,
+    )
+}
+
+fn get_roster_error_text(error: &str, assets: &Res<AssetServer>) -> TextBundle {
+    let font = assets.load("fonts/spectral/spectral_medium.ttf");
+
+    TextBundle::from_section(
+        format!("The characters could not be loaded:\n\n{}", error),
+        TextStyle {
+            font,
+            font_size: 28.0,
+            color: WHITE,
+        },
+    )
+    .with_text_alignment(TextAlignment::Center)
+}
+
+fn get_coop_toggle_container() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            flex_basis: Val::Percent(100.),
+            padding: UiRect::all(Val::Px(10.)),
+            column_gap: Val::Px(20.),
+            row_gap: Val::Px(10.),
+            flex_wrap: FlexWrap::Wrap,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        ..default()
+    }
+}
+
+fn get_coop_toggle_button() -> (ButtonBundle, CoopToggleButton) {
+    get_toggle_button(CoopToggleButton)
+}
+
+fn get_toggle_button<T: Component>(marker: T) -> (ButtonBundle, T) {
+    (
+        ButtonBundle {
+            style: Style {
+                padding: UiRect::px(30., 30., 10., 10.),
+                border: UiRect::all(Val::Px(5.)),
+                ..default()
+            },
+            background_color: PURPLE.into(),
+            border_color: BLUE.into(),
+            ..default()
+        },
+        marker,
+    )
+}
+
+fn get_coop_toggle_label(coop: bool) -> String {
+    if coop {
+        format!(
+            "Two players: On - player two plays the partner with the arrow keys or a gamepad [{:?}]",
+            COOP_TOGGLE_KEY
+        )
+    } else {
+        format!("Two players: Off [{:?}]", COOP_TOGGLE_KEY)
+    }
+}
+
+fn get_coop_toggle_text(coop: bool, assets: &Res<AssetServer>) -> (TextBundle, CoopToggleText) {
+    let font = assets.load("fonts/spectral/spectral_bold.ttf");
+
+    (
+        TextBundle::from_section(
+            get_coop_toggle_label(coop),
+            TextStyle {
+                font,
+                font_size: 28.0,
+                color: LIGHT_TEAL,
+            },
+        ),
+        CoopToggleText,
+    )
+}
+
+fn get_endless_toggle_label(map_mode: &MapMode) -> String {
+    match map_mode {
+        MapMode::Arena => format!("Endless field: Off [{:?}]", ENDLESS_TOGGLE_KEY),
+        MapMode::Endless => format!("Endless field: On [{:?}]", ENDLESS_TOGGLE_KEY),
+    }
+}
+
+fn get_damage_numbers_toggle_label(enabled: bool) -> String {
+    if enabled {
+        format!("Damage numbers: On [{:?}]", DAMAGE_NUMBERS_TOGGLE_KEY)
+    } else {
+        format!("Damage numbers: Off [{:?}]", DAMAGE_NUMBERS_TOGGLE_KEY)
+    }
+}
+
+fn get_damage_numbers_toggle_text(
+    enabled: bool,
+    assets: &Res<AssetServer>,
+) -> (TextBundle, DamageNumbersToggleText) {
+    let font = assets.load("fonts/spectral/spectral_bold.ttf");
+
+    (
+        TextBundle::from_section(
+            get_damage_numbers_toggle_label(enabled),
+            TextStyle {
+                font,
+                font_size: 28.0,
+                color: LIGHT_TEAL,
+            },
+        ),
+        DamageNumbersToggleText,
+    )
+}
+
+fn get_endless_toggle_text(
+    map_mode: &MapMode,
+    assets: &Res<AssetServer>,
+) -> (TextBundle, EndlessToggleText) {
+    let font = assets.load("fonts/spectral/spectral_bold.ttf");
+
+    (
+        TextBundle::from_section(
+            get_endless_toggle_label(map_mode),
+            TextStyle {
+                font,
+                font_size: 28.0,
+                color: LIGHT_TEAL,
+            },
+        ),
+        EndlessToggleText,
+    )
+}
+
+fn get_toggle_text(label: String, assets: &Res<AssetServer>) -> TextBundle {
+    let font = assets.load("fonts/spectral/spectral_bold.ttf");
+
+    TextBundle::from_section(
+        label,
+        TextStyle {
+            font,
+            font_size: 28.0,
+            color: LIGHT_TEAL,
+        },
+    )
+}
+
+fn get_meta_gold_label(meta: &MetaProgress) -> String {
+    format!("Upgrades - {} gold", meta.gold)
+}
+
+fn get_meta_upgrade_label(upgrade: MetaUpgrade, meta: &MetaProgress) -> String {
+    let level = meta.get_level(upgrade);
+
+    if level >= META_MAX_LEVEL {
+        format!(
+            "{} {}/{} [{:?}]",
+            upgrade.get_name(),
+            level,
+            META_MAX_LEVEL,
+            upgrade.get_key()
+        )
+    } else {
+        format!(
+            "{} {}/{} - {} gold [{:?}]",
+            upgrade.get_name(),
+            level,
+            META_MAX_LEVEL,
+            upgrade.get_cost(level),
+            upgrade.get_key()
+        )
+    }
+}
+
 pub fn get_character_sprite(
     character: &CharacterBlock,
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
-    assets: &Res<AssetServer>,
+    sheets: &mut SpriteSheets,
 ) -> Handle<TextureAtlas> {
-    let texture_handle = assets.load(character.pic_sprite);
-    let texture_atlas = TextureAtlas::from_grid(
-        texture_handle,
-        Vec2::new(PLAYER_WIDTH, PLAYER_HEIGHT),
-        9,
-        6,
-        None,
-        None,
-    );
-
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
+    sheets.get_grid(&character.sheet.sprite, get_character_grid(character))
+}
 
-    texture_atlas_handle
+fn get_character_grid(character: &CharacterBlock) -> SheetGrid {
+    SheetGrid::new(
+        Vec2::new(character.sheet.width, character.sheet.height),
+        character.sheet.cols,
+        character.sheet.rows,
+    )
 }
 
-fn get_character_inner_container(character: CharacterBlock) -> impl Bundle {
+fn get_character_inner_container(character: CharacterBlock, num_characters: usize) -> impl Bundle {
+    // Shrink the cards once they start sharing a row with more than one other card.
+    let size = if get_characters_per_row(num_characters) > 2 {
+        Val::Px(360.)
+    } else {
+        Val::Px(500.)
+    };
+
     (
         ButtonBundle {
             style: Style {
-                height: Val::Px(500.),
-                width: Val::Px(500.),
+                height: size,
+                width: size,
                 flex_direction: FlexDirection::Column,
                 // align_content: AlignContent::Center,
                 justify_content: JustifyContent::Center,
@@ -238,12 +793,16 @@ fn get_character_inner_container(character: CharacterBlock) -> impl Bundle {
     )
 }
 
-fn get_character_pic(
+pub fn get_character_portrait(
     character: &CharacterBlock,
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
-    assets: &Res<AssetServer>,
-) -> impl Bundle {
-    let texture_atlas_handle = get_character_sprite(character, texture_atlases, assets);
+    sheets: &mut SpriteSheets,
+) -> Handle<TextureAtlas> {
+    // Portraits usually come off the same sheet as the sprite, so reuse its grid.
+    sheets.get_grid(&character.portrait.sprite, get_character_grid(character))
+}
+
+fn get_character_pic(character: &CharacterBlock, sheets: &mut SpriteSheets) -> impl Bundle {
+    let texture_atlas_handle = get_character_portrait(character, sheets);
 
     (
         AtlasImageBundle {
@@ -259,7 +818,7 @@ fn get_character_pic(
             },
             texture_atlas: texture_atlas_handle,
             texture_atlas_image: UiTextureAtlasImage {
-                index: 0,
+                index: character.portrait.index,
 
                 ..default()
             },
@@ -311,8 +870,88 @@ fn get_character_desc(character: &CharacterBlock, assets: &Res<AssetServer>) ->
     }
 }
 
+fn get_character_stats(character: &CharacterBlock, assets: &Res<AssetServer>) -> TextBundle {
+    let font = assets.load("fonts/spectral/spectral_medium.ttf");
+    let bold_font = assets.load("fonts/spectral/spectral_bold.ttf");
+    let stats = &character.stats;
+
+    let stats_section = TextSection {
+        value: format!(
+            "HP {}   Regen {}/s   Armor {}   Speed {}   \
+             Might x{:.2}   Cooldown x{:.2}   Area x{:.2}\n",
+            stats.max_health,
+            stats.regen,
+            stats.armor,
+            stats.speed,
+            stats.might,
+            stats.cooldown,
+            stats.area
+        ),
+        style: TextStyle {
+            font: font.clone(),
+            font_size: 20.0,
+            color: BORDER_COLOR,
+        },
+    };
+
+    let passive_name_section = TextSection {
+        value: format!("{}: ", character.passive.name),
+        style: TextStyle {
+            font: bold_font.clone(),
+            font_size: 20.0,
+            color: LIGHT_TEAL,
+        },
+    };
+
+    let passive_desc_section = TextSection {
+        value: format!("{}\n", character.passive.desc),
+        style: TextStyle {
+            font: font.clone(),
+            font_size: 20.0,
+            color: LIGHT_TEAL,
+        },
+    };
+
+    let ultimate_name_section = TextSection {
+        value: format!("{}: ", character.ultimate.name),
+        style: TextStyle {
+            font: bold_font,
+            font_size: 20.0,
+            color: LIGHT_TEAL,
+        },
+    };
+
+    let ultimate_desc_section = TextSection {
+        value: character.ultimate.desc.clone(),
+        style: TextStyle {
+            font,
+            font_size: 20.0,
+            color: LIGHT_TEAL,
+        },
+    };
+
+    TextBundle {
+        text: Text {
+            sections: Vec::from([
+                stats_section,
+                passive_name_section,
+                passive_desc_section,
+                ultimate_name_section,
+                ultimate_desc_section,
+            ]),
+            ..default()
+        },
+        style: Style {
+            margin: UiRect::top(Val::Px(20.)),
+            ..default()
+        },
+        ..Default::default()
+    }
+}
+
 fn despawn_select_scene(
     mut commands: Commands,
+    mut next_state: ResMut<NextState<CharacterSelectState>>,
     ui_query: Query<Entity, With<SelectMenuUi>>,
     // music_query: Query<Entity, With<MyMusic>>,
     // sb_query: Query<Entity, With<StartButtonUI>>,
@@ -323,6 +962,8 @@ fn despawn_select_scene(
     for ui in &ui_query {
         commands.entity(ui).despawn_recursive();
     }
+
+    next_state.set(CharacterSelectState::Unloaded);
 }
 
 fn character_select_system(
@@ -338,7 +979,8 @@ fn character_select_system(
     mut commands: Commands,
     music_query: Query<Entity, With<MyMusic>>,
     mut next_game_state: ResMut<NextState<GameState>>,
-    mut next_character_state: ResMut<NextState<SelectedCharacterState>>,
+    mut loading_scene: ResMut<LoadingScene>,
+    mut selected_character: ResMut<SelectedCharacter>,
     mut next_fade_state: ResMut<NextState<FadeState>>,
 ) {
     for (interaction, mut color, mut border_color, character) in &mut block_click_query {
@@ -349,26 +991,21 @@ fn character_select_system(
 
         match *interaction {
             Interaction::Pressed => {
-                println!(
-                    "Pressed character block: {:?}",
-                    character.selected_character_state
-                );
+                println!("Pressed character block: {:?}", character.id);
 
                 // *color = PRESSED_BUTTON_COLOR.into();
 
-                next_character_state.set(character.selected_character_state.clone());
+                selected_character.id = character.id.clone();
                 next_fade_state.set(FadeState::FadeToGame);
-                next_game_state.set(GameState::Corridor);
+                loading_scene.0 = GameState::Corridor;
+                next_game_state.set(GameState::Loading);
 
                 for music in &music_query {
                     commands.entity(music).despawn_recursive();
                 }
             }
             Interaction::Hovered => {
-                println!(
-                    "Hovered character block: {:?}",
-                    character.selected_character_state
-                );
+                println!("Hovered character block: {:?}", character.id);
 
                 *color = BLUE.into();
                 *border_color = BORDER_COLOR.into();
@@ -376,7 +1013,7 @@ fn character_select_system(
             Interaction::None => {
                 // println!(
                 //     "Back to default character block: {:?}",
-                //     character.selected_character_state
+                //     character.id
                 // );
 
                 *color = PURPLE.into();
@@ -386,6 +1023,147 @@ fn character_select_system(
     }
 }
 
+fn coop_toggle_system(
+    mut button_query: Query<
+        (&Interaction, &mut BorderColor),
+        (Changed<Interaction>, With<CoopToggleButton>),
+    >,
+    mut text_query: Query<&mut Text, With<CoopToggleText>>,
+    mut selected_character: ResMut<SelectedCharacter>,
+    input: Res<Input<KeyCode>>,
+) {
+    let mut toggled = input.just_pressed(COOP_TOGGLE_KEY);
+
+    for (interaction, mut border_color) in &mut button_query {
+        match *interaction {
+            Interaction::Pressed => toggled = true,
+            Interaction::Hovered => *border_color = BORDER_COLOR.into(),
+            Interaction::None => *border_color = BLUE.into(),
+        }
+    }
+
+    if !toggled {
+        return;
+    }
+
+    selected_character.coop = !selected_character.coop;
+    println!("Two player co-op: {}", selected_character.coop);
+
+    for mut text in &mut text_query {
+        text.sections[0].value = get_coop_toggle_label(selected_character.coop);
+    }
+}
+
+fn endless_toggle_system(
+    mut button_query: Query<
+        (&Interaction, &mut BorderColor),
+        (Changed<Interaction>, With<EndlessToggleButton>),
+    >,
+    mut text_query: Query<&mut Text, With<EndlessToggleText>>,
+    mut map_mode: ResMut<MapMode>,
+    input: Res<Input<KeyCode>>,
+) {
+    let mut toggled = input.just_pressed(ENDLESS_TOGGLE_KEY);
+
+    for (interaction, mut border_color) in &mut button_query {
+        match *interaction {
+            Interaction::Pressed => toggled = true,
+            Interaction::Hovered => *border_color = BORDER_COLOR.into(),
+            Interaction::None => *border_color = BLUE.into(),
+        }
+    }
+
+    if !toggled {
+        return;
+    }
+
+    *map_mode = match *map_mode {
+        MapMode::Arena => MapMode::Endless,
+        MapMode::Endless => MapMode::Arena,
+    };
+    println!("Map mode: {:?}", *map_mode);
+
+    for mut text in &mut text_query {
+        text.sections[0].value = get_endless_toggle_label(&map_mode);
+    }
+}
+
+fn damage_numbers_toggle_system(
+    mut button_query: Query<
+        (&Interaction, &mut BorderColor),
+        (Changed<Interaction>, With<DamageNumbersToggleButton>),
+    >,
+    mut text_query: Query<&mut Text, With<DamageNumbersToggleText>>,
+    mut damage_numbers: ResMut<DamageNumberSettings>,
+    input: Res<Input<KeyCode>>,
+) {
+    let mut toggled = input.just_pressed(DAMAGE_NUMBERS_TOGGLE_KEY);
+
+    for (interaction, mut border_color) in &mut button_query {
+        match *interaction {
+            Interaction::Pressed => toggled = true,
+            Interaction::Hovered => *border_color = BORDER_COLOR.into(),
+            Interaction::None => *border_color = BLUE.into(),
+        }
+    }
+
+    if !toggled {
+        return;
+    }
+
+    damage_numbers.enabled = !damage_numbers.enabled;
+    println!("Damage numbers: {}", damage_numbers.enabled);
+
+    for mut text in &mut text_query {
+        text.sections[0].value = get_damage_numbers_toggle_label(damage_numbers.enabled);
+    }
+}
+
+fn meta_upgrade_system(
+    mut button_query: Query<
+        (&Interaction, &mut BorderColor, &MetaUpgradeButton),
+        Changed<Interaction>,
+    >,
+    mut upgrade_text_query: Query<(&mut Text, &MetaUpgradeText), Without<MetaGoldText>>,
+    mut gold_text_query: Query<&mut Text, With<MetaGoldText>>,
+    mut meta: ResMut<MetaProgress>,
+    input: Res<Input<KeyCode>>,
+) {
+    let mut bought: Vec<MetaUpgrade> = MetaUpgrade::ALL
+        .into_iter()
+        .filter(|upgrade| input.just_pressed(upgrade.get_key()))
+        .collect();
+
+    for (interaction, mut border_color, button) in &mut button_query {
+        match *interaction {
+            Interaction::Pressed => bought.push(button.0),
+            Interaction::Hovered => *border_color = BORDER_COLOR.into(),
+            Interaction::None => *border_color = BLUE.into(),
+        }
+    }
+
+    let mut is_changed = false;
+
+    for upgrade in bought {
+        if meta.buy(upgrade) {
+            println!("Bought meta upgrade {:?}", upgrade);
+            is_changed = true;
+        }
+    }
+
+    if !is_changed {
+        return;
+    }
+
+    for (mut text, upgrade_text) in &mut upgrade_text_query {
+        text.sections[0].value = get_meta_upgrade_label(upgrade_text.0, &meta);
+    }
+
+    for mut text in &mut gold_text_query {
+        text.sections[0].value = get_meta_gold_label(&meta);
+    }
+}
+
 pub fn reset_camera(
     // query: Query<(&Transform) /*(With<Player>)*/>,
     mut camera_query: Query<&mut Transform, With<Camera> /*, Without<Player>*/>,
@@ -21,6 +21,15 @@ impl Plugin for CorridorPlugin {
     }
 }
 
+// Everything the corridor loads, for the loading screen to fetch up front. The map pulls in its
+// own tilesets.
+pub fn get_corridor_asset_paths() -> Vec<String> {
+    vec![
+        level::CORRIDOR_MAP_PATH.to_string(),
+        "fonts/patua_one/patuaone.ttf".to_string(),
+    ]
+}
+
 pub fn reset_camera(
     // query: Query<(&Transform) /*(With<Player>)*/>,
     mut camera_query: Query<&mut Transform, With<Camera> /*, Without<Player>*/>,
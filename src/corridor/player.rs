@@ -1,19 +1,18 @@
 use bevy::prelude::*;
 
 use crate::character_select::{
-    get_ailsa_character, get_character_sprite, get_lisa_character, CharacterBlock,
-    SelectedCharacterState, PLAYER_HEIGHT, PLAYER_WIDTH,
+    get_character_sprite, CharacterBlock, CharacterRegistry, SelectedCharacter, PLAYER_HEIGHT,
+    PLAYER_WIDTH,
 };
-use crate::corridor::level::{MAP_HEIGHT, MAP_VERTICAL_OFFSET};
 
 use crate::sprite::{
     AnimationIndices, AnimationTimer, Direction, Movable, PlayerSpriteSheetAnimatable,
 };
 
+use crate::sprite_sheets::SpriteSheets;
+use crate::tiled_map::{move_around_colliders, MapCollider};
 use crate::GameState;
 
-use super::level::MAP_WIDTH;
-
 #[derive(States, PartialEq, Eq, Default, Debug, Clone, Hash)]
 pub enum CorridorPlayerState {
     #[default]
@@ -22,8 +21,6 @@ pub enum CorridorPlayerState {
     Started,
 }
 
-pub const PLAYER_SPEED_DEFAULT: f32 = 100.;
-
 pub struct PlayerPlugin;
 
 #[derive(Component)]
@@ -87,6 +84,7 @@ pub fn player_movement(
         ),
         With<Player>,
     >,
+    collider_query: Query<&MapCollider>,
     input: Res<Input<KeyCode>>,
     time: Res<Time>,
     mut next_state: ResMut<NextState<CorridorPlayerState>>,
@@ -106,6 +104,7 @@ pub fn player_movement(
 
     let old_direction = movable.direction.clone();
     let old_is_moving = movable.is_moving.clone();
+    let old_translation = transform.translation;
 
     // Top and bottom with checks for diagonal.
     if input.pressed(KeyCode::W) {
@@ -153,14 +152,12 @@ pub fn player_movement(
         movable.direction = Direction::Right;
     }
 
-    transform.translation.x = transform.translation.x.clamp(
-        -1. * (MAP_WIDTH / 2.) + PLAYER_WIDTH / 2.,
-        MAP_WIDTH / 2. - PLAYER_WIDTH / 2.,
-    );
-
-    transform.translation.y = transform.translation.y.clamp(
-        -1. * (MAP_VERTICAL_OFFSET) + PLAYER_HEIGHT / 2.,
-        MAP_HEIGHT - MAP_VERTICAL_OFFSET - PLAYER_HEIGHT / 2.,
+    // Walls come from the map's collision layer.
+    move_around_colliders(
+        &collider_query,
+        old_translation,
+        &mut transform.translation,
+        Vec2::new(PLAYER_WIDTH, PLAYER_HEIGHT),
     );
 
     movable.is_moving = key_pressed;
@@ -239,63 +236,47 @@ fn animate_sprite(
     }
 }
 
+// None if the roster is empty, in which case the select screen has already said so.
 pub fn get_character_block(
-    state: &SelectedCharacterState,
-) -> (CharacterBlock, PlayerSpriteSheetAnimatable) {
-    let character: CharacterBlock;
+    registry: &CharacterRegistry,
+    id: &str,
+) -> Option<(CharacterBlock, PlayerSpriteSheetAnimatable)> {
+    let character = registry.get(id)?.clone();
+    let animatable = get_character_animatable(&character);
 
-    if state == &SelectedCharacterState::Ailsa {
-        character = get_ailsa_character();
-    } else {
-        character = get_lisa_character();
-    }
+    Some((character, animatable))
+}
 
+pub fn get_character_animatable(character: &CharacterBlock) -> PlayerSpriteSheetAnimatable {
     // Use only the subset of sprites in the sheet that make up the run animation
-    let idle_animation_indices = AnimationIndices { first: 0, last: 1 };
-    let run_down_animation_indices = AnimationIndices { first: 9, last: 17 };
-    let run_horizontal_animation_indices = AnimationIndices {
-        first: 27,
-        last: 35,
-    };
-    let run_up_animation_indices = AnimationIndices {
-        first: 45,
-        last: 53,
-    };
-
-    let run_up_horiz_animation_indices = AnimationIndices {
-        first: 36,
-        last: 44,
-    };
-    let run_down_horiz_animation_indices = AnimationIndices {
-        first: 18,
-        last: 26,
-    };
-
-    let animatable: PlayerSpriteSheetAnimatable = PlayerSpriteSheetAnimatable {
-        idle_anim_indices: idle_animation_indices,
-        moving_horizontal_anim_indices: run_horizontal_animation_indices,
-        moving_up_anim_indices: run_up_animation_indices,
-        moving_down_anim_indices: run_down_animation_indices,
-        moving_up_horiz_anim_indices: run_up_horiz_animation_indices,
-        moving_down_horiz_anim_indices: run_down_horiz_animation_indices,
-    };
-
-    (character, animatable)
+    let animations = &character.animations;
+
+    PlayerSpriteSheetAnimatable {
+        idle_anim_indices: animations.idle,
+        moving_horizontal_anim_indices: animations.run_horizontal,
+        moving_up_anim_indices: animations.run_up,
+        moving_down_anim_indices: animations.run_down,
+        moving_up_horiz_anim_indices: animations.run_up_horiz,
+        moving_down_horiz_anim_indices: animations.run_down_horiz,
+    }
 }
 
 fn setup(
     mut commands: Commands,
-    mut assets: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut sheets: SpriteSheets,
     mut next_state: ResMut<NextState<CorridorPlayerState>>,
-    state: Res<State<SelectedCharacterState>>,
+    selected_character: Res<SelectedCharacter>,
+    registry: Res<CharacterRegistry>,
     // mut meshes: ResMut<Assets<Mesh>>,
     // mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
     println!("Spawning corridor player plugin");
 
-    let (character, animatable) = get_character_block(state.get());
-    let texture_atlas_handle = get_character_sprite(&character, &mut texture_atlases, &mut assets);
+    let Some((character, animatable)) = get_character_block(&registry, &selected_character.id)
+    else {
+        return;
+    };
+    let texture_atlas_handle = get_character_sprite(&character, &mut sheets);
 
     let idle_anims = animatable.idle_anim_indices.clone();
 
@@ -317,7 +298,7 @@ fn setup(
         Player,
         animatable,
         Movable {
-            speed: PLAYER_SPEED_DEFAULT,
+            speed: character.stats.speed,
             direction: Direction::Down,
             is_moving: false,
             current_animation_indices: idle_anims,
@@ -0,0 +1,247 @@
+use bevy::prelude::*;
+
+use crate::character_select::{CharacterUltimate, UltimateEffect};
+use crate::sprite::{get_translation_for_direction, Health, Movable};
+
+use super::controls::{PlayerControls, PlayerInput};
+use super::level::MapMode;
+use super::obstacles::ObstacleMap;
+use super::player::{clamp_to_movable_area, move_around_obstacles, Downed, Invulnerable, Player};
+use super::pools::EntityPools;
+use super::projectile_spawner::{
+    spawn_scaled_explosion_at_position, DamageEvent, EnemyKilledEvent,
+};
+use super::spawner::Enemy;
+use super::stats::{PlayerStats, Stat, StatModifier};
+use super::GamePlayState;
+
+pub struct AbilitiesPlugin;
+
+const DASH_COOLDOWN: f32 = 3.;
+const DASH_DURATION: f32 = 0.2;
+const DASH_SPEED: f32 = 450.;
+const DASH_INVULNERABLE_SECONDS: f32 = 0.35;
+
+// Weapons fire this much faster for as long as a Blessing's invulnerability lasts.
+const BLESSING_COOLDOWN: f32 = 0.7;
+
+impl Plugin for AbilitiesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                trigger_dash,
+                update_dash.after(trigger_dash),
+                charge_ultimate,
+                trigger_ultimate,
+            )
+                .run_if(in_state(GamePlayState::Started).or_else(in_state(GamePlayState::Boss))),
+        );
+    }
+}
+
+#[derive(Debug, Component)]
+pub struct Dash {
+    pub cooldown_timer: Timer,
+    pub dash_timer: Timer,
+    pub direction: Vec3,
+}
+
+impl Dash {
+    pub fn new() -> Self {
+        // Both timers start finished so the dash is available straight away.
+        let mut cooldown_timer = Timer::from_seconds(DASH_COOLDOWN, TimerMode::Once);
+        cooldown_timer.tick(cooldown_timer.duration());
+
+        let mut dash_timer = Timer::from_seconds(DASH_DURATION, TimerMode::Once);
+        dash_timer.tick(dash_timer.duration());
+
+        Self {
+            cooldown_timer,
+            dash_timer,
+            direction: Vec3::ZERO,
+        }
+    }
+
+    pub fn is_dashing(&self) -> bool {
+        !self.dash_timer.finished()
+    }
+}
+
+#[derive(Debug, Component)]
+pub struct Ultimate {
+    pub ultimate: CharacterUltimate,
+    pub charge: u32,
+}
+
+impl Ultimate {
+    pub fn new(ultimate: CharacterUltimate) -> Self {
+        Self {
+            ultimate,
+            charge: 0,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.charge >= self.ultimate.kills_required
+    }
+
+    pub fn percent(&self) -> f32 {
+        if self.ultimate.kills_required == 0 {
+            return 1.;
+        }
+
+        (self.charge as f32 / self.ultimate.kills_required as f32).min(1.)
+    }
+}
+
+fn trigger_dash(
+    mut commands: Commands,
+    mut player_query: Query<
+        (&mut Dash, &Movable, &PlayerControls, Entity),
+        (With<Player>, Without<Downed>),
+    >,
+    input: PlayerInput,
+) {
+    for (mut dash, movable, controls, entity) in player_query.iter_mut() {
+        if !input.dash_just_pressed(controls) {
+            continue;
+        }
+
+        if !dash.cooldown_timer.finished() || dash.is_dashing() {
+            continue;
+        }
+
+        println!("Player dashed");
+
+        let direction = get_translation_for_direction(movable.direction, 0.);
+        dash.direction = Vec3::new(direction.x, direction.y, 0.).normalize_or_zero();
+        dash.dash_timer.reset();
+        dash.cooldown_timer.reset();
+
+        commands
+            .entity(entity)
+            .insert(Invulnerable::new(DASH_INVULNERABLE_SECONDS));
+    }
+}
+
+fn update_dash(
+    mut player_query: Query<(&mut Dash, &mut Transform, &mut TextureAtlasSprite), With<Player>>,
+    obstacle_map: Res<ObstacleMap>,
+    map_mode: Res<MapMode>,
+    time: Res<Time>,
+) {
+    for (mut dash, mut transform, mut sprite) in player_query.iter_mut() {
+        dash.cooldown_timer.tick(time.delta());
+
+        if !dash.is_dashing() {
+            continue;
+        }
+
+        dash.dash_timer.tick(time.delta());
+
+        let old_translation = transform.translation;
+        transform.translation += dash.direction * DASH_SPEED * time.delta_seconds();
+        transform.translation.z = old_translation.z;
+        move_around_obstacles(&obstacle_map, old_translation, &mut transform.translation);
+        clamp_to_movable_area(&mut transform.translation, &map_mode);
+
+        // Ghost the sprite for the length of the dash
+        sprite
+            .color
+            .set_a(if dash.is_dashing() { 0.5 } else { 1. });
+    }
+}
+
+fn charge_ultimate(
+    mut enemy_killed_events: EventReader<EnemyKilledEvent>,
+    mut player_query: Query<&mut Ultimate, With<Player>>,
+) {
+    let kills = enemy_killed_events.read().count() as u32;
+
+    if kills == 0 {
+        return;
+    }
+
+    for mut ultimate in player_query.iter_mut() {
+        ultimate.charge = (ultimate.charge + kills).min(ultimate.ultimate.kills_required);
+    }
+}
+
+fn trigger_ultimate(
+    mut commands: Commands,
+    mut pools: ResMut<EntityPools>,
+    mut player_query: Query<
+        (
+            &mut Ultimate,
+            &mut Health,
+            &mut PlayerStats,
+            &Transform,
+            &PlayerControls,
+            Entity,
+        ),
+        (With<Player>, Without<Enemy>, Without<Downed>),
+    >,
+    enemy_query: Query<(&Transform, Entity), (With<Enemy>, Without<Player>)>,
+    mut damage_event_writer: EventWriter<DamageEvent>,
+    input: PlayerInput,
+) {
+    for (mut ultimate, mut health, mut player_stats, player_transform, controls, entity) in
+        player_query.iter_mut()
+    {
+        if !input.ultimate_just_pressed(controls) || !ultimate.is_ready() {
+            continue;
+        }
+
+        println!("Player used ultimate {}", ultimate.ultimate.name);
+
+        match ultimate.ultimate.effect {
+            UltimateEffect::Shockwave {
+                damage,
+                radius,
+                damage_type,
+            } => {
+                for (enemy_transform, enemy) in enemy_query.iter() {
+                    if enemy_transform
+                        .translation
+                        .truncate()
+                        .distance(player_transform.translation.truncate())
+                        < radius
+                    {
+                        damage_event_writer.send(DamageEvent {
+                            damage: player_stats.get(Stat::Damage, damage),
+                            entity: enemy,
+                            damage_type,
+                            is_critical: false,
+                            knockback: Vec3::ZERO,
+                        });
+                    }
+                }
+
+                // Explosion sheet frames are 32px wide, so scale it up to roughly cover the radius.
+                spawn_scaled_explosion_at_position(
+                    &mut pools,
+                    &mut commands,
+                    &player_transform.translation,
+                    radius / 16.,
+                );
+            }
+            UltimateEffect::Blessing {
+                heal,
+                invulnerable_seconds,
+            } => {
+                health.heal(heal);
+                player_stats.add_modifier(
+                    StatModifier::multiply(Stat::Cooldown, BLESSING_COOLDOWN)
+                        .lasting(invulnerable_seconds),
+                );
+
+                commands
+                    .entity(entity)
+                    .insert(Invulnerable::new(invulnerable_seconds));
+            }
+        }
+
+        ultimate.charge = 0;
+    }
+}
@@ -0,0 +1,235 @@
+use bevy::prelude::*;
+
+use super::level::FloorTileset;
+use super::projectile_spawner::EnemyKilledEvent;
+use super::spawner::{EnemyKind, EnemyRank};
+use crate::meta::MetaProgress;
+
+// Each biome is a separate stage picked from the doors in the corridor, with its own floor,
+// music, enemies and boss. Beating a biome's boss opens the door to the next one, which is saved
+// along with the rest of MetaProgress.
+pub struct BiomesPlugin;
+
+impl Plugin for BiomesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SelectedBiome>()
+            .add_systems(Update, unlock_next_biome);
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Biome {
+    #[default]
+    Meadow,
+    Forest,
+    Dungeon,
+}
+
+// One step of a biome's wave schedule. Every wave while it lasts spawns `count` of `enemy`.
+#[derive(Debug, Clone, Copy)]
+pub struct BiomeWave {
+    pub enemy: EnemyKind,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BiomeBoss {
+    pub enemy: EnemyKind,
+    pub health: f32,
+    pub scale: f32,
+    pub color: Color,
+}
+
+impl Biome {
+    // In unlock order.
+    pub const ALL: [Biome; 3] = [Biome::Meadow, Biome::Forest, Biome::Dungeon];
+
+    // Matches the door names in the corridor map.
+    pub fn get_id(&self) -> &'static str {
+        match self {
+            Biome::Meadow => "meadow",
+            Biome::Forest => "forest",
+            Biome::Dungeon => "dungeon",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Biome> {
+        Biome::ALL.into_iter().find(|biome| biome.get_id() == id)
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Biome::Meadow => "Meadow",
+            Biome::Forest => "Deep Forest",
+            Biome::Dungeon => "Dungeon",
+        }
+    }
+
+    pub fn get_index(&self) -> usize {
+        Biome::ALL
+            .iter()
+            .position(|biome| biome == self)
+            .unwrap_or(0)
+    }
+
+    pub fn get_floor(&self) -> FloorTileset {
+        match self {
+            Biome::Meadow => FloorTileset {
+                path: "sprites/level/tx_tileset_grass.png",
+                size: Vec2::new(256., 256.),
+                tile_size: 32.,
+                // Only the plain grass rows at the top of the sheet.
+                cells: (0..4)
+                    .flat_map(|y| (0..8).map(move |x| UVec2::new(x, y)))
+                    .collect(),
+            },
+            Biome::Forest => FloorTileset {
+                path: "sprites/level/Forest Tilesett.png",
+                size: Vec2::new(176., 320.),
+                tile_size: 16.,
+                // The solid middle of the dark green patch.
+                cells: vec![UVec2::new(1, 11)],
+            },
+            Biome::Dungeon => FloorTileset {
+                path: "sprites/level/dungeon-floor.png",
+                size: Vec2::new(64., 48.),
+                tile_size: 16.,
+                cells: (0..3)
+                    .flat_map(|y| (0..4).map(move |x| UVec2::new(x, y)))
+                    .collect(),
+            },
+        }
+    }
+
+    // Tiled map laid over the floor for this biome's arena, with walls, props and spawn points.
+    // Biomes without one are just the floor and its random obstacles.
+    pub fn get_map_path(&self) -> Option<&'static str> {
+        match self {
+            Biome::Meadow => Some("maps/meadow.tmj"),
+            Biome::Forest => None,
+            Biome::Dungeon => Some("maps/dungeon.tmj"),
+        }
+    }
+
+    // Only the ogg tracks - the mp3s in the music folder need bevy's mp3 feature.
+    pub fn get_music(&self) -> &'static str {
+        match self {
+            Biome::Meadow => "music/gameplay.ogg",
+            Biome::Forest => "music/ending.ogg",
+            Biome::Dungeon => "music/menu.ogg",
+        }
+    }
+
+    // Most obstacle clusters a single floor chunk can roll. No trees in the dungeon.
+    pub fn get_obstacle_clusters_max(&self) -> usize {
+        match self {
+            Biome::Meadow => 4,
+            Biome::Forest => 7,
+            Biome::Dungeon => 0,
+        }
+    }
+
+    pub fn get_wave_seconds(&self) -> f32 {
+        match self {
+            Biome::Meadow => 10.,
+            Biome::Forest => 9.,
+            Biome::Dungeon => 8.,
+        }
+    }
+
+    pub fn get_stage_seconds(&self) -> f32 {
+        match self {
+            Biome::Meadow => 15.,
+            Biome::Forest => 18.,
+            Biome::Dungeon => 20.,
+        }
+    }
+
+    // Chance of each wave enemy rolling into an elite, from the second stage on.
+    pub fn get_elite_chance(&self) -> f64 {
+        match self {
+            Biome::Meadow => 0.01,
+            Biome::Forest => 0.015,
+            Biome::Dungeon => 0.02,
+        }
+    }
+
+    pub fn get_waves(&self) -> Vec<BiomeWave> {
+        let wave = |enemy: EnemyKind, count: usize| BiomeWave { enemy, count };
+
+        match self {
+            Biome::Meadow => vec![
+                wave(EnemyKind::Goblin, 10),
+                wave(EnemyKind::BrownMushroom, 20),
+                wave(EnemyKind::Slime, 30),
+                wave(EnemyKind::Bat, 40),
+                wave(EnemyKind::BlueMushroom, 50),
+            ],
+            Biome::Forest => vec![
+                wave(EnemyKind::BrownMushroom, 15),
+                wave(EnemyKind::Goblin, 25),
+                wave(EnemyKind::BlueMushroom, 35),
+                wave(EnemyKind::Goblin, 45),
+                wave(EnemyKind::BlueMushroom, 55),
+            ],
+            Biome::Dungeon => vec![
+                wave(EnemyKind::Bat, 20),
+                wave(EnemyKind::Slime, 30),
+                wave(EnemyKind::Basic, 40),
+                wave(EnemyKind::Bat, 50),
+                wave(EnemyKind::Slime, 60),
+                wave(EnemyKind::Basic, 70),
+            ],
+        }
+    }
+
+    pub fn get_boss(&self) -> BiomeBoss {
+        match self {
+            Biome::Meadow => BiomeBoss {
+                enemy: EnemyKind::Boss,
+                health: 200.,
+                scale: 1.,
+                color: Color::WHITE,
+            },
+            Biome::Forest => BiomeBoss {
+                enemy: EnemyKind::Goblin,
+                health: 350.,
+                scale: 4.,
+                color: Color::rgb(0.6, 0.9, 0.5),
+            },
+            Biome::Dungeon => BiomeBoss {
+                enemy: EnemyKind::Slime,
+                health: 500.,
+                scale: 5.,
+                color: Color::rgb(0.9, 0.5, 0.9),
+            },
+        }
+    }
+
+    // Same enemy as the boss, smaller and weaker, halfway through the waves.
+    pub fn get_mid_boss(&self) -> BiomeBoss {
+        let boss = self.get_boss();
+
+        BiomeBoss {
+            health: boss.health * 0.4,
+            scale: (boss.scale * 0.6).max(1.5),
+            ..boss
+        }
+    }
+}
+
+// Biome for the current run, set by the corridor door the player walks through.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct SelectedBiome(pub Biome);
+
+fn unlock_next_biome(
+    mut enemy_killed_events: EventReader<EnemyKilledEvent>,
+    selected_biome: Res<SelectedBiome>,
+    mut progress: ResMut<MetaProgress>,
+) {
+    for event in enemy_killed_events.read() {
+        if event.rank == EnemyRank::Boss {
+            progress.unlock_biome_after(selected_biome.0);
+        }
+    }
+}
@@ -0,0 +1,448 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::character_select::CharacterStats;
+use crate::main_menu::{BLUE, DARK_PURPLE, LIGHT_BLUE, LIGHT_TEAL, WHITE};
+use crate::sprite::{AnimationTimer, Health, Movable};
+use crate::GameState;
+
+use super::lvl_up_ui::{
+    get_dialog_container, get_lvl_up_container, get_small_title, get_upgrades_container,
+    PendingLevelUps,
+};
+use super::player::{Downed, Player};
+use super::projectile_spawner::EnemyKilledEvent;
+use super::stats::PlayerStats;
+use super::weapons::{
+    add_player_weapon, get_available_weapons, get_weapon_evolution, StatUpgrade, Weapon,
+};
+use super::GamePlayState;
+
+// Chests dropped by elites and mid-bosses. Walking into one pauses the fight, plays the opening
+// animation and then hands out a few random upgrades on a reveal screen.
+pub struct ChestsPlugin;
+
+impl Plugin for ChestsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GamePlayState::Init), setup)
+            .add_systems(
+                Update,
+                (spawn_chests, open_touched_chest).run_if(
+                    in_state(GamePlayState::Started).or_else(in_state(GamePlayState::Boss)),
+                ),
+            )
+            .add_systems(
+                Update,
+                (animate_opening_chest, close_chest_reveal).run_if(in_state(GamePlayState::Chest)),
+            )
+            .add_systems(OnEnter(GamePlayState::Restart), unload)
+            .add_systems(OnExit(GameState::Gameplay), unload);
+    }
+}
+
+const CHEST_FRAME_COUNT: usize = 8;
+const CHEST_FRAME_SECONDS: f32 = 0.12;
+const CHEST_SCALE: f32 = 1.5;
+// Under the enemies, so a chest never hides something about to bite.
+const CHEST_Z: f32 = 1.5;
+const CHEST_PICKUP_DISTANCE: f32 = 16.;
+
+const WEAPON_REWARD_CHANCE: f64 = 0.4;
+const CLOSE_REVEAL_KEY: KeyCode = KeyCode::Return;
+
+// Put on an enemy that leaves a chest behind, with the number of rewards inside.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct DropsChest {
+    pub rewards: usize,
+}
+
+impl DropsChest {
+    // Mostly a single reward, sometimes three.
+    pub fn elite() -> Self {
+        Self {
+            rewards: if rand::thread_rng().gen_bool(0.2) {
+                3
+            } else {
+                1
+            },
+        }
+    }
+
+    pub fn mid_boss() -> Self {
+        Self {
+            rewards: if rand::thread_rng().gen_bool(0.3) {
+                5
+            } else {
+                3
+            },
+        }
+    }
+}
+
+#[derive(Component, Debug)]
+struct Chest {
+    rewards: usize,
+    frame: usize,
+}
+
+// The animation frames are separate images rather than one sheet.
+#[derive(Resource, Debug)]
+struct ChestFrames(Vec<Handle<Image>>);
+
+// Chest being opened while gameplay waits in GamePlayState::Chest.
+#[derive(Resource, Debug)]
+struct OpeningChest {
+    chest: Entity,
+    player: Entity,
+    resume_state: GamePlayState,
+    is_revealed: bool,
+}
+
+#[derive(Debug, Clone)]
+enum ChestReward {
+    Weapon(Box<Weapon>),
+    Stat(StatUpgrade),
+}
+
+#[derive(Component)]
+struct ChestRevealUi;
+
+#[derive(Component)]
+struct ChestContinueButton;
+
+pub fn get_chest_frame_paths() -> Vec<String> {
+    (0..CHEST_FRAME_COUNT)
+        .map(|frame| format!("sprites/items/chest_closed_anim_f{}.png", frame))
+        .collect()
+}
+
+fn setup(mut commands: Commands, assets: Res<AssetServer>) {
+    let frames = get_chest_frame_paths()
+        .into_iter()
+        .map(|path| assets.load(path))
+        .collect();
+
+    commands.insert_resource(ChestFrames(frames));
+}
+
+fn unload(
+    mut commands: Commands,
+    chest_query: Query<Entity, With<Chest>>,
+    ui_query: Query<Entity, With<ChestRevealUi>>,
+) {
+    for entity in chest_query.iter().chain(ui_query.iter()) {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    commands.remove_resource::<OpeningChest>();
+}
+
+fn spawn_chests(
+    mut commands: Commands,
+    mut enemy_killed_events: EventReader<EnemyKilledEvent>,
+    frames: Res<ChestFrames>,
+) {
+    for event in enemy_killed_events.read() {
+        let Some(drops_chest) = event.chest else {
+            continue;
+        };
+
+        commands.spawn((
+            SpriteBundle {
+                texture: frames.0[0].clone(),
+                transform: Transform::from_translation(event.position.truncate().extend(CHEST_Z))
+                    .with_scale(Vec3::new(CHEST_SCALE, CHEST_SCALE, 1.)),
+                ..default()
+            },
+            Chest {
+                rewards: drops_chest.rewards,
+                frame: 0,
+            },
+            AnimationTimer(Timer::from_seconds(
+                CHEST_FRAME_SECONDS,
+                TimerMode::Repeating,
+            )),
+            Name::new("Chest"),
+        ));
+    }
+}
+
+fn open_touched_chest(
+    mut commands: Commands,
+    chest_query: Query<(&Transform, Entity), With<Chest>>,
+    player_query: Query<(&Transform, Entity), (With<Player>, Without<Downed>)>,
+    pending_level_ups: Res<PendingLevelUps>,
+    state: Res<State<GamePlayState>>,
+    mut next_play_state: ResMut<NextState<GamePlayState>>,
+) {
+    // A level up picked up on the same frame goes first, the chest will still be there after.
+    if !pending_level_ups.players.is_empty() {
+        return;
+    }
+
+    for (chest_transform, chest) in chest_query.iter() {
+        let opener = player_query.iter().find(|(player_transform, _)| {
+            player_transform
+                .translation
+                .truncate()
+                .distance(chest_transform.translation.truncate())
+                < CHEST_PICKUP_DISTANCE
+        });
+
+        if let Some((_, player)) = opener {
+            println!("Chest opened");
+            commands.insert_resource(OpeningChest {
+                chest,
+                player,
+                resume_state: state.get().clone(),
+                is_revealed: false,
+            });
+            next_play_state.set(GamePlayState::Chest);
+            return;
+        }
+    }
+}
+
+// Steps through the chest frames, then rolls and applies the rewards and shows what came out.
+fn animate_opening_chest(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    time: Res<Time>,
+    frames: Res<ChestFrames>,
+    opening_chest: Option<ResMut<OpeningChest>>,
+    mut chest_query: Query<(&mut Chest, &mut Handle<Image>, &mut AnimationTimer)>,
+    mut player_query: Query<
+        (
+            &mut Player,
+            &mut PlayerStats,
+            &mut CharacterStats,
+            &mut Health,
+            &mut Movable,
+            &Name,
+        ),
+        Without<Chest>,
+    >,
+) {
+    let Some(mut opening_chest) = opening_chest else {
+        return;
+    };
+
+    if opening_chest.is_revealed {
+        return;
+    }
+
+    let Ok((mut chest, mut texture, mut timer)) = chest_query.get_mut(opening_chest.chest) else {
+        return;
+    };
+
+    timer.tick(time.delta());
+
+    if !timer.just_finished() {
+        return;
+    }
+
+    if chest.frame + 1 < CHEST_FRAME_COUNT {
+        chest.frame += 1;
+        *texture = frames.0[chest.frame].clone();
+        return;
+    }
+
+    opening_chest.is_revealed = true;
+
+    let player_count = player_query.iter().count();
+
+    let Ok((mut player, mut player_stats, mut stats, mut health, mut movable, name)) =
+        player_query.get_mut(opening_chest.player)
+    else {
+        return;
+    };
+
+    let rewards = roll_chest_rewards(&player, chest.rewards);
+
+    for reward in rewards.iter() {
+        match reward {
+            ChestReward::Weapon(weapon) => add_player_weapon(&mut player.weapons, *weapon.clone()),
+            ChestReward::Stat(upgrade) => {
+                upgrade.apply(&mut player_stats, &mut stats, &mut health, &mut movable);
+                player.passives.push(*upgrade);
+            }
+        }
+    }
+
+    let who = if player_count > 1 {
+        name.as_str()
+    } else {
+        "You"
+    };
+
+    spawn_chest_reveal_ui(&mut commands, &assets, who, &rewards);
+}
+
+// A weapon ready to evolve always comes first. The rest are new weapons or weapon levels, or stat
+// upgrades, which can come up more than once.
+fn roll_chest_rewards(player: &Player, count: usize) -> Vec<ChestReward> {
+    let mut rng = rand::thread_rng();
+    let mut weapons = get_available_weapons(&player.weapons, count);
+    let mut rewards: Vec<ChestReward> = get_weapon_evolution(&player.weapons, &player.passives)
+        .map(|weapon| ChestReward::Weapon(Box::new(weapon)))
+        .into_iter()
+        .collect();
+
+    while rewards.len() < count {
+        if !weapons.is_empty() && rng.gen_bool(WEAPON_REWARD_CHANCE) {
+            rewards.push(ChestReward::Weapon(Box::new(weapons.remove(0))));
+        } else {
+            rewards.push(ChestReward::Stat(
+                StatUpgrade::ALL[rng.gen_range(0..StatUpgrade::ALL.len())],
+            ));
+        }
+    }
+
+    rewards
+}
+
+fn close_chest_reveal(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    opening_chest: Option<Res<OpeningChest>>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<ChestContinueButton>),
+    >,
+    ui_query: Query<Entity, With<ChestRevealUi>>,
+    mut next_play_state: ResMut<NextState<GamePlayState>>,
+) {
+    let Some(opening_chest) = opening_chest else {
+        return;
+    };
+
+    if !opening_chest.is_revealed {
+        return;
+    }
+
+    let mut is_closed = keys.just_pressed(CLOSE_REVEAL_KEY);
+
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => is_closed = true,
+            Interaction::Hovered => *color = DARK_PURPLE.into(),
+            Interaction::None => *color = BLUE.into(),
+        }
+    }
+
+    if !is_closed {
+        return;
+    }
+
+    for ui in &ui_query {
+        commands.entity(ui).despawn_recursive();
+    }
+
+    commands.entity(opening_chest.chest).despawn_recursive();
+    next_play_state.set(opening_chest.resume_state.clone());
+    commands.remove_resource::<OpeningChest>();
+}
+
+// Same look as a level up weapon button, just not clickable.
+fn spawn_reward_card(commands: &mut ChildBuilder, reward: &ChestReward, font: &Handle<Font>) {
+    let (title, desc) = match reward {
+        // Shows the level or evolution, like the level up screen.
+        ChestReward::Weapon(weapon) => (weapon.get_title(), weapon.desc.clone()),
+        ChestReward::Stat(upgrade) => (
+            upgrade.get_name().to_string(),
+            upgrade.get_desc().to_string(),
+        ),
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(10.),
+                    padding: UiRect::px(15., 15., 10., 15.),
+                    border: UiRect::all(Val::Px(1.)),
+                    ..default()
+                },
+                background_color: LIGHT_BLUE.into(),
+                border_color: DARK_PURPLE.into(),
+                ..default()
+            },
+            Name::new(title.clone()),
+        ))
+        .with_children(|commands| {
+            commands.spawn(TextBundle::from_section(
+                title,
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 24.0,
+                    color: LIGHT_TEAL,
+                },
+            ));
+            commands.spawn(TextBundle::from_section(
+                desc,
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 18.0,
+                    color: WHITE,
+                },
+            ));
+        });
+}
+
+fn spawn_chest_reveal_ui(
+    commands: &mut Commands,
+    assets: &Res<AssetServer>,
+    who: &str,
+    rewards: &[ChestReward],
+) {
+    let font = assets.load("fonts/patua_one/patuaone.ttf");
+
+    commands
+        .spawn((get_lvl_up_container(), ChestRevealUi))
+        .with_children(|commands| {
+            commands
+                .spawn(get_dialog_container())
+                .with_children(|commands| {
+                    commands.spawn(TextBundle::from_section(
+                        format!("{} found a treasure chest!", who),
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 38.0,
+                            color: LIGHT_TEAL,
+                        },
+                    ));
+                    commands.spawn(get_small_title("Inside were...".into(), assets));
+                    commands
+                        .spawn(get_upgrades_container())
+                        .with_children(|commands| {
+                            for reward in rewards {
+                                spawn_reward_card(commands, reward, &font);
+                            }
+                        });
+                    commands
+                        .spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    padding: UiRect::px(30., 30., 10., 10.),
+                                    ..default()
+                                },
+                                background_color: BLUE.into(),
+                                ..default()
+                            },
+                            ChestContinueButton,
+                        ))
+                        .with_children(|commands| {
+                            commands.spawn(TextBundle::from_section(
+                                "Continue",
+                                TextStyle {
+                                    font: font.clone(),
+                                    font_size: 24.0,
+                                    color: WHITE,
+                                },
+                            ));
+                        });
+                });
+        });
+}
@@ -0,0 +1,139 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+// Stick has to be pushed at least this far before it counts as a direction, since sprites only
+// face eight ways.
+const GAMEPAD_STICK_THRESHOLD: f32 = 0.5;
+
+// Keys for one player. Player one always gets WASD, player two the arrow keys on the other side
+// of the keyboard, and whoever is set up for it also reads the first connected gamepad.
+#[derive(Debug, Clone, Component)]
+pub struct PlayerControls {
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub dash: KeyCode,
+    pub ultimate: KeyCode,
+    pub gamepad: Option<usize>,
+}
+
+impl PlayerControls {
+    pub fn for_player(index: usize, player_count: usize) -> Self {
+        if index == 0 {
+            Self {
+                up: KeyCode::W,
+                down: KeyCode::S,
+                left: KeyCode::A,
+                right: KeyCode::D,
+                dash: KeyCode::Space,
+                ultimate: KeyCode::Q,
+                // A lone player can use the pad too, otherwise it belongs to player two.
+                gamepad: if player_count == 1 { Some(0) } else { None },
+            }
+        } else {
+            Self {
+                up: KeyCode::Up,
+                down: KeyCode::Down,
+                left: KeyCode::Left,
+                right: KeyCode::Right,
+                dash: KeyCode::ShiftRight,
+                ultimate: KeyCode::ControlRight,
+                gamepad: Some(index - 1),
+            }
+        }
+    }
+
+    pub fn get_dash_label(&self) -> String {
+        get_key_label(self.dash, self.gamepad.is_some(), "A")
+    }
+
+    pub fn get_ultimate_label(&self) -> String {
+        get_key_label(self.ultimate, self.gamepad.is_some(), "B")
+    }
+}
+
+fn get_key_label(key: KeyCode, has_gamepad: bool, button: &str) -> String {
+    let key = match key {
+        KeyCode::ShiftRight => "R Shift".to_string(),
+        KeyCode::ControlRight => "R Ctrl".to_string(),
+        other => format!("{:?}", other),
+    };
+
+    if has_gamepad {
+        format!("{} / {}", key, button)
+    } else {
+        key
+    }
+}
+
+// Keyboard and gamepad state bundled up, so systems can ask what a given player is pressing.
+#[derive(SystemParam)]
+pub struct PlayerInput<'w> {
+    keys: Res<'w, Input<KeyCode>>,
+    gamepads: Res<'w, Gamepads>,
+    buttons: Res<'w, Input<GamepadButton>>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+}
+
+impl<'w> PlayerInput<'w> {
+    fn get_gamepad(&self, controls: &PlayerControls) -> Option<Gamepad> {
+        // Gamepads are kept in a hash map, so sort them to keep the order stable.
+        let mut gamepads: Vec<Gamepad> = self.gamepads.iter().collect();
+        gamepads.sort_by_key(|gamepad| gamepad.id);
+
+        gamepads.get(controls.gamepad?).copied()
+    }
+
+    fn gamepad_pressed(&self, controls: &PlayerControls, button: GamepadButtonType) -> bool {
+        self.get_gamepad(controls)
+            .is_some_and(|gamepad| self.buttons.pressed(GamepadButton::new(gamepad, button)))
+    }
+
+    fn gamepad_just_pressed(&self, controls: &PlayerControls, button: GamepadButtonType) -> bool {
+        self.get_gamepad(controls).is_some_and(|gamepad| {
+            self.buttons
+                .just_pressed(GamepadButton::new(gamepad, button))
+        })
+    }
+
+    fn gamepad_axis(&self, controls: &PlayerControls, axis: GamepadAxisType) -> f32 {
+        self.get_gamepad(controls)
+            .and_then(|gamepad| self.axes.get(GamepadAxis::new(gamepad, axis)))
+            .unwrap_or(0.)
+    }
+
+    pub fn up(&self, controls: &PlayerControls) -> bool {
+        self.keys.pressed(controls.up)
+            || self.gamepad_pressed(controls, GamepadButtonType::DPadUp)
+            || self.gamepad_axis(controls, GamepadAxisType::LeftStickY) > GAMEPAD_STICK_THRESHOLD
+    }
+
+    pub fn down(&self, controls: &PlayerControls) -> bool {
+        self.keys.pressed(controls.down)
+            || self.gamepad_pressed(controls, GamepadButtonType::DPadDown)
+            || self.gamepad_axis(controls, GamepadAxisType::LeftStickY) < -GAMEPAD_STICK_THRESHOLD
+    }
+
+    pub fn left(&self, controls: &PlayerControls) -> bool {
+        self.keys.pressed(controls.left)
+            || self.gamepad_pressed(controls, GamepadButtonType::DPadLeft)
+            || self.gamepad_axis(controls, GamepadAxisType::LeftStickX) < -GAMEPAD_STICK_THRESHOLD
+    }
+
+    pub fn right(&self, controls: &PlayerControls) -> bool {
+        self.keys.pressed(controls.right)
+            || self.gamepad_pressed(controls, GamepadButtonType::DPadRight)
+            || self.gamepad_axis(controls, GamepadAxisType::LeftStickX) > GAMEPAD_STICK_THRESHOLD
+    }
+
+    pub fn dash_just_pressed(&self, controls: &PlayerControls) -> bool {
+        self.keys.just_pressed(controls.dash)
+            || self.gamepad_just_pressed(controls, GamepadButtonType::South)
+    }
+
+    pub fn ultimate_just_pressed(&self, controls: &PlayerControls) -> bool {
+        self.keys.just_pressed(controls.ultimate)
+            || self.gamepad_just_pressed(controls, GamepadButtonType::East)
+    }
+}
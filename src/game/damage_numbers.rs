@@ -0,0 +1,214 @@
+use bevy::prelude::*;
+
+use crate::GameState;
+
+use super::projectile_spawner::{DamageAppliedEvent, DamageType};
+use super::GamePlayState;
+
+// Numbers that float up off enemies as they take damage. Fast ticks on the same enemy add up
+// in one number rather than stacking a pile of them.
+pub struct DamageNumbersPlugin;
+
+impl Plugin for DamageNumbersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DamageNumberSettings>()
+            .add_systems(
+                Update,
+                (spawn_damage_numbers, update_damage_numbers).run_if(
+                    in_state(GamePlayState::Started).or_else(in_state(GamePlayState::Boss)),
+                ),
+            )
+            .add_systems(OnEnter(GamePlayState::Restart), unload)
+            .add_systems(OnExit(GameState::Gameplay), unload);
+    }
+}
+
+// Toggled on the character select screen.
+#[derive(Resource, Debug)]
+pub struct DamageNumberSettings {
+    pub enabled: bool,
+    // Most numbers on screen at once - the oldest makes way for a new one.
+    pub max_numbers: usize,
+}
+
+impl Default for DamageNumberSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_numbers: 40,
+        }
+    }
+}
+
+const FONT_PATH: &str = "fonts/patua_one/patuaone.ttf";
+const FONT_SIZE: f32 = 9.;
+// Crits and weakness hits are drawn this much bigger.
+const BIG_SCALE: f32 = 1.6;
+
+const LIFETIME_SECONDS: f32 = 0.8;
+// Hits landing this soon after the last one on the same enemy are added to its number.
+const MERGE_SECONDS: f32 = 0.25;
+const RISE_SPEED: f32 = 30.;
+const START_HEIGHT: f32 = 14.;
+// Above the enemies and hit effects.
+const Z: f32 = 20.;
+
+#[derive(Component, Debug)]
+struct DamageNumber {
+    target: Entity,
+    damage_type: DamageType,
+    damage: f32,
+    is_big: bool,
+    age: Timer,
+    // Seconds since the last hit was merged in.
+    since_hit: f32,
+}
+
+fn spawn_damage_numbers(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    settings: Res<DamageNumberSettings>,
+    mut damage_applied_events: EventReader<DamageAppliedEvent>,
+    mut number_query: Query<(Entity, &mut DamageNumber, &mut Text, &mut Transform)>,
+) {
+    if !settings.enabled {
+        damage_applied_events.clear();
+        return;
+    }
+
+    // New numbers this frame, kept here so hits later in the same frame can merge into them.
+    let mut new_numbers: Vec<(Vec3, DamageNumber)> = Vec::new();
+    let mut evicted: Vec<Entity> = Vec::new();
+
+    for event in damage_applied_events.read() {
+        if event.damage <= 0. {
+            continue;
+        }
+
+        let is_big = event.is_critical || event.is_weakness;
+        let can_merge = |number: &DamageNumber| {
+            number.target == event.entity
+                && number.damage_type == event.damage_type
+                && number.since_hit < MERGE_SECONDS
+        };
+
+        if let Some((_, number)) = new_numbers.iter_mut().find(|(_, number)| can_merge(number)) {
+            number.damage += event.damage;
+            number.is_big |= is_big;
+            continue;
+        }
+
+        let merge_into = number_query
+            .iter_mut()
+            .find(|(entity, number, _, _)| !evicted.contains(entity) && can_merge(number));
+
+        if let Some((_, mut number, mut text, mut transform)) = merge_into {
+            number.damage += event.damage;
+            number.is_big |= is_big;
+            number.since_hit = 0.;
+            number.age.reset();
+
+            text.sections[0].value = get_damage_label(number.damage);
+            transform.scale = Vec3::splat(get_scale(number.is_big));
+            continue;
+        }
+
+        let count = number_query.iter().len() - evicted.len() + new_numbers.len();
+
+        if count >= settings.max_numbers {
+            let oldest = number_query
+                .iter()
+                .filter(|(entity, _, _, _)| !evicted.contains(entity))
+                .max_by(|(_, a, _, _), (_, b, _, _)| a.age.elapsed().cmp(&b.age.elapsed()))
+                .map(|(entity, _, _, _)| entity);
+
+            match oldest {
+                Some(oldest) => {
+                    commands.entity(oldest).despawn_recursive();
+                    evicted.push(oldest);
+                }
+                // Every number showing is from this frame already.
+                None => continue,
+            }
+        }
+
+        new_numbers.push((
+            event.position,
+            DamageNumber {
+                target: event.entity,
+                damage_type: event.damage_type,
+                damage: event.damage,
+                is_big,
+                age: Timer::from_seconds(LIFETIME_SECONDS, TimerMode::Once),
+                since_hit: 0.,
+            },
+        ));
+    }
+
+    if new_numbers.is_empty() {
+        return;
+    }
+
+    let font = assets.load(FONT_PATH);
+
+    for (position, number) in new_numbers {
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    get_damage_label(number.damage),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: FONT_SIZE,
+                        color: number.damage_type.get_color(),
+                    },
+                ),
+                transform: Transform::from_xyz(position.x, position.y + START_HEIGHT, Z)
+                    .with_scale(Vec3::splat(get_scale(number.is_big))),
+                ..default()
+            },
+            number,
+            Name::new("Damage number"),
+        ));
+    }
+}
+
+// Rises and fades out over its lifetime.
+fn update_damage_numbers(
+    mut commands: Commands,
+    mut number_query: Query<(Entity, &mut DamageNumber, &mut Text, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, mut number, mut text, mut transform) in number_query.iter_mut() {
+        number.age.tick(time.delta());
+        number.since_hit += time.delta_seconds();
+
+        if number.age.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        transform.translation.y += RISE_SPEED * time.delta_seconds();
+        text.sections[0]
+            .style
+            .color
+            .set_a(1. - number.age.percent());
+    }
+}
+
+fn get_damage_label(damage: f32) -> String {
+    format!("{}", damage.round().max(1.))
+}
+
+fn get_scale(is_big: bool) -> f32 {
+    if is_big {
+        BIG_SCALE
+    } else {
+        1.
+    }
+}
+
+fn unload(mut commands: Commands, number_query: Query<Entity, With<DamageNumber>>) {
+    for entity in number_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,184 @@
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::sprite::Health;
+use crate::sprite_sheets::SpriteSheets;
+
+use super::player::{Downed, Player, PlayerDamageEvent};
+use super::pools::EntityPools;
+use super::projectile_spawner::{spawn_scaled_explosion_at_position, EnemyKilledEvent};
+use super::spawner::{spawn_split_enemies, Enemy, EnemyKind, EnemyLimits, LevelSpawns};
+use super::GamePlayState;
+
+// Elites are rolled by the spawner, see spawn_enemy. This covers what their affixes do once
+// they're out on the field, and when they die.
+pub struct ElitesPlugin;
+
+impl Plugin for ElitesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (regenerate_elites, trigger_death_affixes)
+                .run_if(in_state(GamePlayState::Started).or_else(in_state(GamePlayState::Boss))),
+        );
+    }
+}
+
+// Chance of an elite carrying a second affix on top of the first.
+const SECOND_AFFIX_CHANCE: f64 = 0.3;
+
+const ARMORED_DAMAGE_TAKEN: f32 = 0.5;
+// Share of max health healed every second.
+const REGEN_PER_SECOND: f32 = 0.05;
+
+const SPLIT_COUNT_MIN: usize = 2;
+const SPLIT_COUNT_MAX: usize = 3;
+
+const EXPLOSION_RADIUS: f32 = 60.;
+const EXPLOSION_DAMAGE: f32 = 25.;
+const EXPLOSION_SCALE: f32 = 2.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EliteAffix {
+    Fast,
+    Armored,
+    Regenerating,
+    Splitting,
+    Exploding,
+}
+
+impl EliteAffix {
+    const ALL: [EliteAffix; 5] = [
+        EliteAffix::Fast,
+        EliteAffix::Armored,
+        EliteAffix::Regenerating,
+        EliteAffix::Splitting,
+        EliteAffix::Exploding,
+    ];
+
+    // The elite is tinted after its first affix, so players can tell what they're up against.
+    pub fn get_color(&self) -> Color {
+        match self {
+            EliteAffix::Fast => Color::rgb(0.5, 1., 0.5),
+            EliteAffix::Armored => Color::rgb(0.65, 0.7, 0.85),
+            EliteAffix::Regenerating => Color::rgb(1., 0.55, 0.8),
+            EliteAffix::Splitting => Color::rgb(0.5, 0.85, 1.),
+            EliteAffix::Exploding => Color::rgb(1., 0.5, 0.3),
+        }
+    }
+
+    // Only slimes know how to split.
+    fn is_allowed_for(&self, kind: EnemyKind) -> bool {
+        match self {
+            EliteAffix::Splitting => kind == EnemyKind::Slime,
+            _ => true,
+        }
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct Elite {
+    pub affixes: Vec<EliteAffix>,
+}
+
+impl Elite {
+    pub fn get_damage_taken(&self, damage: f32) -> f32 {
+        if self.affixes.contains(&EliteAffix::Armored) {
+            damage * ARMORED_DAMAGE_TAKEN
+        } else {
+            damage
+        }
+    }
+}
+
+pub fn roll_elite_affixes(kind: EnemyKind) -> Vec<EliteAffix> {
+    let mut rng = rand::thread_rng();
+
+    let candidates: Vec<EliteAffix> = EliteAffix::ALL
+        .into_iter()
+        .filter(|affix| affix.is_allowed_for(kind))
+        .collect();
+
+    let count = if rng.gen_bool(SECOND_AFFIX_CHANCE) {
+        2
+    } else {
+        1
+    };
+
+    candidates
+        .choose_multiple(&mut rng, count)
+        .copied()
+        .collect()
+}
+
+fn regenerate_elites(mut elite_query: Query<(&Elite, &mut Health)>, time: Res<Time>) {
+    for (elite, mut health) in elite_query.iter_mut() {
+        if !elite.affixes.contains(&EliteAffix::Regenerating) || health.total <= 0. {
+            continue;
+        }
+
+        let heal = health.max * REGEN_PER_SECOND * time.delta_seconds();
+        health.heal(heal);
+    }
+}
+
+fn trigger_death_affixes(
+    mut commands: Commands,
+    mut sheets: SpriteSheets,
+    mut pools: ResMut<EntityPools>,
+    mut enemy_killed_events: EventReader<EnemyKilledEvent>,
+    player_query: Query<(&Transform, Entity), (With<Player>, Without<Enemy>, Without<Downed>)>,
+    mut player_damage_events: EventWriter<PlayerDamageEvent>,
+    level_spawns: Res<LevelSpawns>,
+    enemy_query: Query<(), With<Enemy>>,
+    enemy_limits: Res<EnemyLimits>,
+) {
+    // Splits are held to the enemy cap like waves are. Spawns only land at the end of the frame,
+    // so ones made earlier this frame are counted by hand.
+    let mut enemy_count = enemy_query.iter().count();
+
+    for event in enemy_killed_events.read() {
+        if event.affixes.contains(&EliteAffix::Splitting) {
+            let count = rand::thread_rng()
+                .gen_range(SPLIT_COUNT_MIN..=SPLIT_COUNT_MAX)
+                .min(enemy_limits.max_enemies.saturating_sub(enemy_count));
+            enemy_count += count;
+
+            spawn_split_enemies(
+                count,
+                event.kind,
+                event.position.truncate(),
+                &mut commands,
+                &mut sheets,
+                &level_spawns,
+            );
+        }
+
+        if event.affixes.contains(&EliteAffix::Exploding) {
+            spawn_scaled_explosion_at_position(
+                &mut pools,
+                &mut commands,
+                &event.position,
+                EXPLOSION_SCALE,
+            );
+
+            // Sent through damage_players like any other hit, so armor is taken off it too -
+            // see get_damage_after_armor.
+            for (player_transform, player_entity) in player_query.iter() {
+                let distance = player_transform
+                    .translation
+                    .truncate()
+                    .distance(event.position.truncate());
+
+                if distance < EXPLOSION_RADIUS {
+                    player_damage_events.send(PlayerDamageEvent {
+                        entity: player_entity,
+                        damage: EXPLOSION_DAMAGE,
+                        source: event.position,
+                    });
+                }
+            }
+        }
+    }
+}
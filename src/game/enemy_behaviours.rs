@@ -0,0 +1,175 @@
+use core::time::Duration;
+
+use bevy::prelude::*;
+use rand::Rng;
+
+// How an enemy gets to the players. Picked per enemy kind in the spawner, so each stage's
+// waves move differently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovementPattern {
+    // Straight at the closest player.
+    Chase,
+    // Dives in fast, then pulls back before the next dive.
+    Swoop,
+    // Moves in short bursts with a rest in between.
+    Hop,
+    // Circles the player at a distance, then dashes in.
+    OrbitDash,
+    // Chases until badly hurt, then runs away.
+    Flee,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BehaviourPhase {
+    Approach,
+    Retreat,
+    Rest,
+    Orbit,
+    Dash,
+}
+
+const SWOOP_SPEED: f32 = 1.8;
+const SWOOP_HIT_DISTANCE: f32 = 20.;
+const SWOOP_MAX_SECONDS: f32 = 3.;
+const SWOOP_RETREAT_SPEED: f32 = 1.2;
+const SWOOP_RETREAT_SECONDS: f32 = 1.;
+
+const HOP_SPEED: f32 = 2.8;
+const HOP_SECONDS: f32 = 0.3;
+const HOP_REST_SECONDS: f32 = 0.6;
+
+const ORBIT_RADIUS: f32 = 110.;
+// How much an orbiting enemy drifts in or out to hold its radius.
+const ORBIT_PULL: f32 = 0.4;
+const ORBIT_SECONDS: f32 = 2.5;
+const DASH_SPEED: f32 = 3.5;
+const DASH_SECONDS: f32 = 0.5;
+
+// Share of max health left when a fleeing enemy turns tail.
+const FLEE_HEALTH: f32 = 0.3;
+const FLEE_SPEED: f32 = 0.8;
+
+#[derive(Component, Debug)]
+pub struct EnemyBehaviour {
+    pub pattern: MovementPattern,
+    phase: BehaviourPhase,
+    phase_timer: Timer,
+    // 1 or -1, so not every orbiting enemy circles the same way.
+    orbit_direction: f32,
+    // Locked in when a dash starts, so dashes go in a straight line.
+    dash_direction: Vec3,
+    max_health: f32,
+}
+
+impl EnemyBehaviour {
+    pub fn new(pattern: MovementPattern, max_health: f32) -> Self {
+        let mut rng = rand::thread_rng();
+
+        let (phase, seconds) = match pattern {
+            MovementPattern::Swoop => (BehaviourPhase::Approach, SWOOP_MAX_SECONDS),
+            // Staggered so a wave of slimes doesn't hop in lockstep.
+            MovementPattern::Hop => (BehaviourPhase::Rest, rng.gen_range(0.05..HOP_REST_SECONDS)),
+            MovementPattern::OrbitDash => (BehaviourPhase::Orbit, ORBIT_SECONDS),
+            MovementPattern::Chase | MovementPattern::Flee => (BehaviourPhase::Approach, 0.),
+        };
+
+        Self {
+            pattern,
+            phase,
+            phase_timer: Timer::from_seconds(seconds, TimerMode::Once),
+            orbit_direction: if rng.gen_bool(0.5) { 1. } else { -1. },
+            dash_direction: Vec3::ZERO,
+            max_health,
+        }
+    }
+
+    fn set_phase(&mut self, phase: BehaviourPhase, seconds: f32) {
+        self.phase = phase;
+        self.phase_timer = Timer::from_seconds(seconds, TimerMode::Once);
+    }
+
+    // Direction to move this frame, scaled by how fast compared to the enemy's normal speed.
+    // Zero means standing still.
+    pub fn get_movement(
+        &mut self,
+        position: Vec3,
+        target: Vec3,
+        health: f32,
+        delta: Duration,
+    ) -> Vec3 {
+        let to_target = (target - position).truncate().extend(0.);
+        let distance = to_target.length();
+        let towards = to_target.normalize_or_zero();
+
+        self.phase_timer.tick(delta);
+
+        match self.pattern {
+            MovementPattern::Chase => towards,
+            MovementPattern::Swoop => match self.phase {
+                BehaviourPhase::Retreat => {
+                    if self.phase_timer.finished() {
+                        self.set_phase(BehaviourPhase::Approach, SWOOP_MAX_SECONDS);
+                    }
+
+                    -towards * SWOOP_RETREAT_SPEED
+                }
+                _ => {
+                    if distance < SWOOP_HIT_DISTANCE || self.phase_timer.finished() {
+                        self.set_phase(BehaviourPhase::Retreat, SWOOP_RETREAT_SECONDS);
+                    }
+
+                    towards * SWOOP_SPEED
+                }
+            },
+            MovementPattern::Hop => match self.phase {
+                BehaviourPhase::Rest => {
+                    if self.phase_timer.finished() {
+                        self.set_phase(BehaviourPhase::Approach, HOP_SECONDS);
+                    }
+
+                    Vec3::ZERO
+                }
+                _ => {
+                    if self.phase_timer.finished() {
+                        self.set_phase(BehaviourPhase::Rest, HOP_REST_SECONDS);
+                    }
+
+                    towards * HOP_SPEED
+                }
+            },
+            MovementPattern::OrbitDash => match self.phase {
+                BehaviourPhase::Dash => {
+                    if self.phase_timer.finished() {
+                        self.set_phase(BehaviourPhase::Orbit, ORBIT_SECONDS);
+                    }
+
+                    self.dash_direction * DASH_SPEED
+                }
+                _ => {
+                    if distance > ORBIT_RADIUS * 1.5 {
+                        // Too far out to orbit yet - the timer waits until it gets close.
+                        self.phase_timer.reset();
+                        return towards;
+                    }
+
+                    if self.phase_timer.finished() {
+                        self.dash_direction = towards;
+                        self.set_phase(BehaviourPhase::Dash, DASH_SECONDS);
+                    }
+
+                    let around = Vec3::new(-towards.y, towards.x, 0.) * self.orbit_direction;
+                    let pull = ((distance - ORBIT_RADIUS) / ORBIT_RADIUS).clamp(-1., 1.);
+
+                    (around + towards * pull * ORBIT_PULL).normalize_or_zero()
+                }
+            },
+            MovementPattern::Flee => {
+                if health < self.max_health * FLEE_HEALTH {
+                    -towards * FLEE_SPEED
+                } else {
+                    towards
+                }
+            }
+        }
+    }
+}
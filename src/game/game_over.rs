@@ -273,9 +273,7 @@ pub fn reset_camera(
     // query: Query<(&Transform) /*(With<Player>)*/>,
     mut camera_query: Query<&mut Transform, With<Camera> /*, Without<Player>*/>,
 ) {
-    // let player_transform = query.single();
-    let mut camera_transform = camera_query.single_mut();
-
-    //
-    camera_transform.translation = Vec3::new(0., 0., 0.);
+    for mut camera_transform in camera_query.iter_mut() {
+        camera_transform.translation = Vec3::new(0., 0., 0.);
+    }
 }
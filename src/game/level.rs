@@ -1,21 +1,38 @@
 use bevy::prelude::*;
-use bevy::sprite::Anchor;
+use bevy::render::mesh::Indices;
+use bevy::render::render_resource::PrimitiveTopology;
+use bevy::sprite::MaterialMesh2dBundle;
 
-use super::player::Player;
+use super::biomes::SelectedBiome;
+use super::player::{get_players_center, Player};
+use crate::sprite_sheets::SpriteSheets;
+use crate::tiled_map::{spawn_tiled_map, unload_tiled_map, MapEntity, TiledMap};
 // use crate::util_fade::FadeState;
 use crate::GameState;
 
 pub struct LevelPlugin;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 use super::GamePlayState;
 
 impl Plugin for LevelPlugin {
     fn build(&self, app: &mut App) {
         println!("Load game play level plugin");
-        app.add_systems(OnEnter(GamePlayState::Init), setup)
+        app.init_resource::<LevelSeed>()
+            .init_resource::<MapMode>()
+            .add_systems(
+                OnEnter(GamePlayState::Init),
+                (roll_level_seed, setup, spawn_map).chain(),
+            )
             .add_systems(OnEnter(GamePlayState::Restart), unload)
             .add_systems(Update, update.run_if(in_state(GamePlayState::Started)))
+            .add_systems(
+                Update,
+                stream_floor_chunks
+                    .run_if(resource_equals(MapMode::Endless))
+                    .run_if(in_state(GamePlayState::Started).or_else(in_state(GamePlayState::Boss))),
+            )
             .add_systems(OnExit(GameState::Gameplay), unload);
     }
 }
@@ -25,13 +42,67 @@ pub const MAP_HEIGHT: f32 = 3200.;
 pub const MAP_MOVABLE_WIDTH: f32 = 2560.;
 pub const MAP_MOVABLE_HEIGHT: f32 = 2560.;
 
+// Floor is drawn as square chunks of tiles, one mesh each, all sharing the tileset texture.
+// Chunks are built from the level seed so endless mode can keep making more of them, and a
+// biome's Tiled map (if it has one) is laid over the top.
+pub const CHUNK_SIZE: i32 = 16;
+pub const TILE_SIZE: f32 = 32.;
+pub const CHUNK_WORLD_SIZE: f32 = CHUNK_SIZE as f32 * TILE_SIZE;
+
+// In endless mode, chunks this many away from the players are kept loaded. Anything past one
+// more than that gets dropped, so walking back and forth over a border doesn't churn chunks.
+const STREAM_RADIUS_CHUNKS: i32 = 2;
+
 #[derive(Debug, Component)]
 pub struct Floor {}
 
-fn unload(mut floor: Query<Entity, With<Floor>>, mut commands: Commands) {
+#[derive(Debug, Component)]
+pub struct FloorChunk {
+    pub position: IVec2,
+}
+
+// Arena is the fixed walled-in map. Endless streams the floor in around the players instead, with
+// no edges to run into.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MapMode {
+    #[default]
+    Arena,
+    Endless,
+}
+
+// Seed for the run's layout. Tile variation and obstacle placement are built from it, so the
+// same seed always gives the same arena.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct LevelSeed(pub u64);
+
+// Sheet the floor tiles are picked from, and which cells of it to pick from.
+#[derive(Debug, Clone)]
+pub struct FloorTileset {
+    pub path: &'static str,
+    pub size: Vec2,
+    pub tile_size: f32,
+    pub cells: Vec<UVec2>,
+}
+
+// Material for the floor, created once so every chunk shares the same texture.
+#[derive(Resource, Debug)]
+pub struct FloorMaterial {
+    pub material: Handle<ColorMaterial>,
+    pub tileset: FloorTileset,
+}
+
+fn unload(
+    mut floor: Query<Entity, With<Floor>>,
+    map_query: Query<Entity, With<MapEntity>>,
+    mut commands: Commands,
+) {
     for floor in &mut floor.iter_mut() {
         commands.entity(floor).despawn_recursive();
     }
+
+    unload_tiled_map(&mut commands, &map_query);
+
+    commands.remove_resource::<FloorMaterial>();
 }
 
 fn update(
@@ -44,89 +115,205 @@ fn update(
     // Check for collision with player
 }
 
+pub fn roll_level_seed(mut level_seed: ResMut<LevelSeed>) {
+    level_seed.0 = rand::random();
+
+    println!("Level seed {}", level_seed.0);
+}
+
+// Each chunk gets its own rng from the level seed and its position, so a chunk looks the same
+// no matter when or in what order it's built.
+pub fn get_chunk_rng(seed: u64, chunk: IVec2) -> StdRng {
+    let chunk_seed = seed
+        ^ (chunk.x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (chunk.y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+
+    StdRng::seed_from_u64(chunk_seed)
+}
+
+pub fn get_chunk_position(position: Vec2) -> IVec2 {
+    (position / CHUNK_WORLD_SIZE).floor().as_ivec2()
+}
+
+fn get_tile_uvs(tileset: &FloorTileset, cell: UVec2) -> (Vec2, Vec2) {
+    // Pull in half a texel so neighbouring tiles never bleed into each other.
+    let inset = Vec2::splat(0.5) / tileset.size;
+    let tile = Vec2::splat(tileset.tile_size) / tileset.size;
+
+    let min = cell.as_vec2() * tile + inset;
+    let max = (cell + UVec2::ONE).as_vec2() * tile - inset;
+
+    (min, max)
+}
+
+// One quad per tile, laid out from the chunk's bottom left corner.
+fn get_chunk_mesh(seed: u64, chunk: IVec2, tileset: &FloorTileset) -> Mesh {
+    let mut rng = get_chunk_rng(seed, chunk);
+
+    let total_tiles = (CHUNK_SIZE * CHUNK_SIZE) as usize;
+    let mut positions: Vec<[f32; 3]> = Vec::with_capacity(total_tiles * 4);
+    let mut normals: Vec<[f32; 3]> = Vec::with_capacity(total_tiles * 4);
+    let mut uvs: Vec<[f32; 2]> = Vec::with_capacity(total_tiles * 4);
+    let mut indices: Vec<u32> = Vec::with_capacity(total_tiles * 6);
+
+    for y in 0..CHUNK_SIZE {
+        for x in 0..CHUNK_SIZE {
+            let random_cell = tileset.cells[rng.gen_range(0..tileset.cells.len())];
+            let (uv_min, uv_max) = get_tile_uvs(tileset, random_cell);
+
+            let left = x as f32 * TILE_SIZE;
+            let bottom = y as f32 * TILE_SIZE;
+            let right = left + TILE_SIZE;
+            let top = bottom + TILE_SIZE;
+
+            let first = positions.len() as u32;
+
+            positions.extend([
+                [left, bottom, 0.],
+                [right, bottom, 0.],
+                [right, top, 0.],
+                [left, top, 0.],
+            ]);
+            normals.extend([[0., 0., 1.]; 4]);
+            // Texture v runs downwards, world y runs up.
+            uvs.extend([
+                [uv_min.x, uv_max.y],
+                [uv_max.x, uv_max.y],
+                [uv_max.x, uv_min.y],
+                [uv_min.x, uv_min.y],
+            ]);
+            indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+        }
+    }
+
+    Mesh::new(PrimitiveTopology::TriangleList)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_indices(Some(Indices::U32(indices)))
+}
+
+pub fn spawn_floor_chunk(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    material: &FloorMaterial,
+    seed: u64,
+    chunk: IVec2,
+) {
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes
+                .add(get_chunk_mesh(seed, chunk, &material.tileset))
+                .into(),
+            material: material.material.clone(),
+            transform: Transform::from_xyz(
+                chunk.x as f32 * CHUNK_WORLD_SIZE,
+                chunk.y as f32 * CHUNK_WORLD_SIZE,
+                -0.4,
+            ),
+            ..default()
+        },
+        Floor {},
+        FloorChunk { position: chunk },
+        Name::new(format!("Floor Chunk {} {}", chunk.x, chunk.y)),
+    ));
+}
+
 fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    level_seed: Res<LevelSeed>,
+    map_mode: Res<MapMode>,
+    selected_biome: Res<SelectedBiome>,
 ) {
     println!("Game play level setup");
 
-    // Sprite stuff
-    let block_width = 32.;
-    let block_height = 32.;
-    let block_cols = 8;
-    let block_rows = 4;
-    let total_atlas_blocks = block_cols * block_rows;
-
-    // Calculate number of loops needed to fill the desired MAP or REGION size.
-    let total_tiles_wide = (MAP_WIDTH as i32) / (block_width as i32); // In this case - 6
-    let total_tiles_high = (MAP_HEIGHT as i32) / (block_height as i32); // In this case - 80
-    let total_tiles = total_tiles_high * total_tiles_wide;
-    let final_x = MAP_WIDTH / 2.;
-    let final_y = MAP_HEIGHT / 2.;
-
-    // println!("TOTAL TILES {}", total_tiles);
-
-    let mut rng = rand::thread_rng();
-
-    // Start at -1280, -1280 for 2560 size map.
-    let starting_point_x = -1. * final_x;
-    let starting_point_y = -1. * final_y;
-
-    // println!("STARTING POINT Y {}", starting_point_y);
-
-    let mut rolling_x = starting_point_x;
-    let mut rolling_y = starting_point_y;
-
-    //commands.spawn(Camera2dBundle::default());
-    for index in 0..total_tiles {
-        // println!("INDEX {} {} {}", index, rolling_x, rolling_y);
-
-        let texture_handle = asset_server.load("sprites/level/tx_tileset_grass.png");
-
-        let texture_atlas = TextureAtlas::from_grid(
-            texture_handle,
-            Vec2::new(block_width, block_height),
-            block_cols,
-            block_rows,
-            None,
-            None,
-        );
-
-        let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
-        let random_index: usize = rng.gen_range(0..total_atlas_blocks);
-
-        commands.spawn((
-            SpriteSheetBundle {
-                texture_atlas: texture_atlas_handle,
-                sprite: TextureAtlasSprite {
-                    index: random_index,
-                    anchor: Anchor::BottomLeft,
-                    ..default()
-                },
-                transform: Transform {
-                    // scale: Vec3::new(1., 1., 1.),
-                    rotation: Quat::IDENTITY,
-                    translation: Vec3::new(rolling_x, rolling_y, -0.4),
-                    ..default()
-                },
-                // global_transform: Transform::
-                ..default()
-            },
-            Floor {},
-        ));
-
-        rolling_x += block_width;
-
-        if rolling_x >= final_x {
-            rolling_x = starting_point_x;
-            rolling_y += block_height;
+    let tileset = selected_biome.0.get_floor();
+    let material = FloorMaterial {
+        material: materials.add(ColorMaterial::from(asset_server.load(tileset.path))),
+        tileset,
+    };
+
+    // Enough chunks to cover the whole map, lined up on the origin. Endless only needs the ones
+    // around the start, the rest stream in as players move.
+    let (max_chunk_x, max_chunk_y) = match *map_mode {
+        MapMode::Arena => (
+            (MAP_WIDTH / 2. / CHUNK_WORLD_SIZE).ceil() as i32,
+            (MAP_HEIGHT / 2. / CHUNK_WORLD_SIZE).ceil() as i32,
+        ),
+        MapMode::Endless => (STREAM_RADIUS_CHUNKS + 1, STREAM_RADIUS_CHUNKS + 1),
+    };
+
+    for chunk_y in -max_chunk_y..max_chunk_y {
+        for chunk_x in -max_chunk_x..max_chunk_x {
+            spawn_floor_chunk(
+                &mut commands,
+                &mut meshes,
+                &material,
+                level_seed.0,
+                IVec2::new(chunk_x, chunk_y),
+            );
         }
+    }
 
-        // if rolling_y >= final_y {
-        //     break;
-        //     //panic!("Should not get here - loop should finish before this.");
-        // }
+    commands.insert_resource(material);
+}
+
+// Walls, props and spawn points for the arena, from the biome's map. The loading screen has
+// already fetched it, so it's spawned straight away with the floor.
+fn spawn_map(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    maps: Res<Assets<TiledMap>>,
+    mut sheets: SpriteSheets,
+    selected_biome: Res<SelectedBiome>,
+) {
+    let Some(path) = selected_biome.0.get_map_path() else {
+        return;
+    };
+
+    let Some(map) = maps.get(asset_server.load::<TiledMap>(path)) else {
+        println!("Arena map {} isn't loaded, leaving it out", path);
+        return;
+    };
+
+    spawn_tiled_map(map, &mut commands, &mut sheets);
+}
+
+fn stream_floor_chunks(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    chunk_query: Query<(&FloorChunk, Entity)>,
+    player_query: Query<&Transform, With<Player>>,
+    material: Res<FloorMaterial>,
+    level_seed: Res<LevelSeed>,
+) {
+    let Some(center) = get_players_center(player_query.iter()) else {
+        return;
+    };
+
+    let center_chunk = get_chunk_position(center.truncate());
+    let mut loaded: Vec<IVec2> = vec![];
+
+    for (chunk, entity) in chunk_query.iter() {
+        let distance = (chunk.position - center_chunk).abs().max_element();
+
+        if distance > STREAM_RADIUS_CHUNKS + 1 {
+            commands.entity(entity).despawn_recursive();
+        } else {
+            loaded.push(chunk.position);
+        }
+    }
+
+    for chunk_y in -STREAM_RADIUS_CHUNKS..=STREAM_RADIUS_CHUNKS {
+        for chunk_x in -STREAM_RADIUS_CHUNKS..=STREAM_RADIUS_CHUNKS {
+            let chunk = center_chunk + IVec2::new(chunk_x, chunk_y);
+
+            if !loaded.contains(&chunk) {
+                spawn_floor_chunk(&mut commands, &mut meshes, &material, level_seed.0, chunk);
+            }
+        }
     }
 }
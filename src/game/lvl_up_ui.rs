@@ -1,8 +1,10 @@
+use std::collections::VecDeque;
+
 use bevy::prelude::*;
 
 use super::{
     player::{CanLevel, Player},
-    weapons::{get_available_weapons, Weapon},
+    weapons::{add_player_weapon, get_available_weapons, Weapon},
 };
 use crate::{
     game::GamePlayState,
@@ -15,33 +17,45 @@ pub struct LvlUpUiPlugin;
 impl Plugin for LvlUpUiPlugin {
     fn build(&self, app: &mut App) {
         println!("Load game play ui plugin");
-        app.add_systems(OnEnter(GamePlayState::LevelUp), on_level_up)
+        app.init_resource::<PendingLevelUps>()
+            .add_systems(OnEnter(GamePlayState::LevelUp), on_level_up)
             .add_systems(
                 Update,
                 weapon_button_select.run_if(in_state(GamePlayState::LevelUp)),
             )
             .add_systems(OnExit(GamePlayState::LevelUp), unload)
-            .add_systems(OnExit(GameState::Gameplay), unload);
+            .add_systems(OnEnter(GamePlayState::Restart), clear_pending_level_ups)
+            .add_systems(
+                OnExit(GameState::Gameplay),
+                (unload, clear_pending_level_ups),
+            );
 
         // app.add_systems(OnEnterStartup, ui_setup)
         //     .add_systems(PostUpdate, ui_update);
     }
 }
 
+// Players waiting on a level up pick, in the order they leveled. With shared XP both co-op
+// players level together, so they take turns on the same screen.
+#[derive(Resource, Debug, Default)]
+pub struct PendingLevelUps {
+    pub players: VecDeque<Entity>,
+}
+
 #[derive(Component)]
-struct LvlUpContainer;
+pub(super) struct LvlUpContainer;
 
 #[derive(Component)]
 struct TitleUi;
 
 #[derive(Component)]
-struct SmallTitleUi;
+pub(super) struct SmallTitleUi;
 
 #[derive(Component)]
-struct LvlUpDialogContainer;
+pub(super) struct LvlUpDialogContainer;
 
 #[derive(Component)]
-struct UpgradesContainer;
+pub(super) struct UpgradesContainer;
 
 #[derive(Component)]
 struct WeaponButtonUI {
@@ -57,7 +71,7 @@ struct WeaponIconUi;
 #[derive(Component)]
 struct WeaponDescUi;
 
-fn get_dialog_container() -> (NodeBundle, LvlUpDialogContainer, Name) {
+pub(super) fn get_dialog_container() -> (NodeBundle, LvlUpDialogContainer, Name) {
     (
         NodeBundle {
             style: Style {
@@ -79,7 +93,7 @@ fn get_dialog_container() -> (NodeBundle, LvlUpDialogContainer, Name) {
     )
 }
 
-fn get_upgrades_container() -> (NodeBundle, UpgradesContainer, Name) {
+pub(super) fn get_upgrades_container() -> (NodeBundle, UpgradesContainer, Name) {
     (
         NodeBundle {
             style: Style {
@@ -97,11 +111,11 @@ fn get_upgrades_container() -> (NodeBundle, UpgradesContainer, Name) {
     )
 }
 
-fn get_title(lvl: u32, assets: &Res<AssetServer>) -> (TextBundle, Name, TitleUi) {
+fn get_title(who: &str, lvl: u32, assets: &Res<AssetServer>) -> (TextBundle, Name, TitleUi) {
     let font = assets.load("fonts/patua_one/patuaone.ttf");
 
     let section = TextSection {
-        value: format!("You reached level {}!", lvl),
+        value: format!("{} reached level {}!", who, lvl),
         style: TextStyle {
             font: font,
             font_size: 38.0,
@@ -123,7 +137,10 @@ fn get_title(lvl: u32, assets: &Res<AssetServer>) -> (TextBundle, Name, TitleUi)
     )
 }
 
-fn get_small_title(txt: String, assets: &Res<AssetServer>) -> (TextBundle, Name, SmallTitleUi) {
+pub(super) fn get_small_title(
+    txt: String,
+    assets: &Res<AssetServer>,
+) -> (TextBundle, Name, SmallTitleUi) {
     let font = assets.load("fonts/patua_one/patuaone.ttf");
 
     let section = TextSection {
@@ -155,7 +172,7 @@ fn get_weapon_title(
     let font = assets.load("fonts/patua_one/patuaone.ttf");
 
     let section = TextSection {
-        value: weapon.name.clone(),
+        value: weapon.get_title(),
         style: TextStyle {
             font: font,
             font_size: 24.0,
@@ -212,7 +229,7 @@ fn get_weapon_desc(weapon: &Weapon, assets: &Res<AssetServer>) -> (TextBundle, N
     )
 }
 
-fn get_lvl_up_container() -> (NodeBundle, LvlUpContainer, Name) {
+pub(super) fn get_lvl_up_container() -> (NodeBundle, LvlUpContainer, Name) {
     (
         NodeBundle {
             style: Style {
@@ -266,27 +283,69 @@ fn get_weapon_button(weapon: &Weapon) -> (ButtonBundle, WeaponButtonUI) {
 fn on_level_up(
     mut commands: Commands,
     assets: Res<AssetServer>,
-    mut player_query: Query<(&Player, &CanLevel), With<Player>>,
+    player_query: Query<(&mut Player, &CanLevel, &Name)>,
+    mut pending_level_ups: ResMut<PendingLevelUps>,
     mut next_play_state: ResMut<NextState<GamePlayState>>,
 ) {
-    let (player, lvl) = player_query.single_mut();
+    spawn_next_level_up(
+        &mut commands,
+        &assets,
+        &player_query,
+        &mut pending_level_ups,
+        &mut next_play_state,
+    );
+}
 
-    let weapons_new = get_available_weapons(&player.weapons, 3);
+// Shows the pick screen for whoever is next in line, skipping anyone with nothing left to learn.
+// Goes back to normal play once everyone has picked.
+fn spawn_next_level_up(
+    commands: &mut Commands,
+    assets: &Res<AssetServer>,
+    player_query: &Query<(&mut Player, &CanLevel, &Name)>,
+    pending_level_ups: &mut PendingLevelUps,
+    next_play_state: &mut NextState<GamePlayState>,
+) {
+    while let Some(&entity) = pending_level_ups.players.front() {
+        let Ok((player, lvl, name)) = player_query.get(entity) else {
+            pending_level_ups.players.pop_front();
+            continue;
+        };
+
+        let weapons_new = get_available_weapons(&player.weapons, 3);
+
+        if weapons_new.is_empty() {
+            pending_level_ups.players.pop_front();
+            continue;
+        }
 
-    let mut btns: Vec<(ButtonBundle, WeaponButtonUI)> = vec![];
+        let who = if player_query.iter().count() > 1 {
+            name.as_str()
+        } else {
+            "You"
+        };
 
-    if weapons_new.len() == 0 {
-        next_play_state.set(GamePlayState::Started);
+        spawn_level_up_ui(commands, assets, who, lvl.level, weapons_new);
+        return;
     }
 
+    next_play_state.set(GamePlayState::Started);
+}
+
+fn spawn_level_up_ui(
+    commands: &mut Commands,
+    assets: &Res<AssetServer>,
+    who: &str,
+    lvl: u32,
+    weapons_new: Vec<Weapon>,
+) {
     commands
         .spawn(get_lvl_up_container())
         .with_children(|commands| {
             commands
                 .spawn(get_dialog_container())
                 .with_children(|commands| {
-                    commands.spawn(get_title(lvl.level, &assets));
-                    commands.spawn(get_small_title("Pick a skill.".into(), &assets));
+                    commands.spawn(get_title(who, lvl, assets));
+                    commands.spawn(get_small_title("Pick a skill.".into(), assets));
                     commands
                         .spawn(get_upgrades_container())
                         .with_children(|commands| {
@@ -294,8 +353,8 @@ fn on_level_up(
                                 let btn = get_weapon_button(&weapon);
 
                                 commands.spawn(btn).with_children(|commands| {
-                                    commands.spawn(get_weapon_title(&weapon, &assets));
-                                    commands.spawn(get_weapon_desc(&weapon, &assets));
+                                    commands.spawn(get_weapon_title(&weapon, assets));
+                                    commands.spawn(get_weapon_desc(&weapon, assets));
                                 });
                             }
                         });
@@ -315,20 +374,38 @@ fn weapon_button_select(
     >,
     mut next_play_state: ResMut<NextState<GamePlayState>>,
     // mut next_fade_state: ResMut<NextState<FadeState>>,
-    mut player_query: Query<(&mut Player), With<Player>>,
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    mut player_query: Query<(&mut Player, &CanLevel, &Name)>,
+    mut pending_level_ups: ResMut<PendingLevelUps>,
+    ui_query: Query<Entity, With<LvlUpContainer>>,
 ) {
-    let mut player = player_query.single_mut();
-
     for (interaction, mut color, mut border_color, weapon_btn) in &mut interaction_query {
         match *interaction {
             Interaction::Pressed => {
                 *color = DARK_PURPLE.into();
                 *border_color = LIGHT_TEAL.into();
 
-                player.weapons.push(weapon_btn.weapon.clone());
+                if let Some(entity) = pending_level_ups.players.pop_front() {
+                    if let Ok((mut player, _, _)) = player_query.get_mut(entity) {
+                        add_player_weapon(&mut player.weapons, weapon_btn.weapon.clone());
+                    }
+                }
+
+                for ui in &ui_query {
+                    commands.entity(ui).despawn_recursive();
+                }
+
+                // Next player's pick, or revert to normal play.
+                spawn_next_level_up(
+                    &mut commands,
+                    &assets,
+                    &player_query,
+                    &mut pending_level_ups,
+                    &mut next_play_state,
+                );
 
-                // Revert to normal play.
-                next_play_state.set(GamePlayState::Started);
+                return;
 
                 // ADD WEAPON TO PLAYER WEAPONS
                 // next_fade_state.set(FadeState::FadeToGame);
@@ -373,6 +450,10 @@ fn weapon_button_select(
 
 // fn get_available_weapon(weapons: Vec<ProjectileCategory>) -> TextBundle {}
 
+fn clear_pending_level_ups(mut pending_level_ups: ResMut<PendingLevelUps>) {
+    pending_level_ups.players.clear();
+}
+
 fn unload(mut ui_query: Query<Entity, With<LvlUpContainer>>, mut commands: Commands) {
     for ui in &mut ui_query.iter_mut() {
         commands.entity(ui).despawn_recursive();
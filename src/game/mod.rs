@@ -1,28 +1,48 @@
-mod level;
+mod abilities;
+pub mod biomes;
+mod chests;
+mod controls;
+pub mod damage_numbers;
+mod elites;
+mod enemy_behaviours;
+pub mod level;
+mod obstacles;
+mod pickups;
 mod player;
-mod projectile_spawner;
+mod pools;
+pub mod projectile_spawner;
 // mod potato_anim;
 // mod potato_enemy;
 mod spawner;
+pub mod stats;
 // mod potato_spawner;
 mod game_over;
 mod lvl_up_ui;
-mod ui;
+pub mod ui;
 pub mod weapons;
 
 use bevy::app::Plugin;
 use bevy::audio::{PlaybackMode, Volume, VolumeLevel};
 use bevy::prelude::*;
 
-use crate::GameState;
+use crate::{GameState, CAMERA_SCALE};
 
+use self::abilities::AbilitiesPlugin;
+use self::biomes::{Biome, BiomesPlugin, SelectedBiome};
+use self::chests::ChestsPlugin;
+use self::damage_numbers::DamageNumbersPlugin;
+use self::elites::ElitesPlugin;
 use self::level::LevelPlugin;
+use self::obstacles::ObstaclesPlugin;
+use self::pickups::PickupsPlugin;
 use self::player::PlayerPlugin;
+use self::pools::PoolsPlugin;
 
 use self::game_over::GameOverPlugin;
 use self::lvl_up_ui::LvlUpUiPlugin;
 use self::projectile_spawner::ProjectileSpawnerPlugin;
-use self::spawner::EnemySpawnerPlugin;
+use self::spawner::{EnemyKind, EnemySpawnerPlugin};
+use self::stats::StatsPlugin;
 use self::ui::GameUiPlugin;
 
 pub struct GameplayPlugin;
@@ -34,6 +54,7 @@ pub enum GamePlayState {
     Init,
     Started,
     LevelUp,
+    Chest, // Paused while a treasure chest opens
     Boss,
     GameOver,
     Restart,
@@ -44,23 +65,60 @@ pub struct MyMusic;
 
 impl Plugin for GameplayPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(GameUiPlugin)
+        app.add_plugins(BiomesPlugin)
+            .add_plugins(GameUiPlugin)
             .add_plugins(LvlUpUiPlugin)
             .add_plugins(LevelPlugin)
+            .add_plugins(ObstaclesPlugin)
             .add_state::<GamePlayState>()
             .add_plugins(PlayerPlugin)
+            .add_plugins(StatsPlugin)
             .add_plugins(EnemySpawnerPlugin)
+            .add_plugins(PoolsPlugin)
             .add_plugins(ProjectileSpawnerPlugin)
+            .add_plugins(AbilitiesPlugin)
+            .add_plugins(ElitesPlugin)
+            .add_plugins(DamageNumbersPlugin)
+            .add_plugins(ChestsPlugin)
+            .add_plugins(PickupsPlugin)
             .add_plugins(GameOverPlugin)
             .add_systems(
                 OnEnter(GameState::Gameplay),
                 (reset_camera, spawn_game_stuff),
             )
             .add_systems(OnEnter(GamePlayState::Restart), (reset_camera, restart))
-            .add_systems(OnExit(GameState::Gameplay), despawn_game_stuff);
+            .add_systems(
+                OnExit(GameState::Gameplay),
+                (reset_camera, despawn_game_stuff),
+            );
     }
 }
 
+// Everything a run on this biome loads, for the loading screen to fetch up front.
+pub fn get_gameplay_asset_paths(biome: Biome) -> Vec<String> {
+    let mut paths: Vec<String> = vec![
+        biome.get_music().to_string(),
+        biome.get_floor().path.to_string(),
+        pools::EXPLOSION_SHEET_PATH.to_string(),
+        pools::BLOOD_SHEET_PATH.to_string(),
+        weapons::PASSIVE_ICONS_PATH.to_string(),
+    ];
+
+    paths.extend(
+        EnemyKind::ALL
+            .iter()
+            .map(|kind| kind.get_sheet_path())
+            .chain(weapons::get_weapon_sprite_paths())
+            .chain(obstacles::get_obstacle_sheet_paths())
+            .chain(pickups::get_pickup_sprite_paths())
+            .map(|path| path.to_string()),
+    );
+    paths.extend(chests::get_chest_frame_paths());
+    paths.extend(biome.get_map_path().map(|path| path.to_string()));
+
+    paths
+}
+
 fn restart(
     mut commands: Commands,
     assets: Res<AssetServer>,
@@ -74,13 +132,14 @@ fn spawn_game_stuff(
     mut commands: Commands,
     assets: Res<AssetServer>,
     mut next_gameplay_state: ResMut<NextState<GamePlayState>>,
+    selected_biome: Res<SelectedBiome>,
     // mut next_level_state: ResMut<NextState<CorridorLevelState>>,
 ) {
     println!("Loading game plugin");
 
     commands.spawn((
         AudioBundle {
-            source: assets.load("music/gameplay.ogg"),
+            source: assets.load(selected_biome.0.get_music()),
             settings: PlaybackSettings {
                 mode: PlaybackMode::Loop,
                 volume: Volume::Absolute(VolumeLevel::new(0.3)),
@@ -108,11 +167,14 @@ pub struct GameLevel;
 
 pub fn reset_camera(
     // query: Query<(&Transform) /*(With<Player>)*/>,
-    mut camera_query: Query<&mut Transform, With<Camera> /*, Without<Player>*/>,
+    mut camera_query: Query<
+        (&mut Transform, &mut OrthographicProjection),
+        With<Camera>, /*, Without<Player>*/
+    >,
 ) {
-    // let player_transform = query.single();
-    let mut camera_transform = camera_query.single_mut();
-
-    //
-    camera_transform.translation = Vec3::new(0., 0., 0.);
+    // Co-op zooms the camera out to fit both players, so put the zoom back as well.
+    for (mut camera_transform, mut projection) in camera_query.iter_mut() {
+        camera_transform.translation = Vec3::new(0., 0., 0.);
+        projection.scale = CAMERA_SCALE;
+    }
 }
@@ -0,0 +1,571 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy::utils::HashMap;
+use rand::prelude::*;
+
+use crate::sprite_sheets::SpriteSheets;
+use crate::tiled_map::MapCollider;
+use crate::GameState;
+
+use super::biomes::SelectedBiome;
+use super::level::{
+    get_chunk_position, get_chunk_rng, FloorChunk, LevelSeed, MapMode, CHUNK_WORLD_SIZE,
+    MAP_MOVABLE_HEIGHT, MAP_MOVABLE_WIDTH,
+};
+use super::pools::EntityPools;
+use super::weapons::{Projectile, ProjectileCategory};
+use super::GamePlayState;
+
+pub struct ObstaclesPlugin;
+
+impl Plugin for ObstaclesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ObstacleMap>()
+            .add_systems(OnEnter(GamePlayState::Init), setup)
+            .add_systems(OnEnter(GamePlayState::Restart), unload)
+            .add_systems(OnExit(GameState::Gameplay), unload)
+            .add_systems(
+                Update,
+                (
+                    add_map_walls.before(spawn_chunk_obstacles),
+                    spawn_chunk_obstacles,
+                    remove_chunk_colliders,
+                )
+                    .run_if(resource_exists::<ObstacleAssets>()),
+            )
+            .add_systems(
+                Update,
+                block_projectiles
+                    .run_if(in_state(GamePlayState::Started).or_else(in_state(GamePlayState::Boss))),
+            );
+    }
+}
+
+// Obstacles come in loose clumps, a few per floor chunk (how many depends on the biome), which
+// leaves gaps to kite enemies through.
+const OBSTACLES_PER_CLUSTER_MAX: usize = 5;
+const CLUSTER_SPREAD: f32 = 70.;
+const FLOWER_BEDS_PER_CHUNK_MAX: usize = 3;
+
+// Nothing gets placed this close to the middle, so players never spawn boxed in.
+const CLEAR_SPAWN_RADIUS: f32 = 160.;
+
+// Room left between the arena map's walls and any obstacle, so they don't grow out of the walls.
+const WALL_CLEARANCE: f32 = 24.;
+
+// Keeps obstacle rolls from lining up with the floor tile rolls for the same chunk.
+const OBSTACLE_SEED_SALT: u64 = 0x0B57_AC1E;
+
+#[derive(Debug, Component)]
+pub struct Obstacle;
+
+#[derive(Debug, Component)]
+pub struct FlowerBed;
+
+// Round collider at the base of an obstacle, e.g. a tree trunk or the middle of a rock.
+#[derive(Debug, Clone, Copy)]
+pub struct ObstacleCollider {
+    pub position: Vec2,
+    pub radius: f32,
+    pub blocks_projectiles: bool,
+}
+
+// Colliders for every obstacle on the floor, grouped by floor chunk. They never move, so they're
+// kept here rather than queried, which lets any movement system check against them. Walls are
+// the collider rectangles from the biome's arena map, and block projectiles too.
+#[derive(Resource, Debug, Default)]
+pub struct ObstacleMap {
+    pub chunks: HashMap<IVec2, Vec<ObstacleCollider>>,
+    pub walls: Vec<Rect>,
+}
+
+impl ObstacleMap {
+    // Obstacles stay inside their own chunk, so only the chunks touching this one need checking.
+    fn get_nearby_colliders(&self, position: Vec2) -> impl Iterator<Item = &ObstacleCollider> {
+        let chunk = get_chunk_position(position);
+
+        (-1..=1)
+            .flat_map(move |y| (-1..=1).map(move |x| chunk + IVec2::new(x, y)))
+            .filter_map(|chunk| self.chunks.get(&chunk))
+            .flatten()
+    }
+
+    // Walls are checked as a box around the body, like the corridor does for the players.
+    fn is_in_wall(&self, position: Vec2, radius: f32) -> bool {
+        let body = Rect::from_center_size(position, Vec2::splat(radius * 2.));
+
+        self.walls
+            .iter()
+            .any(|wall| !wall.intersect(body).is_empty())
+    }
+
+    // Moves from `from` to `to`, sliding around any obstacle in the way instead of stopping dead,
+    // then pushes back out of anything still overlapping. Walls keep whichever axis of the move
+    // is clear, so a wall hit at an angle gets slid along.
+    pub fn get_position_around_obstacles(&self, from: Vec2, to: Vec2, radius: f32) -> Vec2 {
+        let mut movement = to - from;
+
+        for collider in self.get_nearby_colliders(to) {
+            let min_distance = radius + collider.radius;
+
+            if (from + movement).distance_squared(collider.position) >= min_distance * min_distance
+            {
+                continue;
+            }
+
+            let normal = (from - collider.position).normalize_or_zero();
+            let into = movement.dot(normal);
+
+            if into < 0. {
+                // Keep the speed but walk along the edge. Head-on, just pick a side.
+                let tangent = movement - normal * into;
+                let direction = if tangent.length_squared() > 0.0001 {
+                    tangent.normalize()
+                } else {
+                    normal.perp()
+                };
+
+                movement = direction * movement.length();
+            }
+        }
+
+        let mut position = from + movement;
+
+        for collider in self.get_nearby_colliders(to) {
+            let min_distance = radius + collider.radius;
+            let offset = position - collider.position;
+            let distance = offset.length();
+
+            if distance < min_distance {
+                let normal = if distance > 0. { offset / distance } else { Vec2::X };
+                position = collider.position + normal * min_distance;
+            }
+        }
+
+        // Anything that starts inside a wall, like an enemy spawned there, is let walk out.
+        if !self.is_in_wall(position, radius) || self.is_in_wall(from, radius) {
+            return position;
+        }
+
+        [Vec2::new(position.x, from.y), Vec2::new(from.x, position.y)]
+            .into_iter()
+            .find(|position| !self.is_in_wall(*position, radius))
+            .unwrap_or(from)
+    }
+
+    pub fn blocks_projectile_at(&self, position: Vec2) -> bool {
+        self.walls.iter().any(|wall| wall.contains(position))
+            || self.get_nearby_colliders(position).any(|collider| {
+                collider.blocks_projectiles
+                    && position.distance_squared(collider.position)
+                        < collider.radius * collider.radius
+            })
+    }
+}
+
+// Obstacle sprites cut out of their sheets once per run, shared by every chunk.
+#[derive(Resource, Debug)]
+struct ObstacleAssets {
+    kinds: Vec<ObstacleKind>,
+    kind_atlases: Vec<(Handle<TextureAtlas>, usize)>,
+    flower_kinds: Vec<ObstacleKind>,
+    flower_atlases: Vec<(Handle<TextureAtlas>, usize)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ObstacleSheet {
+    Trees,
+    Props,
+    Plants,
+    Flowers,
+}
+
+impl ObstacleSheet {
+    const ALL: [ObstacleSheet; 4] = [
+        ObstacleSheet::Trees,
+        ObstacleSheet::Props,
+        ObstacleSheet::Plants,
+        ObstacleSheet::Flowers,
+    ];
+
+    fn get_path(&self) -> &'static str {
+        match self {
+            ObstacleSheet::Trees => "sprites/level/trees.png",
+            ObstacleSheet::Props => "sprites/level/props.png",
+            ObstacleSheet::Plants => "sprites/level/tx_plant.png",
+            ObstacleSheet::Flowers => "sprites/level/flower_pipo.png",
+        }
+    }
+
+    fn get_size(&self) -> Vec2 {
+        match self {
+            ObstacleSheet::Trees => Vec2::new(384., 528.),
+            ObstacleSheet::Props => Vec2::new(480., 592.),
+            ObstacleSheet::Plants => Vec2::new(512., 512.),
+            ObstacleSheet::Flowers => Vec2::new(64., 96.),
+        }
+    }
+}
+
+// One sprite cut out of a sheet. `base` is how far up from the bottom of the sprite the collider
+// sits, so trees only block at the trunk and can be walked behind.
+#[derive(Debug, Clone)]
+struct ObstacleKind {
+    sheet: ObstacleSheet,
+    rect: Rect,
+    radius: f32,
+    base: f32,
+    blocks_projectiles: bool,
+}
+
+fn obstacle(
+    sheet: ObstacleSheet,
+    [x, y, width, height]: [f32; 4],
+    radius: f32,
+    base: f32,
+    blocks_projectiles: bool,
+) -> ObstacleKind {
+    ObstacleKind {
+        sheet,
+        rect: Rect::new(x, y, x + width, y + height),
+        radius,
+        base,
+        blocks_projectiles,
+    }
+}
+
+pub fn get_obstacle_sheet_paths() -> Vec<&'static str> {
+    ObstacleSheet::ALL
+        .iter()
+        .map(|sheet| sheet.get_path())
+        .collect()
+}
+
+fn get_obstacle_kinds() -> Vec<ObstacleKind> {
+    use ObstacleSheet::*;
+
+    vec![
+        // Small trees, autumn and summer
+        obstacle(Trees, [1., 0., 60., 86.], 7., 6., true),
+        obstacle(Trees, [64., 0., 61., 86.], 7., 6., true),
+        obstacle(Trees, [129., 2., 59., 84.], 7., 6., true),
+        obstacle(Trees, [193., 2., 59., 84.], 7., 6., true),
+        obstacle(Trees, [268., 9., 40., 77.], 5., 6., true),
+        obstacle(Trees, [332., 9., 40., 77.], 5., 6., true),
+        obstacle(Trees, [0., 112., 61., 86.], 7., 6., true),
+        obstacle(Trees, [64., 112., 61., 86.], 7., 6., true),
+        obstacle(Trees, [129., 114., 59., 84.], 7., 6., true),
+        obstacle(Trees, [193., 114., 59., 84.], 7., 6., true),
+        // Big trees
+        obstacle(Trees, [39., 213., 144., 147.], 14., 10., true),
+        obstacle(Trees, [199., 213., 144., 147.], 14., 10., true),
+        obstacle(Trees, [199., 373., 144., 147.], 14., 10., true),
+        obstacle(Plants, [24., 14., 113., 139.], 8., 8., true),
+        obstacle(Plants, [161., 17., 97., 136.], 8., 8., true),
+        obstacle(Plants, [295., 31., 79., 120.], 7., 8., true),
+        // Bushes - solid, but low enough to throw things over
+        obstacle(Plants, [156., 190., 38., 32.], 14., 14., false),
+        obstacle(Plants, [216., 185., 47., 42.], 18., 18., false),
+        obstacle(Plants, [346., 190., 40., 35.], 15., 15., false),
+        obstacle(Props, [385., 196., 30., 25.], 11., 11., false),
+        obstacle(Props, [417., 195., 29., 27.], 11., 11., false),
+        // Rocks
+        obstacle(Props, [2., 34., 27., 26.], 11., 12., true),
+        obstacle(Props, [35., 34., 27., 26.], 11., 12., true),
+        obstacle(Props, [100., 33., 26., 28.], 11., 13., true),
+        obstacle(Props, [2., 98., 27., 26.], 11., 12., true),
+        obstacle(Props, [100., 97., 26., 28.], 11., 13., true),
+        obstacle(Props, [3., 0., 45., 29.], 17., 13., true),
+        obstacle(Props, [2., 64., 46., 29.], 17., 13., true),
+        // Toadstools
+        obstacle(Props, [242., 288., 29., 46.], 7., 8., false),
+        obstacle(Props, [274., 288., 29., 46.], 7., 8., false),
+        obstacle(Props, [306., 288., 29., 46.], 7., 8., false),
+    ]
+}
+
+// Flower patches are just for looks, so players walk straight over them.
+fn get_flower_bed_kinds() -> Vec<ObstacleKind> {
+    vec![
+        obstacle(ObstacleSheet::Flowers, [0., 0., 64., 32.], 0., 0., false),
+        obstacle(ObstacleSheet::Flowers, [0., 32., 64., 64.], 0., 0., false),
+    ]
+}
+
+// One atlas per sheet with a texture per kind, returning the atlas and index for each kind in
+// the same order. Atlases are registered under the sheet and its rects, so later runs reuse them.
+fn get_kind_atlases(
+    kinds: &[ObstacleKind],
+    sheets: &mut SpriteSheets,
+) -> Vec<(Handle<TextureAtlas>, usize)> {
+    let mut sheet_rects: Vec<(ObstacleSheet, Vec<Rect>)> = vec![];
+    let mut indices: Vec<(ObstacleSheet, usize)> = vec![];
+
+    for kind in kinds.iter() {
+        let position = match sheet_rects.iter().position(|(sheet, _)| *sheet == kind.sheet) {
+            Some(position) => position,
+            None => {
+                sheet_rects.push((kind.sheet, vec![]));
+                sheet_rects.len() - 1
+            }
+        };
+
+        let rects = &mut sheet_rects[position].1;
+        indices.push((kind.sheet, rects.len()));
+        rects.push(kind.rect);
+    }
+
+    let handles: Vec<(ObstacleSheet, Handle<TextureAtlas>)> = sheet_rects
+        .into_iter()
+        .map(|(sheet, rects)| {
+            let id = format!("{}#{:?}", sheet.get_path(), rects);
+
+            let handle = sheets.get_or_add(id, |assets| {
+                let mut atlas =
+                    TextureAtlas::new_empty(assets.load(sheet.get_path()), sheet.get_size());
+
+                for rect in rects.iter() {
+                    atlas.add_texture(*rect);
+                }
+
+                atlas
+            });
+
+            (sheet, handle)
+        })
+        .collect();
+
+    indices
+        .into_iter()
+        .map(|(sheet, index)| {
+            let (_, handle) = handles
+                .iter()
+                .find(|(other, _)| *other == sheet)
+                .unwrap();
+
+            (handle.clone(), index)
+        })
+        .collect()
+}
+
+// Random spot inside the chunk, kept far enough from its edges that obstacles never poke out.
+fn get_random_chunk_position(rng: &mut impl Rng, chunk: IVec2, margin: f32) -> Vec2 {
+    let origin = chunk.as_vec2() * CHUNK_WORLD_SIZE;
+
+    origin
+        + Vec2::new(
+            rng.gen_range(margin..CHUNK_WORLD_SIZE - margin),
+            rng.gen_range(margin..CHUNK_WORLD_SIZE - margin),
+        )
+}
+
+// Lower obstacles draw over higher ones, all between the floor and enemies. The map has no fixed
+// height in endless mode, so y gets squashed into 0.5..1.5 instead of divided by the map size.
+fn get_obstacle_z(y: f32) -> f32 {
+    1. - (y / (CHUNK_WORLD_SIZE * 8.)).atan() / std::f32::consts::PI
+}
+
+fn is_placeable(position: Vec2, map_mode: &MapMode, walls: &[Rect]) -> bool {
+    if position.length() < CLEAR_SPAWN_RADIUS {
+        return false;
+    }
+
+    if walls
+        .iter()
+        .any(|wall| wall.inset(WALL_CLEARANCE).contains(position))
+    {
+        return false;
+    }
+
+    match map_mode {
+        MapMode::Arena => {
+            position.x.abs() < MAP_MOVABLE_WIDTH / 2. && position.y.abs() < MAP_MOVABLE_HEIGHT / 2.
+        }
+        MapMode::Endless => true,
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut sheets: SpriteSheets,
+    mut obstacle_map: ResMut<ObstacleMap>,
+) {
+    println!("Game play obstacles setup");
+
+    let kinds = get_obstacle_kinds();
+    let kind_atlases = get_kind_atlases(&kinds, &mut sheets);
+
+    let flower_kinds = get_flower_bed_kinds();
+    let flower_atlases = get_kind_atlases(&flower_kinds, &mut sheets);
+
+    obstacle_map.chunks.clear();
+    obstacle_map.walls.clear();
+
+    commands.insert_resource(ObstacleAssets {
+        kinds,
+        kind_atlases,
+        flower_kinds,
+        flower_atlases,
+    });
+}
+
+// Picks up the collider rectangles of the arena map as it spawns. They come in on the same frame
+// as the first floor chunks, so this runs first to keep obstacles off them.
+fn add_map_walls(
+    collider_query: Query<&MapCollider, Added<MapCollider>>,
+    mut obstacle_map: ResMut<ObstacleMap>,
+) {
+    obstacle_map
+        .walls
+        .extend(collider_query.iter().map(|collider| collider.rect));
+}
+
+// Fills in obstacles and flower beds for each new floor chunk. They're spawned as children of
+// the chunk so they go away with it, and rolled from the chunk's own seed so a chunk that streams
+// back in looks the same as before.
+fn spawn_chunk_obstacles(
+    mut commands: Commands,
+    chunk_query: Query<(&FloorChunk, Entity), Added<FloorChunk>>,
+    obstacle_assets: Res<ObstacleAssets>,
+    mut obstacle_map: ResMut<ObstacleMap>,
+    level_seed: Res<LevelSeed>,
+    map_mode: Res<MapMode>,
+    selected_biome: Res<SelectedBiome>,
+) {
+    let clusters_max = selected_biome.0.get_obstacle_clusters_max();
+
+    for (chunk, chunk_entity) in chunk_query.iter() {
+        let mut rng = get_chunk_rng(level_seed.0 ^ OBSTACLE_SEED_SALT, chunk.position);
+        let origin = chunk.position.as_vec2() * CHUNK_WORLD_SIZE;
+        let mut colliders: Vec<ObstacleCollider> = vec![];
+
+        commands.entity(chunk_entity).with_children(|commands| {
+            for _ in 0..rng.gen_range(0..=clusters_max) {
+                let center = get_random_chunk_position(&mut rng, chunk.position, CLUSTER_SPREAD * 2.);
+
+                for _ in 0..rng.gen_range(1..=OBSTACLES_PER_CLUSTER_MAX) {
+                    let position = center
+                        + Vec2::new(
+                            rng.gen_range(-CLUSTER_SPREAD..CLUSTER_SPREAD),
+                            rng.gen_range(-CLUSTER_SPREAD..CLUSTER_SPREAD),
+                        );
+
+                    let kind_index = rng.gen_range(0..obstacle_assets.kinds.len());
+                    let kind = &obstacle_assets.kinds[kind_index];
+
+                    let is_overlapping = colliders.iter().any(|collider| {
+                        collider.position.distance(position) < collider.radius + kind.radius + 4.
+                    });
+
+                    if is_overlapping || !is_placeable(position, &map_mode, &obstacle_map.walls) {
+                        continue;
+                    }
+
+                    let (texture_atlas, index) = obstacle_assets.kind_atlases[kind_index].clone();
+                    let local = position - origin;
+
+                    commands.spawn((
+                        SpriteSheetBundle {
+                            texture_atlas,
+                            sprite: TextureAtlasSprite {
+                                index,
+                                // Put the collider point at the entity position.
+                                anchor: Anchor::Custom(Vec2::new(
+                                    0.,
+                                    -0.5 + kind.base / kind.rect.height(),
+                                )),
+                                ..default()
+                            },
+                            // Relative to the chunk, which sits at -0.4.
+                            transform: Transform::from_xyz(
+                                local.x,
+                                local.y,
+                                get_obstacle_z(position.y) + 0.4,
+                            ),
+                            ..default()
+                        },
+                        Obstacle,
+                        Name::new("Obstacle"),
+                    ));
+
+                    colliders.push(ObstacleCollider {
+                        position,
+                        radius: kind.radius,
+                        blocks_projectiles: kind.blocks_projectiles,
+                    });
+                }
+            }
+
+            // Flowers only grow where obstacles do.
+            let flower_beds_max = FLOWER_BEDS_PER_CHUNK_MAX.min(clusters_max);
+
+            for _ in 0..rng.gen_range(0..=flower_beds_max) {
+                let position = get_random_chunk_position(&mut rng, chunk.position, 32.);
+                let kind_index = rng.gen_range(0..obstacle_assets.flower_kinds.len());
+
+                if !is_placeable(position, &map_mode, &obstacle_map.walls) {
+                    continue;
+                }
+
+                let (texture_atlas, index) = obstacle_assets.flower_atlases[kind_index].clone();
+                let local = position - origin;
+
+                commands.spawn((
+                    SpriteSheetBundle {
+                        texture_atlas,
+                        sprite: TextureAtlasSprite::new(index),
+                        transform: Transform::from_xyz(local.x, local.y, 0.1),
+                        ..default()
+                    },
+                    FlowerBed,
+                    Name::new("Flower Bed"),
+                ));
+            }
+        });
+
+        obstacle_map.chunks.insert(chunk.position, colliders);
+    }
+}
+
+// Drops the colliders for any chunk that has streamed out.
+fn remove_chunk_colliders(
+    mut removed_chunks: RemovedComponents<FloorChunk>,
+    chunk_query: Query<&FloorChunk>,
+    mut obstacle_map: ResMut<ObstacleMap>,
+) {
+    if removed_chunks.read().next().is_none() {
+        return;
+    }
+
+    obstacle_map
+        .chunks
+        .retain(|position, _| chunk_query.iter().any(|chunk| chunk.position == *position));
+}
+
+fn block_projectiles(
+    mut commands: Commands,
+    projectile_query: Query<(&Transform, &Projectile, Entity)>,
+    obstacle_map: Res<ObstacleMap>,
+    mut pools: ResMut<EntityPools>,
+) {
+    for (transform, projectile, entity) in projectile_query.iter() {
+        // Instant weapons land on the target directly, so only thrown and fired ones get stopped.
+        if projectile.props.projectile_category != ProjectileCategory::Projectile
+            && projectile.props.projectile_category != ProjectileCategory::ProjectileAoe
+        {
+            continue;
+        }
+
+        if obstacle_map.blocks_projectile_at(transform.translation.truncate()) {
+            pools.release_projectile(&mut commands, entity);
+        }
+    }
+}
+
+// Obstacle entities go with their floor chunks, and walls with the map, so only the colliders
+// and sprites need clearing.
+fn unload(mut commands: Commands, mut obstacle_map: ResMut<ObstacleMap>) {
+    obstacle_map.chunks.clear();
+    obstacle_map.walls.clear();
+    commands.remove_resource::<ObstacleAssets>();
+}
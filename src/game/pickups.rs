@@ -0,0 +1,466 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::sprite::Health;
+use crate::GameState;
+
+use super::lvl_up_ui::PendingLevelUps;
+use super::player::{get_players_center, CanLevel, Downed, Player};
+use super::pools::EntityPools;
+use super::projectile_spawner::{
+    spawn_scaled_explosion_at_position, DamageEvent, DamageType, EnemyKilledEvent,
+};
+use super::spawner::{Enemy, EnemyRank};
+use super::weapons::has_available_weapons;
+use super::GamePlayState;
+
+// Things lying on the floor for the players to walk over: the XP gems every kill drops, and the
+// odd item from a drop table or the pickup timer.
+pub struct PickupsPlugin;
+
+impl Plugin for PickupsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GamePlayState::Init), setup)
+            .add_systems(
+                Update,
+                (
+                    spawn_kill_drops,
+                    spawn_timed_pickups,
+                    magnetize_nearby_gems,
+                    move_magnetized_pickups,
+                    collect_pickups,
+                    update_pickup_pops,
+                    update_enemy_freeze,
+                )
+                    .run_if(
+                        in_state(GamePlayState::Started).or_else(in_state(GamePlayState::Boss)),
+                    ),
+            )
+            .add_systems(OnEnter(GamePlayState::Restart), unload)
+            .add_systems(OnExit(GameState::Gameplay), unload);
+    }
+}
+
+const PICKUP_Z: f32 = 1.4;
+const PICKUP_COLLECT_DISTANCE: f32 = 14.;
+// Gems this close to a player fly to them on their own, no magnet needed.
+const GEM_ATTRACT_DISTANCE: f32 = 40.;
+const MAGNETIZED_SPEED: f32 = 260.;
+
+const TIMED_PICKUP_SECONDS: f32 = 40.;
+const TIMED_PICKUP_DISTANCE: f32 = 120.;
+
+const POTION_HEAL: f32 = 30.;
+const FREEZE_SECONDS: f32 = 5.;
+const FREEZE_TINT: Color = Color::rgba(0.55, 0.8, 1., 0.25);
+const POP_SECONDS: f32 = 0.4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PickupKind {
+    Experience(u64),
+    HealthPotion,
+    Magnet,
+    BouquetBomb,
+    FreezeClock,
+}
+
+impl PickupKind {
+    const ITEMS: [PickupKind; 4] = [
+        PickupKind::HealthPotion,
+        PickupKind::Magnet,
+        PickupKind::BouquetBomb,
+        PickupKind::FreezeClock,
+    ];
+
+    fn get_sprite(&self) -> &'static str {
+        match self {
+            PickupKind::Experience(_) => "sprites/items/xp_gem.png",
+            PickupKind::HealthPotion => "sprites/items/potion.png",
+            PickupKind::Magnet => "sprites/items/magnet.png",
+            PickupKind::BouquetBomb => "sprites/items/bouquet.png",
+            PickupKind::FreezeClock => "sprites/items/clock.png",
+        }
+    }
+}
+
+pub fn get_pickup_sprite_paths() -> Vec<&'static str> {
+    std::iter::once(PickupKind::Experience(0))
+        .chain(PickupKind::ITEMS)
+        .map(|kind| kind.get_sprite())
+        .collect()
+}
+
+// Chance of each item dropping from a kill, on top of the XP gem, by how tough the enemy was.
+fn get_drop_table(rank: EnemyRank) -> [(PickupKind, f64); 4] {
+    match rank {
+        EnemyRank::Normal => [
+            (PickupKind::HealthPotion, 0.015),
+            (PickupKind::Magnet, 0.004),
+            (PickupKind::BouquetBomb, 0.002),
+            (PickupKind::FreezeClock, 0.003),
+        ],
+        EnemyRank::Elite | EnemyRank::MidBoss => [
+            (PickupKind::HealthPotion, 0.4),
+            (PickupKind::Magnet, 0.25),
+            (PickupKind::BouquetBomb, 0.1),
+            (PickupKind::FreezeClock, 0.15),
+        ],
+        EnemyRank::Boss => [
+            (PickupKind::HealthPotion, 0.),
+            (PickupKind::Magnet, 0.),
+            (PickupKind::BouquetBomb, 0.),
+            (PickupKind::FreezeClock, 0.),
+        ],
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct Pickup {
+    pub kind: PickupKind,
+}
+
+// Flying towards the nearest player.
+#[derive(Component, Debug)]
+struct Magnetized;
+
+// Collected item sprite growing and fading where it was picked up.
+#[derive(Component, Debug)]
+struct PickupPop {
+    timer: Timer,
+}
+
+// Enemies stand still while this is around - see the spawner's run conditions.
+#[derive(Resource, Debug)]
+pub struct EnemyFreeze {
+    timer: Timer,
+}
+
+#[derive(Component)]
+struct FreezeOverlay;
+
+#[derive(Resource, Debug)]
+struct PickupTimer(Timer);
+
+fn setup(mut commands: Commands) {
+    commands.insert_resource(PickupTimer(Timer::from_seconds(
+        TIMED_PICKUP_SECONDS,
+        TimerMode::Repeating,
+    )));
+}
+
+fn unload(
+    mut commands: Commands,
+    pickup_query: Query<Entity, Or<(With<Pickup>, With<PickupPop>, With<FreezeOverlay>)>>,
+) {
+    for entity in pickup_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    commands.remove_resource::<EnemyFreeze>();
+}
+
+fn spawn_pickup(
+    commands: &mut Commands,
+    assets: &Res<AssetServer>,
+    kind: PickupKind,
+    position: Vec2,
+) {
+    commands.spawn((
+        SpriteBundle {
+            texture: assets.load(kind.get_sprite()),
+            transform: Transform::from_translation(position.extend(PICKUP_Z)),
+            ..default()
+        },
+        Pickup { kind },
+        Name::new("Pickup"),
+    ));
+}
+
+fn spawn_kill_drops(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    mut enemy_killed_events: EventReader<EnemyKilledEvent>,
+) {
+    let mut rng = rand::thread_rng();
+
+    for event in enemy_killed_events.read() {
+        let position = event.position.truncate();
+
+        if event.experience > 0 {
+            spawn_pickup(
+                &mut commands,
+                &assets,
+                PickupKind::Experience(event.experience),
+                position,
+            );
+        }
+
+        for (kind, chance) in get_drop_table(event.rank) {
+            if chance > 0. && rng.gen_bool(chance) {
+                // Nudged off the gem so both can be seen.
+                let offset = Vec2::new(rng.gen_range(-10. ..10.), rng.gen_range(-10. ..10.));
+                spawn_pickup(&mut commands, &assets, kind, position + offset);
+            }
+        }
+    }
+}
+
+fn spawn_timed_pickups(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    time: Res<Time>,
+    mut pickup_timer: ResMut<PickupTimer>,
+    player_query: Query<&Transform, With<Player>>,
+) {
+    if !pickup_timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let Some(players_center) = get_players_center(player_query.iter()) else {
+        return;
+    };
+
+    let mut rng = rand::thread_rng();
+    let kind = PickupKind::ITEMS[rng.gen_range(0..PickupKind::ITEMS.len())];
+    let angle = rng.gen_range(0. ..std::f32::consts::TAU);
+
+    spawn_pickup(
+        &mut commands,
+        &assets,
+        kind,
+        players_center.truncate() + Vec2::from_angle(angle) * TIMED_PICKUP_DISTANCE,
+    );
+}
+
+fn magnetize_nearby_gems(
+    mut commands: Commands,
+    pickup_query: Query<(&Transform, &Pickup, Entity), Without<Magnetized>>,
+    player_query: Query<&Transform, (With<Player>, Without<Downed>)>,
+) {
+    for (transform, pickup, entity) in pickup_query.iter() {
+        if !matches!(pickup.kind, PickupKind::Experience(_)) {
+            continue;
+        }
+
+        let is_near = player_query.iter().any(|player_transform| {
+            player_transform
+                .translation
+                .truncate()
+                .distance(transform.translation.truncate())
+                < GEM_ATTRACT_DISTANCE
+        });
+
+        if is_near {
+            commands.entity(entity).try_insert(Magnetized);
+        }
+    }
+}
+
+fn move_magnetized_pickups(
+    time: Res<Time>,
+    mut pickup_query: Query<&mut Transform, (With<Magnetized>, Without<Player>)>,
+    player_query: Query<&Transform, (With<Player>, Without<Downed>)>,
+) {
+    for mut transform in pickup_query.iter_mut() {
+        let position = transform.translation.truncate();
+
+        let nearest = player_query
+            .iter()
+            .map(|player_transform| player_transform.translation.truncate())
+            .min_by(|a, b| a.distance(position).total_cmp(&b.distance(position)));
+
+        if let Some(target) = nearest {
+            let step =
+                (target - position).clamp_length_max(MAGNETIZED_SPEED * time.delta_seconds());
+            transform.translation += step.extend(0.);
+        }
+    }
+}
+
+fn collect_pickups(
+    mut commands: Commands,
+    mut pools: ResMut<EntityPools>,
+    pickup_query: Query<(&Transform, &Pickup, &Handle<Image>, Entity), Without<Player>>,
+    mut player_query: Query<(&Transform, &mut Health, Entity), (With<Player>, Without<Downed>)>,
+    // Downed players still get their share of XP, so everyone stays on the same level.
+    mut level_query: Query<(&mut CanLevel, &Player, Entity)>,
+    enemy_query: Query<(&Transform, &Enemy, Entity), Without<Player>>,
+    camera_query: Query<(&Transform, &OrthographicProjection), With<Camera>>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut pending_level_ups: ResMut<PendingLevelUps>,
+    mut next_play_state: ResMut<NextState<GamePlayState>>,
+) {
+    for (pickup_transform, pickup, texture, pickup_entity) in pickup_query.iter() {
+        let position = pickup_transform.translation;
+
+        let collector = player_query
+            .iter()
+            .find_map(|(player_transform, .., entity)| {
+                (player_transform
+                    .translation
+                    .truncate()
+                    .distance(position.truncate())
+                    < PICKUP_COLLECT_DISTANCE)
+                    .then_some(entity)
+            });
+
+        let Some(collector) = collector else {
+            continue;
+        };
+
+        commands.entity(pickup_entity).despawn_recursive();
+
+        match pickup.kind {
+            // XP is shared - every gem counts for each player, and each gets their own pick.
+            PickupKind::Experience(experience) => {
+                for (mut lvl, player, player_entity) in level_query.iter_mut() {
+                    if add_player_experience(experience, &mut lvl) {
+                        println!("Player leveled up to {}", lvl.level);
+                        if has_available_weapons(&player.weapons) {
+                            pending_level_ups.players.push_back(player_entity);
+                            next_play_state.set(GamePlayState::LevelUp);
+                        }
+                    }
+                }
+
+                continue;
+            }
+            PickupKind::HealthPotion => {
+                if let Ok((_, mut health, ..)) = player_query.get_mut(collector) {
+                    health.heal(POTION_HEAL);
+                }
+            }
+            PickupKind::Magnet => {
+                for (_, other, _, entity) in pickup_query.iter() {
+                    if matches!(other.kind, PickupKind::Experience(_)) {
+                        commands.entity(entity).try_insert(Magnetized);
+                    }
+                }
+            }
+            PickupKind::BouquetBomb => {
+                let Ok((camera_transform, projection)) = camera_query.get_single() else {
+                    continue;
+                };
+
+                let camera_position = camera_transform.translation.truncate();
+                let screen = Rect::from_corners(
+                    camera_position + projection.area.min,
+                    camera_position + projection.area.max,
+                );
+
+                for (enemy_transform, enemy, entity) in enemy_query.iter() {
+                    let is_boss = matches!(enemy.rank, EnemyRank::MidBoss | EnemyRank::Boss);
+
+                    if !is_boss && screen.contains(enemy_transform.translation.truncate()) {
+                        damage_events.send(DamageEvent {
+                            damage: f32::MAX,
+                            entity,
+                            damage_type: DamageType::Normal,
+                            is_critical: false,
+                            knockback: Vec3::ZERO,
+                        });
+                    }
+                }
+
+                spawn_scaled_explosion_at_position(
+                    &mut pools,
+                    &mut commands,
+                    &position,
+                    4.,
+                );
+            }
+            PickupKind::FreezeClock => {
+                commands.insert_resource(EnemyFreeze {
+                    timer: Timer::from_seconds(FREEZE_SECONDS, TimerMode::Once),
+                });
+            }
+        }
+
+        commands.spawn((
+            SpriteBundle {
+                texture: texture.clone(),
+                transform: Transform::from_translation(position),
+                ..default()
+            },
+            PickupPop {
+                timer: Timer::from_seconds(POP_SECONDS, TimerMode::Once),
+            },
+        ));
+    }
+}
+
+// Return true if lvled up
+fn add_player_experience(experience: u64, lvl: &mut CanLevel) -> bool {
+    lvl.experience += experience;
+
+    if lvl.experience >= lvl.level_step {
+        lvl.experience -= lvl.level_step;
+        lvl.level += 1;
+
+        return true;
+    }
+
+    false
+}
+
+fn spawn_freeze_overlay(commands: &mut Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            background_color: FREEZE_TINT.into(),
+            ..default()
+        },
+        FreezeOverlay,
+        Name::new("Freeze Overlay"),
+    ));
+}
+
+fn update_pickup_pops(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut pop_query: Query<(&mut Transform, &mut Sprite, &mut PickupPop, Entity)>,
+) {
+    for (mut transform, mut sprite, mut pop, entity) in pop_query.iter_mut() {
+        pop.timer.tick(time.delta());
+
+        if pop.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let scale = 1. + pop.timer.percent();
+        transform.scale = Vec3::new(scale, scale, 1.);
+        sprite.color.set_a(pop.timer.percent_left());
+    }
+}
+
+fn update_enemy_freeze(
+    mut commands: Commands,
+    time: Res<Time>,
+    freeze: Option<ResMut<EnemyFreeze>>,
+    overlay_query: Query<Entity, With<FreezeOverlay>>,
+) {
+    let Some(mut freeze) = freeze else {
+        return;
+    };
+
+    // Another clock while frozen just starts the timer over, under the same tint.
+    if overlay_query.is_empty() {
+        spawn_freeze_overlay(&mut commands);
+    }
+
+    if !freeze.timer.tick(time.delta()).finished() {
+        return;
+    }
+
+    commands.remove_resource::<EnemyFreeze>();
+
+    for overlay in overlay_query.iter() {
+        commands.entity(overlay).despawn_recursive();
+    }
+}
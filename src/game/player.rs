@@ -1,19 +1,23 @@
 use crate::character_select::{
-    get_character_sprite, SelectedCharacterState, PLAYER_HEIGHT, PLAYER_WIDTH,
+    get_character_sprite, CharacterBlock, CharacterPassive, CharacterRegistry, PassiveEffect,
+    SelectedCharacter, PLAYER_HEIGHT, PLAYER_WIDTH,
 };
 use crate::corridor::player::{get_character_block, get_indices_for_movable_direction};
 use crate::sprite::{AnimationTimer, Direction, Health, Movable, PlayerSpriteSheetAnimatable};
-use crate::GameState;
+use crate::meta::MetaProgress;
+use crate::sprite_sheets::SpriteSheets;
+use crate::{GameState, CAMERA_SCALE};
 
 use bevy::prelude::*;
 
-use super::level::{MAP_MOVABLE_HEIGHT, MAP_MOVABLE_WIDTH};
-
-use super::weapons::Weapon;
+use super::abilities::{Dash, Ultimate};
+use super::controls::{PlayerControls, PlayerInput};
+use super::level::{MapMode, MAP_MOVABLE_HEIGHT, MAP_MOVABLE_WIDTH};
+use super::obstacles::ObstacleMap;
+use super::stats::{PlayerStats, Stat};
+use super::weapons::{get_weapon_for_type, StatUpgrade, Weapon};
 use super::GamePlayState;
 
-const PLAYER_SPEED_DEFAULT: f32 = 100.;
-
 pub struct PlayerPlugin;
 
 #[derive(Debug, Component)]
@@ -25,12 +29,81 @@ pub struct CanLevel {
 
 #[derive(Component)]
 pub struct Player {
+    pub index: usize, // 0 for player one, 1 for player two in co-op
     pub weapons: Vec<Weapon>,
+    pub passives: Vec<StatUpgrade>, // Picked up from chests, in order
+}
+
+// Fallen co-op player, out of the fight until their partner walks over to pick them up.
+#[derive(Debug, Component)]
+pub struct Downed;
+
+const REVIVE_DISTANCE: f32 = 20.;
+const REVIVE_HEALTH_PERCENT: f32 = 0.5;
+const REVIVE_INVULNERABLE_SECONDS: f32 = 2.;
+const DOWNED_COLOR: Color = Color::rgba(0.4, 0.4, 0.4, 0.7);
+
+// World area the camera keeps the players inside before it starts zooming out. Players can't
+// get further apart than what fits on screen at the max zoom.
+const CAMERA_FRAMING_AREA: Vec2 = Vec2::new(460., 250.);
+const MAX_CAMERA_ZOOM: f32 = 1.7;
+const PLAYER_SPAWN_SPACING: f32 = 30.;
+
+// Players bump into obstacles at their feet rather than the middle of the sprite.
+const PLAYER_FEET_OFFSET: f32 = PLAYER_HEIGHT / 2. - 4.;
+const PLAYER_COLLISION_RADIUS: f32 = 6.;
+
+// Enemies can't deal contact damage while this is on the player.
+#[derive(Debug, Component)]
+pub struct Invulnerable {
+    pub timer: Timer,
+}
+
+impl Invulnerable {
+    pub fn new(seconds: f32) -> Self {
+        Self {
+            timer: Timer::from_seconds(seconds, TimerMode::Once),
+        }
+    }
+}
+
+// Anything that hurts a player sends one of these rather than touching their Health, so armor,
+// i-frames and the hit feedback are all sorted out in one place - see damage_players.
+#[derive(Event, Debug)]
+pub struct PlayerDamageEvent {
+    pub entity: Entity,
+    pub damage: f32,
+    // Where the hit came from, the player is knocked away from it.
+    pub source: Vec3,
+}
+
+// No hit sound ships with the game. Point this at a file under assets/ to have it play whenever
+// a player takes damage.
+#[derive(Resource, Debug, Default)]
+pub struct PlayerHitSound(pub Option<String>);
+
+// However much armor a player has, every hit still does at least this much.
+const MIN_PLAYER_DAMAGE: f32 = 1.;
+const HIT_INVULNERABLE_SECONDS: f32 = 0.8;
+const HIT_FLASH_SECONDS: f32 = 0.15;
+const HIT_FLASH_COLOR: Color = Color::rgb(1., 0.3, 0.3);
+const HIT_BLINK_SECONDS: f32 = 0.08;
+const HIT_BLINK_ALPHA: f32 = 0.35;
+const KNOCKBACK_SPEED: f32 = 220.;
+const KNOCKBACK_SECONDS: f32 = 0.1;
+
+// Flashes red, then blinks for as long as the hit's i-frames last, while being knocked back.
+#[derive(Debug, Component)]
+struct HitFeedback {
+    timer: Timer,
+    knockback: Vec3,
 }
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GamePlayState::Init), setup)
+        app.add_event::<PlayerDamageEvent>()
+            .init_resource::<PlayerHitSound>()
+            .add_systems(OnEnter(GamePlayState::Init), setup)
             .add_systems(OnEnter(GamePlayState::Restart), unload)
             .add_systems(OnExit(GameState::Gameplay), unload)
             .add_systems(
@@ -38,7 +111,12 @@ impl Plugin for PlayerPlugin {
                 (
                     player_movement,
                     update_camera_from_player_position,
+                    damage_players.before(check_health),
+                    update_hit_feedback,
                     check_health,
+                    revive_downed_players,
+                    apply_regeneration,
+                    update_invulnerability,
                 )
                     .run_if(in_state(GamePlayState::Started)),
             )
@@ -47,7 +125,12 @@ impl Plugin for PlayerPlugin {
                 (
                     player_movement,
                     update_camera_from_player_position,
+                    damage_players.before(check_health),
+                    update_hit_feedback,
                     check_health,
+                    revive_downed_players,
+                    apply_regeneration,
+                    update_invulnerability,
                 )
                     .run_if(in_state(GamePlayState::Boss)),
             );
@@ -60,31 +143,317 @@ pub fn unload(mut query: Query<Entity, With<Player>>, mut commands: Commands) {
     }
 }
 
+// Characters playing this run - the selected one, plus their partner in co-op.
+pub fn get_selected_characters<'a>(
+    registry: &'a CharacterRegistry,
+    selected_character: &SelectedCharacter,
+) -> Vec<&'a CharacterBlock> {
+    let mut characters: Vec<&CharacterBlock> =
+        registry.get(&selected_character.id).into_iter().collect();
+
+    if selected_character.coop {
+        characters.extend(registry.get_partner(&selected_character.id));
+    }
+
+    characters
+}
+
+// Midpoint between all players, which is what the camera and enemy waves centre on.
+pub fn get_players_center<'a>(transforms: impl Iterator<Item = &'a Transform>) -> Option<Vec3> {
+    let (min, max) = get_players_bounds(transforms)?;
+
+    Some((min + max) / 2.)
+}
+
+fn get_players_bounds<'a>(transforms: impl Iterator<Item = &'a Transform>) -> Option<(Vec3, Vec3)> {
+    transforms.fold(None, |bounds, transform| match bounds {
+        None => Some((transform.translation, transform.translation)),
+        Some((min, max)) => Some((min.min(transform.translation), max.max(transform.translation))),
+    })
+}
+
 pub fn update_camera_from_player_position(
     query: Query<&Transform, With<Player>>,
-    mut camera_query: Query<&mut Transform, (With<Camera>, Without<Player>)>,
+    mut camera_query: Query<
+        (&mut Transform, &mut OrthographicProjection),
+        (With<Camera>, Without<Player>),
+    >,
 ) {
-    let player_transform = query.single();
-
-    let mut camera_transform = camera_query.single_mut();
-
-    //
-    camera_transform.translation.x = player_transform.translation.x;
-    camera_transform.translation.y = player_transform.translation.y;
+    let Some((min, max)) = get_players_bounds(query.iter()) else {
+        return;
+    };
+
+    let center = (min + max) / 2.;
+    let zoom = ((max - min).truncate() / CAMERA_FRAMING_AREA)
+        .max_element()
+        .clamp(1., MAX_CAMERA_ZOOM);
+
+    for (mut camera_transform, mut projection) in camera_query.iter_mut() {
+        camera_transform.translation.x = center.x;
+        camera_transform.translation.y = center.y;
+        projection.scale = CAMERA_SCALE * zoom;
+    }
 }
 
+// A player at zero health goes down. It's only game over once nobody is left standing, which
+// in single player is straight away.
 pub fn check_health(
-    query: Query<&Health, With<Player>>,
+    mut commands: Commands,
+    mut query: Query<(&Health, &mut TextureAtlasSprite, Entity, Option<&Downed>), With<Player>>,
     mut next_state: ResMut<NextState<GamePlayState>>,
 ) {
-    let player_health = query.single();
+    if query.is_empty() {
+        return;
+    }
+
+    let mut players_standing = 0;
+
+    for (health, mut sprite, entity, downed) in query.iter_mut() {
+        if downed.is_some() {
+            continue;
+        }
 
-    if player_health.total <= 0. {
+        if health.total <= 0. {
+            println!("Player has fallen");
+            sprite.color = DOWNED_COLOR;
+            commands
+                .entity(entity)
+                .insert(Downed)
+                .remove::<(Invulnerable, HitFeedback)>();
+        } else {
+            players_standing += 1;
+        }
+    }
+
+    if players_standing == 0 {
         println!("Player has died");
         next_state.set(GamePlayState::GameOver);
     }
 }
 
+pub fn revive_downed_players(
+    mut commands: Commands,
+    mut downed_query: Query<
+        (&mut Health, &mut TextureAtlasSprite, &Transform, Entity),
+        (With<Player>, With<Downed>),
+    >,
+    rescuer_query: Query<&Transform, (With<Player>, Without<Downed>)>,
+) {
+    for (mut health, mut sprite, transform, entity) in downed_query.iter_mut() {
+        let is_touched = rescuer_query.iter().any(|rescuer| {
+            rescuer
+                .translation
+                .truncate()
+                .distance(transform.translation.truncate())
+                < REVIVE_DISTANCE
+        });
+
+        if !is_touched {
+            continue;
+        }
+
+        println!("Player revived");
+
+        health.total = health.max * REVIVE_HEALTH_PERCENT;
+        sprite.color = Color::WHITE;
+
+        commands
+            .entity(entity)
+            .remove::<Downed>()
+            .insert(Invulnerable::new(REVIVE_INVULNERABLE_SECONDS));
+    }
+}
+
+// Only the arena has walls, endless maps let players wander as far as they like.
+pub fn clamp_to_movable_area(translation: &mut Vec3, map_mode: &MapMode) {
+    if *map_mode == MapMode::Endless {
+        return;
+    }
+
+    translation.x = translation.x.clamp(
+        -1. * (MAP_MOVABLE_WIDTH / 2.) + PLAYER_WIDTH / 2.,
+        MAP_MOVABLE_WIDTH / 2. - PLAYER_WIDTH / 2.,
+    );
+
+    translation.y = translation.y.clamp(
+        -1. * (MAP_MOVABLE_HEIGHT / 2.) + PLAYER_HEIGHT / 2.,
+        MAP_MOVABLE_HEIGHT / 2. + PLAYER_HEIGHT / 2.,
+    );
+}
+
+// Redoes a move from `old` to `translation` so the player's feet go around obstacles.
+pub fn move_around_obstacles(obstacle_map: &ObstacleMap, old: Vec3, translation: &mut Vec3) {
+    let feet = Vec2::new(0., PLAYER_FEET_OFFSET);
+
+    let position = obstacle_map.get_position_around_obstacles(
+        old.truncate() - feet,
+        translation.truncate() - feet,
+        PLAYER_COLLISION_RADIUS,
+    ) + feet;
+
+    translation.x = position.x;
+    translation.y = position.y;
+}
+
+// Keeps a player within screen reach of everyone else, so the camera can always frame them all.
+fn clamp_to_partners(translation: &mut Vec3, entity: Entity, positions: &[(Entity, Vec3)]) {
+    let max_distance = CAMERA_FRAMING_AREA * MAX_CAMERA_ZOOM;
+
+    for (other, position) in positions {
+        if *other == entity {
+            continue;
+        }
+
+        translation.x = translation
+            .x
+            .clamp(position.x - max_distance.x, position.x + max_distance.x);
+        translation.y = translation
+            .y
+            .clamp(position.y - max_distance.y, position.y + max_distance.y);
+    }
+}
+
+pub fn update_invulnerability(
+    mut commands: Commands,
+    mut query: Query<(&mut Invulnerable, Entity), With<Player>>,
+    time: Res<Time>,
+) {
+    for (mut invulnerable, entity) in query.iter_mut() {
+        invulnerable.timer.tick(time.delta());
+
+        if invulnerable.timer.finished() {
+            commands.entity(entity).remove::<Invulnerable>();
+        }
+    }
+}
+
+// Every hit on a player, from enemy contact to elite explosions, goes through this.
+pub fn get_damage_after_armor(damage: f32, player_stats: &PlayerStats) -> f32 {
+    (damage - player_stats.get(Stat::Armor, 0.)).max(MIN_PLAYER_DAMAGE)
+}
+
+// A player only takes one hit at a time - whatever lands first gives them i-frames, and
+// everything else that frame (or until they run out) is ignored.
+pub fn damage_players(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    hit_sound: Res<PlayerHitSound>,
+    mut damage_events: EventReader<PlayerDamageEvent>,
+    mut player_query: Query<
+        (&mut Health, &PlayerStats, &Transform, Option<&Invulnerable>),
+        (With<Player>, Without<Downed>),
+    >,
+) {
+    let mut hit_players: Vec<Entity> = Vec::new();
+
+    for event in damage_events.read() {
+        if hit_players.contains(&event.entity) {
+            continue;
+        }
+
+        let Ok((mut health, player_stats, transform, invulnerable)) =
+            player_query.get_mut(event.entity)
+        else {
+            continue;
+        };
+
+        if invulnerable.is_some() || health.total <= 0. {
+            continue;
+        }
+
+        hit_players.push(event.entity);
+
+        health.total -= get_damage_after_armor(event.damage, player_stats);
+
+        if let Some(path) = hit_sound.0.as_ref() {
+            commands.spawn(AudioBundle {
+                source: assets.load(path),
+                settings: PlaybackSettings::DESPAWN,
+            });
+        }
+
+        // Falling is handled by check_health.
+        if health.total <= 0. {
+            continue;
+        }
+
+        let mut knockback = transform.translation - event.source;
+        knockback.z = 0.;
+
+        commands.entity(event.entity).insert((
+            Invulnerable::new(HIT_INVULNERABLE_SECONDS),
+            HitFeedback {
+                timer: Timer::from_seconds(HIT_INVULNERABLE_SECONDS, TimerMode::Once),
+                knockback: knockback.normalize_or_zero(),
+            },
+        ));
+    }
+}
+
+fn update_hit_feedback(
+    mut commands: Commands,
+    mut query: Query<
+        (
+            &mut HitFeedback,
+            &mut TextureAtlasSprite,
+            &mut Transform,
+            Entity,
+        ),
+        (With<Player>, Without<Downed>),
+    >,
+    obstacle_map: Res<ObstacleMap>,
+    map_mode: Res<MapMode>,
+    time: Res<Time>,
+) {
+    for (mut feedback, mut sprite, mut transform, entity) in query.iter_mut() {
+        feedback.timer.tick(time.delta());
+
+        if feedback.timer.finished() {
+            sprite.color = Color::WHITE;
+            commands.entity(entity).remove::<HitFeedback>();
+            continue;
+        }
+
+        let elapsed = feedback.timer.elapsed_secs();
+
+        if elapsed < KNOCKBACK_SECONDS {
+            let old_translation = transform.translation;
+            transform.translation += feedback.knockback * KNOCKBACK_SPEED * time.delta_seconds();
+            move_around_obstacles(&obstacle_map, old_translation, &mut transform.translation);
+            clamp_to_movable_area(&mut transform.translation, &map_mode);
+        }
+
+        sprite.color = if elapsed < HIT_FLASH_SECONDS {
+            HIT_FLASH_COLOR
+        } else if ((elapsed / HIT_BLINK_SECONDS) as u32).is_multiple_of(2) {
+            Color::WHITE.with_a(HIT_BLINK_ALPHA)
+        } else {
+            Color::WHITE
+        };
+    }
+}
+
+// Regeneration passives count on top of the Regen stat.
+pub fn apply_regeneration(
+    mut query: Query<
+        (&mut Health, &PlayerStats, &CharacterPassive),
+        (With<Player>, Without<Downed>),
+    >,
+    time: Res<Time>,
+) {
+    for (mut health, player_stats, passive) in query.iter_mut() {
+        let base = match passive.effect {
+            PassiveEffect::Regeneration { per_second } => per_second,
+            _ => 0.,
+        };
+        let per_second = player_stats.get(Stat::Regen, base);
+
+        if per_second > 0. && health.total > 0. && health.total < health.max {
+            health.heal(per_second * time.delta_seconds());
+        }
+    }
+}
+
 pub fn player_movement(
     mut player: Query<
         (
@@ -93,100 +462,116 @@ pub fn player_movement(
             &mut Transform,
             &mut AnimationTimer,
             &PlayerSpriteSheetAnimatable,
+            &PlayerControls,
+            Entity,
+            Option<&Downed>,
         ),
         With<Player>,
     >,
-    input: Res<Input<KeyCode>>,
+    input: PlayerInput,
+    obstacle_map: Res<ObstacleMap>,
+    map_mode: Res<MapMode>,
     time: Res<Time>,
     state: Res<State<GamePlayState>>,
 ) {
-    let (mut movable, mut sprite, mut transform, mut timer, animateable) = player.single_mut();
+    let positions: Vec<(Entity, Vec3)> = player
+        .iter()
+        .map(|(_, _, transform, _, _, _, entity, _)| (entity, transform.translation))
+        .collect();
+
+    for (mut movable, mut sprite, mut transform, mut timer, animateable, controls, entity, downed) in
+        player.iter_mut()
+    {
+        if downed.is_some() {
+            continue;
+        }
 
-    let normal_translation = time.delta_seconds() * movable.speed;
+        let up = input.up(controls);
+        let down = input.down(controls);
+        let left = input.left(controls);
+        let right = input.right(controls);
 
-    let diagonal_translation = (normal_translation * normal_translation * 2.).sqrt() / 2.;
+        let normal_translation = time.delta_seconds() * movable.speed;
 
-    let mut key_pressed = false;
+        let diagonal_translation = (normal_translation * normal_translation * 2.).sqrt() / 2.;
 
-    let old_direction = movable.direction.clone();
-    let old_is_moving = movable.is_moving.clone();
+        let mut key_pressed = false;
 
-    // Top and bottom with checks for diagonal.
-    if input.pressed(KeyCode::W) {
-        key_pressed = true;
+        let old_direction = movable.direction.clone();
+        let old_is_moving = movable.is_moving.clone();
+        let old_translation = transform.translation;
 
-        if input.pressed(KeyCode::D) {
-            movable.direction = Direction::UpRight;
-            sprite.flip_x = false;
-            transform.translation.y += diagonal_translation;
-            transform.translation.x += diagonal_translation;
-        } else if input.pressed(KeyCode::A) {
-            movable.direction = Direction::UpLeft;
+        // Top and bottom with checks for diagonal.
+        if up {
+            key_pressed = true;
+
+            if right {
+                movable.direction = Direction::UpRight;
+                sprite.flip_x = false;
+                transform.translation.y += diagonal_translation;
+                transform.translation.x += diagonal_translation;
+            } else if left {
+                movable.direction = Direction::UpLeft;
+                sprite.flip_x = true;
+                transform.translation.y += diagonal_translation;
+                transform.translation.x -= diagonal_translation;
+            } else {
+                movable.direction = Direction::Up;
+                transform.translation.y += normal_translation;
+            }
+        } else if down {
+            key_pressed = true;
+            if right {
+                sprite.flip_x = false;
+                movable.direction = Direction::DownRight;
+                transform.translation.y -= diagonal_translation;
+                transform.translation.x += diagonal_translation;
+            } else if left {
+                sprite.flip_x = true;
+                movable.direction = Direction::DownLeft;
+                transform.translation.y -= diagonal_translation;
+                transform.translation.x -= diagonal_translation;
+            } else {
+                movable.direction = Direction::Down;
+                transform.translation.y -= normal_translation;
+            }
+        } else if left {
+            key_pressed = true;
+            transform.translation.x -= normal_translation;
             sprite.flip_x = true;
-            transform.translation.y += diagonal_translation;
-            transform.translation.x -= diagonal_translation;
-        } else {
-            movable.direction = Direction::Up;
-            transform.translation.y += normal_translation;
-        }
-    } else if input.pressed(KeyCode::S) {
-        key_pressed = true;
-        if input.pressed(KeyCode::D) {
+            movable.direction = Direction::Left;
+        } else if right {
+            key_pressed = true;
+            transform.translation.x += normal_translation;
             sprite.flip_x = false;
-            movable.direction = Direction::DownRight;
-            transform.translation.y -= diagonal_translation;
-            transform.translation.x += diagonal_translation;
-        } else if input.pressed(KeyCode::A) {
-            sprite.flip_x = true;
-            movable.direction = Direction::DownLeft;
-            transform.translation.y -= diagonal_translation;
-            transform.translation.x -= diagonal_translation;
-        } else {
-            movable.direction = Direction::Down;
-            transform.translation.y -= normal_translation;
-        }
-    } else if input.pressed(KeyCode::A) {
-        key_pressed = true;
-        transform.translation.x -= normal_translation;
-        sprite.flip_x = true;
-        movable.direction = Direction::Left;
-    } else if input.pressed(KeyCode::D) {
-        key_pressed = true;
-        transform.translation.x += normal_translation;
-        sprite.flip_x = false;
-        movable.direction = Direction::Right;
-    }
-
-    transform.translation.x = transform.translation.x.clamp(
-        -1. * (MAP_MOVABLE_WIDTH / 2.) + PLAYER_WIDTH / 2.,
-        MAP_MOVABLE_WIDTH / 2. - PLAYER_WIDTH / 2.,
-    );
-
-    transform.translation.y = transform.translation.y.clamp(
-        -1. * (MAP_MOVABLE_HEIGHT / 2.) + PLAYER_HEIGHT / 2.,
-        MAP_MOVABLE_HEIGHT / 2. + PLAYER_HEIGHT / 2.,
-    );
+            movable.direction = Direction::Right;
+        }
 
-    movable.is_moving = key_pressed;
+        move_around_obstacles(&obstacle_map, old_translation, &mut transform.translation);
+        clamp_to_movable_area(&mut transform.translation, &map_mode);
+        clamp_to_partners(&mut transform.translation, entity, &positions);
 
-    // IMPORTANT - need to compare with prior frame state to make sure not resetting anim unnecessarily,
-    // but also makes sure to reset on EVERY movement or direction change.
-    if movable.direction != old_direction || movable.is_moving != old_is_moving {
-        let chosen = get_indices_for_movable_direction(&movable, &animateable, &sprite);
+        movable.is_moving = key_pressed;
 
-        if chosen.is_some() {
-            movable.current_animation_indices = chosen.unwrap();
-        }
+        // IMPORTANT - need to compare with prior frame state to make sure not resetting anim unnecessarily,
+        // but also makes sure to reset on EVERY movement or direction change.
+        if movable.direction != old_direction || movable.is_moving != old_is_moving {
+            let chosen = get_indices_for_movable_direction(&movable, &animateable, &sprite);
 
-        sprite.index = movable.current_animation_indices.first;
-    } else {
-        timer.tick(time.delta());
+            if chosen.is_some() {
+                movable.current_animation_indices = chosen.unwrap();
+            }
 
-        if timer.just_finished() {
-            sprite.index = if sprite.index >= movable.current_animation_indices.last {
-                movable.current_animation_indices.first
-            } else {
-                sprite.index + 1
+            sprite.index = movable.current_animation_indices.first;
+        } else {
+            timer.tick(time.delta());
+
+            if timer.just_finished() {
+                sprite.index = if sprite.index >= movable.current_animation_indices.last {
+                    movable.current_animation_indices.first
+                } else {
+                    sprite.index + 1
+                }
             }
         }
     }
@@ -194,47 +579,68 @@ pub fn player_movement(
 
 fn setup(
     mut commands: Commands,
-    mut assets: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut sheets: SpriteSheets,
     mut next_state: ResMut<NextState<GamePlayState>>,
-    state: Res<State<SelectedCharacterState>>,
+    selected_character: Res<SelectedCharacter>,
+    registry: Res<CharacterRegistry>,
+    meta: Res<MetaProgress>,
     // mut meshes: ResMut<Assets<Mesh>>,
     // mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
     println!("Spawning game player plugin");
 
-    let (character, animatable) = get_character_block(state.get());
-    let texture_atlas_handle = get_character_sprite(&character, &mut texture_atlases, &mut assets);
-
-    let idle_anims = animatable.idle_anim_indices.clone();
-
-    commands.spawn((
-        SpriteSheetBundle {
-            texture_atlas: texture_atlas_handle,
-            sprite: TextureAtlasSprite::new(idle_anims.first),
-            transform: Transform::from_xyz(0., 0., 10.),
-            ..default()
-        },
-        AnimationTimer(Timer::from_seconds(0.3, TimerMode::Repeating)),
-        Player {
-            weapons: vec![character.starting_weapon.clone()],
-        },
-        animatable,
-        Health { total: 100. },
-        CanLevel {
-            experience: 0,
-            level: 1,
-            level_step: 100,
-        },
-        Movable {
-            speed: PLAYER_SPEED_DEFAULT,
-            direction: Direction::Down,
-            is_moving: false,
-            current_animation_indices: idle_anims,
-            is_collided: false,
-            is_state_changed: true,
-        },
-    ));
+    let characters = get_selected_characters(&registry, &selected_character);
+    let player_count = characters.len();
+
+    for (index, character) in characters.into_iter().enumerate() {
+        let Some((character, animatable)) = get_character_block(&registry, &character.id) else {
+            continue;
+        };
+        let texture_atlas_handle =
+            get_character_sprite(&character, &mut sheets);
+
+        let idle_anims = animatable.idle_anim_indices.clone();
+
+        // Spread players out side by side around the centre of the map.
+        let x = (index as f32 - (player_count - 1) as f32 / 2.) * PLAYER_SPAWN_SPACING;
+
+        commands.spawn((
+            SpriteSheetBundle {
+                texture_atlas: texture_atlas_handle,
+                sprite: TextureAtlasSprite::new(idle_anims.first),
+                transform: Transform::from_xyz(x, 0., 10.),
+                ..default()
+            },
+            AnimationTimer(Timer::from_seconds(0.3, TimerMode::Repeating)),
+            Player {
+                index,
+                weapons: vec![get_weapon_for_type(&character.starting_weapon)],
+                passives: vec![],
+            },
+            PlayerControls::for_player(index, player_count),
+            Name::new(character.name.clone()),
+            animatable,
+            Health::new(character.stats.max_health),
+            character.stats.clone(),
+            PlayerStats::new(&character.stats, &meta),
+            character.passive.clone(),
+            Dash::new(),
+            Ultimate::new(character.ultimate.clone()),
+            CanLevel {
+                experience: 0,
+                level: 1,
+                level_step: 100,
+            },
+            Movable {
+                speed: character.stats.speed,
+                direction: Direction::Down,
+                is_moving: false,
+                current_animation_indices: idle_anims,
+                is_collided: false,
+                is_state_changed: true,
+            },
+        ));
+    }
 
     next_state.set(GamePlayState::Started);
 }
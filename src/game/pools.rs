@@ -0,0 +1,167 @@
+use bevy::prelude::*;
+
+use crate::sprite::{
+    AnimationTimer, EffectSpriteSheetAnimatable, Movable, ProjectileDealsDamage,
+    ProjectileSpriteSheetAnimatable,
+};
+use crate::sprite_sheets::{SheetGrid, SpriteSheets};
+use crate::GameState;
+
+use super::projectile_spawner;
+use super::weapons::{DamageEffect, Explosion, Projectile};
+
+// Projectiles and hit effects come and go many times a second, so instead of spawning and
+// despawning them, hidden entities are kept around and handed out again. Take one, insert the
+// components it needs, and release it when it's done - see projectile_spawner.
+pub struct PoolsPlugin;
+
+impl Plugin for PoolsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EntityPools>()
+            .add_systems(OnEnter(GameState::Gameplay), setup)
+            .add_systems(Last, recycle_released)
+            .add_systems(
+                OnExit(GameState::Gameplay),
+                unload.after(projectile_spawner::unload),
+            );
+    }
+}
+
+pub const EXPLOSION_SHEET_PATH: &str = "sprites/effects/explosion_anim_spritesheet.png";
+pub const BLOOD_SHEET_PATH: &str = "sprites/effects/bloodspurt.png";
+
+const PROJECTILE_POOL_SIZE: usize = 200;
+const EFFECT_POOL_SIZE: usize = 100;
+
+// On every entity a pool owns, whether it's out or not.
+#[derive(Component)]
+struct PoolMember;
+
+#[derive(Default)]
+struct Pool {
+    free: Vec<Entity>,
+    // Released this frame. They go back to free once their components are gone, so a release
+    // and a take in the same frame can't fight over one entity.
+    released: Vec<Entity>,
+}
+
+impl Pool {
+    // Spawns a new one if everything is out, the pool just grows.
+    fn take(&mut self, commands: &mut Commands) -> Entity {
+        self.free
+            .pop()
+            .unwrap_or_else(|| spawn_pool_member(commands))
+    }
+
+    // False if it was already released, e.g. a projectile that hit an enemy and an obstacle in
+    // the same frame.
+    fn release(&mut self, entity: Entity) -> bool {
+        if self.released.contains(&entity) || self.free.contains(&entity) {
+            return false;
+        }
+
+        self.released.push(entity);
+        true
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct EntityPools {
+    projectiles: Pool,
+    effects: Pool,
+    // Every explosion and blood spurt uses the same sheet.
+    pub explosion_atlas: Handle<TextureAtlas>,
+    pub blood_atlas: Handle<TextureAtlas>,
+}
+
+impl EntityPools {
+    pub fn take_projectile(&mut self, commands: &mut Commands) -> Entity {
+        self.projectiles.take(commands)
+    }
+
+    pub fn take_effect(&mut self, commands: &mut Commands) -> Entity {
+        self.effects.take(commands)
+    }
+
+    pub fn release_projectile(&mut self, commands: &mut Commands, entity: Entity) {
+        if self.projectiles.release(entity) {
+            commands
+                .entity(entity)
+                .remove::<(
+                    Projectile,
+                    ProjectileDealsDamage,
+                    Movable,
+                    AnimationTimer,
+                    ProjectileSpriteSheetAnimatable,
+                )>()
+                .insert(Visibility::Hidden);
+        }
+    }
+
+    pub fn release_effect(&mut self, commands: &mut Commands, entity: Entity) {
+        if self.effects.release(entity) {
+            commands
+                .entity(entity)
+                .remove::<(
+                    Explosion,
+                    DamageEffect,
+                    EffectSpriteSheetAnimatable,
+                    AnimationTimer,
+                )>()
+                .insert(Visibility::Hidden);
+        }
+    }
+}
+
+fn spawn_pool_member(commands: &mut Commands) -> Entity {
+    commands
+        .spawn((
+            SpriteSheetBundle {
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            PoolMember,
+        ))
+        .id()
+}
+
+fn setup(mut commands: Commands, mut sheets: SpriteSheets) {
+    let mut pools = EntityPools {
+        explosion_atlas: sheets.get_grid(
+            EXPLOSION_SHEET_PATH,
+            SheetGrid::new(Vec2::new(32., 32.), 6, 1),
+        ),
+        blood_atlas: sheets.get_grid(BLOOD_SHEET_PATH, SheetGrid::new(Vec2::new(100., 100.), 6, 6)),
+        ..default()
+    };
+
+    for _ in 0..PROJECTILE_POOL_SIZE {
+        pools
+            .projectiles
+            .free
+            .push(spawn_pool_member(&mut commands));
+    }
+
+    for _ in 0..EFFECT_POOL_SIZE {
+        pools.effects.free.push(spawn_pool_member(&mut commands));
+    }
+
+    commands.insert_resource(pools);
+}
+
+// Runs after all of Update's commands are applied, so released entities are clean by now.
+fn recycle_released(mut pools: ResMut<EntityPools>) {
+    let pools = &mut *pools;
+
+    for pool in [&mut pools.projectiles, &mut pools.effects] {
+        pool.free.append(&mut pool.released);
+    }
+}
+
+fn unload(mut commands: Commands, member_query: Query<Entity, With<PoolMember>>) {
+    for entity in member_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    commands.insert_resource(EntityPools::default());
+}
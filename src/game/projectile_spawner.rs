@@ -1,21 +1,26 @@
-use core::time::Duration;
-
 use bevy::prelude::*;
 use rand::Rng;
+use serde::Deserialize;
 
-use crate::game::level::{MAP_HEIGHT, MAP_WIDTH};
-use crate::game::weapons::{Explosion, WeaponsEnum};
+use crate::character_select::{CharacterPassive, PassiveEffect};
+use crate::game::level::{MapMode, MAP_HEIGHT, MAP_WIDTH};
+use crate::game::weapons::Explosion;
 use crate::sprite::{
     get_translation_for_direction, AnimationIndices, AnimationTimer, Direction,
     EffectSpriteSheetAnimatable, Health, Movable, ProjectileDealsDamage,
     ProjectileSpriteSheetAnimatable,
 };
+use crate::sprite_sheets::SpriteSheets;
 use crate::GameState;
 
-use super::player::{CanLevel, Player};
-use super::spawner::{Enemy, GivesExperience};
+use super::chests::DropsChest;
+use super::elites::{Elite, EliteAffix};
+use super::player::{Downed, Player};
+use super::pools::EntityPools;
+use super::spawner::{Enemy, EnemyKind, EnemyRank, GivesExperience, Knockback};
+use super::stats::{PlayerStats, Stat};
 use super::weapons::{
-    get_weapon_sprite, DamageEffect, Projectile, ProjectileAimMethod, ProjectileCategory,
+    get_projectile_sprite, DamageEffect, Projectile, ProjectileAimMethod, ProjectileCategory,
     ProjectileProps, Weapon,
 };
 use super::GamePlayState;
@@ -31,11 +36,15 @@ impl Plugin for ProjectileSpawnerPlugin {
             //     Update,
             //     (spawn_weapon_projectiles).run_if(in_state(GamePlayState::Started)),
             // )
+            .add_event::<DamageEvent>()
+            .add_event::<DamageAppliedEvent>()
+            .add_event::<EnemyKilledEvent>()
             .add_systems(
                 Update,
                 (
                     update_projectiles,
                     update_projectile_collisions,
+                    apply_damage_events.after(update_projectile_collisions),
                     update_explosions_damage_effects,
                     spawn_weapon_projectiles,
                 )
@@ -126,6 +135,7 @@ fn get_random_nearby_position(
     rng: &mut rand::prelude::ThreadRng,
     player_transform: &Transform,
     projectile: &ProjectileProps,
+    map_mode: &MapMode,
 ) -> Vec3 {
     let rnd_x: f32 = rng.gen_range(0. ..projectile.projectile_aim_range);
     let rnd_y: f32 = rng.gen_range(0. ..(projectile.projectile_aim_range * 0.75));
@@ -144,8 +154,14 @@ fn get_random_nearby_position(
             rnd_y
         });
 
-    let final_x_pos = x_pos.clamp(-1. * MAP_WIDTH / 2., MAP_WIDTH / 2.);
-    let final_y_pos = y_pos.clamp(-1. * MAP_HEIGHT / 2., MAP_HEIGHT / 2.);
+    // Endless maps have no edge to keep projectiles inside of.
+    let (final_x_pos, final_y_pos) = match map_mode {
+        MapMode::Arena => (
+            x_pos.clamp(-1. * MAP_WIDTH / 2., MAP_WIDTH / 2.),
+            y_pos.clamp(-1. * MAP_HEIGHT / 2., MAP_HEIGHT / 2.),
+        ),
+        MapMode::Endless => (x_pos, y_pos),
+    };
 
     // let random_x_initial: usize = rng.gen_range(0..400);
     // let random_y_initial: usize = rng.gen_range(0..300);
@@ -170,9 +186,11 @@ fn spawn_projectile_for_aim_method(
     player_movable: &Movable,
     enemy_query: &Query<(&Transform, Entity), (With<Enemy>, Without<Player>)>,
     rng: &mut rand::prelude::ThreadRng,
-    asset_server: &Res<AssetServer>,
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
+    sheets: &mut SpriteSheets,
     commands: &mut Commands,
+    pools: &mut EntityPools,
+    map_mode: &MapMode,
+    spread_angle: f32,
 ) {
     // Default to player facing direction - ProjectileAimMethod::PlayerFacing
     let mut origin: Vec3 = player_transform.translation.clone();
@@ -196,8 +214,12 @@ fn spawn_projectile_for_aim_method(
                     origin = vec;
                 }
                 None => {
-                    origin =
-                        get_random_nearby_position(rng, player_transform, &weapon.projectile_props);
+                    origin = get_random_nearby_position(
+                        rng,
+                        player_transform,
+                        &weapon.projectile_props,
+                        map_mode,
+                    );
                 }
             };
         }
@@ -227,20 +249,30 @@ fn spawn_projectile_for_aim_method(
         }
         ProjectileAimMethod::Random => {
             // Uses the default direction but alters the origin transform
-            origin = get_random_nearby_position(rng, player_transform, &weapon.projectile_props);
+            origin = get_random_nearby_position(
+                rng,
+                player_transform,
+                &weapon.projectile_props,
+                map_mode,
+            );
             direction = get_translation_for_direction(Direction::Custom(Vec3::new(0., 1., 0.)), 9.);
         }
         // Otherwise, use defaults for both transform (player origin) and direction (player direction)
         _ => {}
     }
 
+    // Fans multi-projectile weapons out around the aimed direction.
+    if spread_angle != 0. {
+        direction = Quat::from_rotation_z(spread_angle) * direction;
+    }
+
     spawn_sprite(
         weapon,
         direction,
         origin,
-        texture_atlases,
-        &asset_server,
+        sheets,
         commands,
+        pools,
     );
 }
 
@@ -250,9 +282,9 @@ fn spawn_static_projectile(
     player_movable: &Movable,
     enemy_query: &Query<(&Transform, Entity), (With<Enemy>, Without<Player>)>,
     rng: &mut rand::prelude::ThreadRng,
-    asset_server: &Res<AssetServer>,
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
+    sheets: &mut SpriteSheets,
     commands: &mut Commands,
+    pools: &mut EntityPools,
 ) {
     // Default to player facing direction - ProjectileAimMethod::PlayerFacing
     let mut origin: Vec3 = player_transform.translation.clone();
@@ -310,49 +342,69 @@ fn spawn_static_projectile(
         weapon,
         direction,
         origin,
-        texture_atlases,
-        &asset_server,
+        sheets,
         commands,
+        pools,
     );
 }
 
 fn spawn_weapon_projectiles(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut sheets: SpriteSheets,
     enemy_query: Query<(&Transform, Entity), (With<Enemy>, Without<Player>)>,
     mut player_weapon_query: Query<
-        (&mut Player, &Transform, &Movable),
-        (Without<Enemy>, With<Player>),
+        (
+            &mut Player,
+            &Transform,
+            &Movable,
+            &PlayerStats,
+            &CharacterPassive,
+        ),
+        (Without<Enemy>, With<Player>, Without<Downed>),
     >,
     time: Res<Time>,
+    map_mode: Res<MapMode>,
+    mut pools: ResMut<EntityPools>,
 ) {
     let mut rng = rand::thread_rng();
 
-    for (mut player, player_transform, movable) in player_weapon_query.iter_mut() {
+    for (mut player, player_transform, movable, stats, passive) in player_weapon_query.iter_mut() {
         for weapon in player.weapons.iter_mut() {
-            weapon.tick_timer.tick(time.delta());
+            // Cooldown below 1 ticks the weapon timer faster, above 1 slower.
+            let cooldown = stats.get(Stat::Cooldown, 1.).max(0.1);
+            weapon.tick_timer.tick(time.delta().mul_f32(1. / cooldown));
 
             if !weapon.tick_timer.finished() {
                 continue;
             }
 
+            let weapon = &get_weapon_with_player_stats(weapon, stats, passive);
+
             match weapon.projectile_props.projectile_category {
                 ProjectileCategory::Projectile
                 | ProjectileCategory::ProjectileAoe
                 | ProjectileCategory::Instant
                 | ProjectileCategory::InstantAoe => {
                     println!("Firing projectile towards enemy");
-                    spawn_projectile_for_aim_method(
-                        weapon,
-                        &player_transform,
-                        &movable,
-                        &enemy_query,
-                        &mut rng,
-                        &asset_server,
-                        &mut texture_atlases,
-                        &mut commands,
-                    );
+                    let count = weapon.projectile_props.projectile_count;
+
+                    for index in 0..count {
+                        let spread_angle = (index as f32 - (count - 1) as f32 / 2.)
+                            * weapon.projectile_props.projectile_spread;
+
+                        spawn_projectile_for_aim_method(
+                            weapon,
+                            &player_transform,
+                            &movable,
+                            &enemy_query,
+                            &mut rng,
+                            &mut sheets,
+                            &mut commands,
+                            &mut pools,
+                            &map_mode,
+                            spread_angle,
+                        );
+                    }
                 }
                 //
                 // ProjectileCategory::Instant => {
@@ -376,13 +428,78 @@ fn spawn_weapon_projectiles(
     }
 }
 
-struct DamageEvent {
-    damage: f32,
-    entity_id: u32,
-    damage_type: DamageType,
+// Copy of the weapon with the player's stats and passive baked into the projectile props.
+fn get_weapon_with_player_stats(
+    weapon: &Weapon,
+    stats: &PlayerStats,
+    passive: &CharacterPassive,
+) -> Weapon {
+    let mut weapon = weapon.clone();
+    let props = &mut weapon.projectile_props;
+    let area = stats.get(Stat::Area, 1.);
+
+    props.projectile_base_damage = stats.get(Stat::Damage, props.projectile_base_damage);
+    props.projectile_aoe_radius *= area;
+    props.projectile_sprite_scale *= area;
+    props.projectile_speed = stats.get(Stat::ProjectileSpeed, props.projectile_speed);
+    props.projectile_duration = stats.get(Stat::Duration, props.projectile_duration);
+    props.projectile_crit_chance = stats.get(Stat::CritChance, props.projectile_crit_chance);
+    props.projectile_crit_multiplier =
+        stats.get(Stat::CritMultiplier, props.projectile_crit_multiplier);
+    props.projectile_count = stats
+        .get(Stat::Amount, props.projectile_count as f32)
+        .round()
+        .max(1.) as usize;
+
+    if let PassiveEffect::DamageTypeBonus {
+        damage_type,
+        multiplier,
+    } = passive.effect
+    {
+        if damage_type == props.projectile_damage_type {
+            props.projectile_base_damage *= multiplier;
+        }
+    }
+
+    weapon
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+// Every source of damage to enemies (projectiles, abilities) goes through this event so death,
+// experience and effects are handled in one place - see apply_damage_events.
+#[derive(Event, Debug, Clone)]
+pub struct DamageEvent {
+    pub damage: f32,
+    pub entity: Entity,
+    pub damage_type: DamageType,
+    pub is_critical: bool,
+    // Push along the hit direction, before the enemy's mass. Zero for hits that don't push.
+    pub knockback: Vec3,
+}
+
+// Sent once a DamageEvent has landed, with the damage the enemy actually took.
+#[derive(Event, Debug, Clone)]
+pub struct DamageAppliedEvent {
+    pub entity: Entity,
+    pub position: Vec3,
+    pub damage: f32,
+    pub damage_type: DamageType,
+    pub is_critical: bool,
+    // The enemy is weak to this damage type, see EnemyKind::get_weakness.
+    pub is_weakness: bool,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct EnemyKilledEvent {
+    pub position: Vec3,
+    pub rank: EnemyRank,
+    pub kind: EnemyKind,
+    pub experience: u64,
+    pub chest: Option<DropsChest>,
+    // Empty for anything but elites.
+    pub affixes: Vec<EliteAffix>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
 pub enum DamageType {
     #[default]
     Normal,
@@ -393,67 +510,58 @@ pub enum DamageType {
     Psychological,
 }
 
+impl DamageType {
+    pub fn get_color(&self) -> Color {
+        match self {
+            DamageType::Normal => Color::WHITE,
+            DamageType::Fire => Color::rgb(1., 0.55, 0.2),
+            DamageType::Water => Color::rgb(0.4, 0.7, 1.),
+            DamageType::Earth => Color::rgb(0.75, 0.6, 0.35),
+            DamageType::Lightning => Color::rgb(1., 0.95, 0.35),
+            DamageType::Psychological => Color::rgb(0.85, 0.5, 1.),
+        }
+    }
+}
+
 fn update_projectile_collisions(
-    assets: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
     mut commands: Commands,
-    mut enemy_query: Query<
-        (&Transform, &mut Health, &GivesExperience, &Enemy, Entity),
-        (With<Enemy>, Without<Player>),
-    >,
+    enemy_query: Query<(&Transform, Entity), (With<Enemy>, Without<Player>)>,
     mut projectile_query: Query<
         (
             &Transform,
             &mut Movable,
             &mut ProjectileDealsDamage,
-            &Projectile,
+            &mut Projectile,
             Entity,
         ),
         (With<Projectile>, Without<Player>),
     >,
-    mut player_query: Query<(&mut CanLevel, &Player), With<Player>>,
-    mut next_play_state: ResMut<NextState<GamePlayState>>,
-    mut next_state: ResMut<NextState<GameState>>,
-    time: Res<Time>,
+    mut damage_event_writer: EventWriter<DamageEvent>,
+    mut pools: ResMut<EntityPools>,
+    mut sheets: SpriteSheets,
 ) {
-    let (mut lvl, player) = player_query.single_mut();
+    let mut rng = rand::thread_rng();
+    let mut chained_bolts: Vec<(ProjectileProps, Vec3, Vec<Entity>)> = Vec::new();
 
     for (
         projectile_transform,
         mut projectile_movable,
         mut projectile_damage,
-        projectile,
+        mut projectile,
         projectile_entity,
     ) in projectile_query.iter_mut()
     {
-        // let mut collided = false;
-
-        // // projectile_damage.tick_timer.tick(time.delta());
-
-        // // Check for player collision
-        // let distance = enemy_transform
-        //     .translation
-        //     .distance(player_transform.translation);
-
-        // if distance < COLLISION_DISTANCE {
-        //     println!("COLLIDED WITH ENEMY {}", distance);
-        //     colliding_enemies.push(ent_original.index());
-
-        //     if enemy_damage.tick_timer.finished() {
-        //         enemy_damage.tick_timer.reset();
-        //         player_health.total -= enemy_damage.damage;
-        //     }
-
-        //     collided = true;
-        // }
-
         if projectile_damage.is_triggered {
             continue;
         }
 
         let mut damage_events: Vec<DamageEvent> = Vec::new();
 
-        for (enemy_transform, _, _, _, enemy) in enemy_query.iter() {
+        for (enemy_transform, enemy) in enemy_query.iter() {
+            if projectile.chain_hits.contains(&enemy) {
+                continue;
+            }
+
             let distance = enemy_transform
                 .translation
                 .distance(projectile_transform.translation);
@@ -473,81 +581,55 @@ fn update_projectile_collisions(
                     || projectile.props.projectile_category == ProjectileCategory::InstantAoe
                 {
                     // Collided with an enemy. Now trigger damage to everything in area.
-                    let damage_radius = projectile.props.projectile_aoe_radius + collision_distance;
-
-                    for (enemy_transform, _, _, _, enemy) in enemy_query.iter() {
+                    for (enemy_transform, enemy) in enemy_query.iter() {
                         let aoe_distance = projectile_transform
                             .translation
                             .distance(enemy_transform.translation);
 
-                        // IF normal projectile, no checking for aoe - just kill initial collided enemy and remove projectile entity on FIRST enemy hit.
-
-                        // If aoe projectile, add distance from aoe radius to collision distance. Isn't simplified to be on all projectiles as fundamentally different in that the normal projectile goes on FIRST hit,
-                        // but doesn't immediately disappear after the first collision - only after the loop has been finished do we despawn it.
-
                         if projectile.props.projectile_aoe_radius > 0.
                             && aoe_distance < projectile.props.projectile_aoe_radius
                         {
                             println!("Collided with enemy due to {} distance from projectile being less than projectile_aoe_radius", aoe_distance);
-                            damage_events.push(DamageEvent {
-                                damage: projectile_damage.damage
+                            damage_events.push(get_projectile_damage_event(
+                                projectile_damage.damage
                                     * projectile.props.projectile_aoe_damage_scale,
-                                entity_id: enemy.index(),
-                                damage_type: projectile.props.projectile_damage_type,
-                            });
+                                enemy,
+                                enemy_transform.translation - projectile_transform.translation,
+                                &projectile.props,
+                                &mut rng,
+                            ));
                         }
                     }
                 } else {
-                    damage_events.push(DamageEvent {
-                        damage: projectile_damage.damage,
-                        entity_id: enemy.index(),
-                        damage_type: projectile.props.projectile_damage_type,
-                    });
+                    damage_events.push(get_projectile_damage_event(
+                        projectile_damage.damage,
+                        enemy,
+                        enemy_transform.translation - projectile_transform.translation,
+                        &projectile.props,
+                        &mut rng,
+                    ));
+
+                    if projectile.props.projectile_chain_count > 0 {
+                        chained_bolts.extend(get_chained_bolt(
+                            &mut projectile,
+                            enemy,
+                            enemy_transform.translation,
+                            &enemy_query,
+                        ));
+                    }
                 }
 
                 break;
             }
         }
 
-        // Apply dama ge events
-        for event in damage_events.iter() {
-            for (enemy_transform, mut enemy_health, exp, enemy, entity) in enemy_query.iter_mut() {
-                if entity.index() == event.entity_id {
-                    enemy_health.total -= event.damage;
-                    println!("applying dmg event {} {}", event.damage, enemy_health.total);
-
-                    if enemy_health.total <= 0. {
-                        println!("Enemy died {} {}", entity.index(), enemy_health.total);
-                        if add_player_experience(exp.experience, &mut lvl) {
-                            println!("Player leveled up to {}", lvl.level);
-                            if player.weapons.len() < WeaponsEnum::VALUES.len() {
-                                next_play_state.set(GamePlayState::LevelUp);
-                            }
-                        }
-
-                        if enemy.is_boss {
-                            next_state.set(GameState::GameWon);
-                            next_play_state.set(GamePlayState::Unloaded);
-                            return;
-                        }
-
-                        commands.entity(entity).despawn_recursive();
-
-                        spawn_explosion_at_position(
-                            &assets,
-                            &mut texture_atlases,
-                            &mut commands,
-                            &enemy_transform.translation,
-                        );
-                    } else {
-                        spawn_damage_effect_at_position(
-                            &assets,
-                            &mut texture_atlases,
-                            &mut commands,
-                            &enemy_transform.translation,
-                        );
-                    }
-                }
+        // Instant projectiles hit every frame they're out, but only shove each enemy once -
+        // otherwise the knockback's stun keeps restarting for as long as the bolt lasts.
+        for event in damage_events.iter_mut() {
+            if projectile.knocked_back.contains(&event.entity) {
+                event.knockback = Vec3::ZERO;
+            } else {
+                projectile.knocked_back.push(event.entity);
             }
         }
 
@@ -557,58 +639,205 @@ fn update_projectile_collisions(
         {
             projectile_movable.is_moving = false;
             projectile_damage.is_triggered = true;
-            commands.entity(projectile_entity).despawn_recursive();
+            pools.release_projectile(&mut commands, projectile_entity);
         }
+
+        damage_event_writer.send_batch(damage_events);
+    }
+
+    // Pointing up, the same as bolts dropped on a random enemy.
+    let direction = get_translation_for_direction(Direction::Custom(Vec3::new(0., 1., 0.)), 9.);
+
+    for (props, target, chain_hits) in chained_bolts {
+        spawn_projectile_sprite(
+            &props,
+            chain_hits,
+            direction,
+            target,
+            &mut sheets,
+            &mut commands,
+            &mut pools,
+        );
     }
 }
 
-// Return true if lvled up
-fn add_player_experience(experience: u64, lvl: &mut CanLevel) -> bool {
-    lvl.experience += experience;
+// Instant bolts sit on their target and keep colliding every frame, so a bolt only gets to jump
+// once - the chain carries on from the new bolt, one fewer jump left.
+fn get_chained_bolt(
+    projectile: &mut Projectile,
+    struck: Entity,
+    struck_position: Vec3,
+    enemy_query: &Query<(&Transform, Entity), (With<Enemy>, Without<Player>)>,
+) -> Option<(ProjectileProps, Vec3, Vec<Entity>)> {
+    let mut props = projectile.props.clone();
+    projectile.props.projectile_chain_count = 0;
+    props.projectile_chain_count -= 1;
+
+    let mut chain_hits = projectile.chain_hits.clone();
+    chain_hits.push(struck);
+
+    let (target, _) = enemy_query
+        .iter()
+        .filter(|(_, enemy)| !chain_hits.contains(enemy))
+        .map(|(transform, _)| {
+            (
+                transform.translation,
+                transform.translation.distance(struck_position),
+            )
+        })
+        .filter(|(_, distance)| *distance < props.projectile_chain_range)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+
+    Some((props, target, chain_hits))
+}
+
+// Each hit rolls for a crit on its own, so an area hit can crit some enemies and not others.
+// Enemies are pushed away from where the projectile was when it hit them.
+fn get_projectile_damage_event(
+    damage: f32,
+    entity: Entity,
+    direction: Vec3,
+    props: &ProjectileProps,
+    rng: &mut impl Rng,
+) -> DamageEvent {
+    let is_critical = rng.gen_bool(props.projectile_crit_chance.clamp(0., 1.) as f64);
+
+    DamageEvent {
+        damage: if is_critical {
+            damage * props.projectile_crit_multiplier
+        } else {
+            damage
+        },
+        entity,
+        damage_type: props.projectile_damage_type,
+        is_critical,
+        knockback: direction.truncate().normalize_or_zero().extend(0.) * props.projectile_knockback,
+    }
+}
+
+// Extra damage taken from the type an enemy is weak to.
+const WEAKNESS_DAMAGE_SCALE: f32 = 1.5;
+
+fn apply_damage_events(
+    mut pools: ResMut<EntityPools>,
+    mut commands: Commands,
+    mut enemy_query: Query<
+        (
+            &Transform,
+            &mut Health,
+            &GivesExperience,
+            &Enemy,
+            Option<&DropsChest>,
+            Option<&Elite>,
+        ),
+        (With<Enemy>, Without<Player>),
+    >,
+    mut damage_events: EventReader<DamageEvent>,
+    mut damage_applied_events: EventWriter<DamageAppliedEvent>,
+    mut enemy_killed_events: EventWriter<EnemyKilledEvent>,
+    mut next_play_state: ResMut<NextState<GamePlayState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for event in damage_events.read() {
+        let Ok((enemy_transform, mut enemy_health, exp, enemy, drops_chest, elite)) =
+            enemy_query.get_mut(event.entity)
+        else {
+            continue;
+        };
+
+        // Already killed by an earlier event this frame - despawn is deferred until commands apply.
+        if enemy_health.total <= 0. {
+            continue;
+        }
+
+        let is_weakness = enemy.kind.get_weakness() == Some(event.damage_type);
+
+        let damage = if is_weakness {
+            event.damage * WEAKNESS_DAMAGE_SCALE
+        } else {
+            event.damage
+        };
+
+        let damage = match elite {
+            Some(elite) => elite.get_damage_taken(damage),
+            None => damage,
+        };
+
+        damage_applied_events.send(DamageAppliedEvent {
+            entity: event.entity,
+            position: enemy_transform.translation,
+            // Overkill doesn't count, a bouquet bomb only takes what health was left.
+            damage: damage.min(enemy_health.total),
+            damage_type: event.damage_type,
+            is_critical: event.is_critical,
+            is_weakness,
+        });
+
+        enemy_health.total -= damage;
+        println!("applying dmg event {} {}", damage, enemy_health.total);
 
-    if lvl.experience >= lvl.level_step {
-        lvl.experience = lvl.experience - lvl.level_step;
-        lvl.level += 1;
+        if enemy_health.total > 0. && event.knockback != Vec3::ZERO {
+            commands
+                .entity(event.entity)
+                .insert(Knockback::new(event.knockback, enemy.rank));
+        }
+
+        if enemy_health.total <= 0. {
+            println!("Enemy died {} {}", event.entity.index(), enemy_health.total);
+
+            // The XP drops as a gem for the players to collect, see pickups.
+            enemy_killed_events.send(EnemyKilledEvent {
+                position: enemy_transform.translation,
+                rank: enemy.rank,
+                kind: enemy.kind,
+                experience: exp.experience,
+                chest: drops_chest.copied(),
+                affixes: elite.map(|elite| elite.affixes.clone()).unwrap_or_default(),
+            });
+
+            if enemy.rank == EnemyRank::Boss {
+                next_state.set(GameState::GameWon);
+                next_play_state.set(GamePlayState::Unloaded);
+                return;
+            }
 
-        return true;
+            commands.entity(event.entity).despawn_recursive();
+
+            spawn_explosion_at_position(&mut pools, &mut commands, &enemy_transform.translation);
+        } else {
+            spawn_damage_effect_at_position(
+                &mut pools,
+                &mut commands,
+                &enemy_transform.translation,
+            );
+        }
     }
+}
 
-    return false;
+fn spawn_explosion_at_position(pools: &mut EntityPools, commands: &mut Commands, position: &Vec3) {
+    spawn_scaled_explosion_at_position(pools, commands, position, 1.);
 }
 
-fn spawn_explosion_at_position(
-    assets: &Res<AssetServer>,
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
+pub fn spawn_scaled_explosion_at_position(
+    pools: &mut EntityPools,
     commands: &mut Commands,
     position: &Vec3,
+    scale: f32,
 ) {
-    const PROJECTILE_HEIGHT: f32 = 32.;
-    const PROJECTILE_WIDTH: f32 = 32.;
-
     let anim_indices = AnimationIndices { first: 0, last: 5 };
 
     let animatable = EffectSpriteSheetAnimatable {
         anim_indices: anim_indices.clone(),
     };
 
-    let texture_handle = assets.load("sprites/effects/explosion_anim_spritesheet.png");
-
-    let texture_atlas = TextureAtlas::from_grid(
-        texture_handle,
-        Vec2::new(PROJECTILE_WIDTH, PROJECTILE_HEIGHT),
-        6,
-        1,
-        None,
-        None,
-    );
-
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
+    let entity = pools.take_effect(commands);
 
-    commands.spawn((
+    commands.entity(entity).insert((
         SpriteSheetBundle {
-            texture_atlas: texture_atlas_handle.clone(),
+            texture_atlas: pools.explosion_atlas.clone(),
             sprite: TextureAtlasSprite::new(anim_indices.first),
-            transform: Transform::from_xyz(position.x, position.y, 8.),
+            transform: Transform::from_xyz(position.x, position.y, 8.)
+                .with_scale(Vec3::new(scale, scale, 1.)),
             ..default()
         },
         AnimationTimer(Timer::from_seconds(0.05, TimerMode::Repeating)),
@@ -618,36 +847,21 @@ fn spawn_explosion_at_position(
 }
 
 fn spawn_damage_effect_at_position(
-    assets: &Res<AssetServer>,
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
+    pools: &mut EntityPools,
     commands: &mut Commands,
     position: &Vec3,
 ) {
-    const PROJECTILE_HEIGHT: f32 = 100.;
-    const PROJECTILE_WIDTH: f32 = 100.;
-
     let anim_indices = AnimationIndices { first: 0, last: 12 };
 
     let animatable = EffectSpriteSheetAnimatable {
         anim_indices: anim_indices.clone(),
     };
 
-    let texture_handle = assets.load("sprites/effects/bloodspurt.png");
+    let entity = pools.take_effect(commands);
 
-    let texture_atlas = TextureAtlas::from_grid(
-        texture_handle,
-        Vec2::new(PROJECTILE_WIDTH, PROJECTILE_HEIGHT),
-        6,
-        6,
-        None,
-        None,
-    );
-
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
-    commands.spawn((
+    commands.entity(entity).insert((
         SpriteSheetBundle {
-            texture_atlas: texture_atlas_handle.clone(),
+            texture_atlas: pools.blood_atlas.clone(),
             sprite: TextureAtlasSprite::new(anim_indices.first),
             transform: Transform::from_xyz(position.x, position.y, 8.),
             ..default()
@@ -666,19 +880,20 @@ pub fn update_projectiles(
             &mut Movable,
             &mut TextureAtlasSprite,
             &mut AnimationTimer,
-            &Projectile,
+            &mut Projectile,
             Entity,
         ),
         (With<Projectile>, Without<Player>),
     >,
     mut commands: Commands,
+    mut pools: ResMut<EntityPools>,
 ) {
     for (
         mut projectile_transform,
         projectile_movable,
         mut projectile_sprite,
         mut projectile_anim_timer,
-        projectile,
+        mut projectile,
         entity,
     ) in projectile_query.iter_mut()
     {
@@ -726,8 +941,8 @@ pub fn update_projectiles(
                     if projectile.props.projectile_category == ProjectileCategory::Instant
                         || projectile.props.projectile_category == ProjectileCategory::InstantAoe
                     {
-                        // despawn after animation finished - only fir instant weapons
-                        commands.entity(entity).despawn_recursive();
+                        // release after animation finished - only fir instant weapons
+                        pools.release_projectile(&mut commands, entity);
                     }
                     projectile_movable.current_animation_indices.first
                 } else {
@@ -735,8 +950,8 @@ pub fn update_projectiles(
                 };
         }
 
-        if projectile_anim_timer.elapsed() > Duration::from_secs(20) {
-            commands.entity(entity).despawn_recursive();
+        if projectile.lifetime.tick(time.delta()).finished() {
+            pools.release_projectile(&mut commands, entity);
         }
 
         // Update position
@@ -761,6 +976,7 @@ pub fn update_explosions_damage_effects(
     //     With<DamageEffect>,
     // >,
     mut commands: Commands,
+    mut pools: ResMut<EntityPools>,
 ) {
     for (mut explosion_sprite, mut explosion_anim_timer, mut explosion_animatable, entity) in
         explosions_query.iter_mut()
@@ -769,7 +985,7 @@ pub fn update_explosions_damage_effects(
 
         if explosion_anim_timer.just_finished() {
             if explosion_sprite.index >= explosion_animatable.anim_indices.last {
-                commands.entity(entity).despawn_recursive();
+                pools.release_effect(&mut commands, entity);
             } else {
                 explosion_sprite.index = explosion_sprite.index + 1;
             }
@@ -914,23 +1130,44 @@ fn spawn_sprite(
     weapon: &Weapon,
     direction_translation: Vec3,
     origin: Vec3,
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
-    assets: &Res<AssetServer>,
+    sheets: &mut SpriteSheets,
     commands: &mut Commands,
+    pools: &mut EntityPools,
 ) {
-    let (texture_atlas_handle, animatable) = get_weapon_sprite(assets, texture_atlases, weapon);
-
-    let rotation_quat = get_rotation_from_direction(
+    spawn_projectile_sprite(
+        &weapon.projectile_props,
+        Vec::new(),
         direction_translation,
-        weapon.projectile_props.projectile_rotation_offset,
+        origin,
+        sheets,
+        commands,
+        pools,
     );
+}
 
-    commands.spawn((
+// Chained bolts come from another bolt's props rather than a weapon, carrying the chain so far.
+fn spawn_projectile_sprite(
+    props: &ProjectileProps,
+    chain_hits: Vec<Entity>,
+    direction_translation: Vec3,
+    origin: Vec3,
+    sheets: &mut SpriteSheets,
+    commands: &mut Commands,
+    pools: &mut EntityPools,
+) {
+    let (texture_atlas_handle, animatable) = get_projectile_sprite(sheets, props);
+
+    let rotation_quat =
+        get_rotation_from_direction(direction_translation, props.projectile_rotation_offset);
+
+    let entity = pools.take_projectile(commands);
+
+    commands.entity(entity).insert((
         SpriteSheetBundle {
             texture_atlas: texture_atlas_handle.clone(),
             sprite: TextureAtlasSprite {
                 index: animatable.moving_anim_indices.first,
-                anchor: weapon.projectile_props.projectile_sprite_anchor,
+                anchor: props.projectile_sprite_anchor,
                 ..default()
             },
             transform: Transform {
@@ -940,8 +1177,8 @@ fn spawn_sprite(
                     z: 9.,
                 },
                 scale: Vec3::new(
-                    weapon.projectile_props.projectile_sprite_scale,
-                    weapon.projectile_props.projectile_sprite_scale,
+                    props.projectile_sprite_scale,
+                    props.projectile_sprite_scale,
                     1.,
                 ),
 
@@ -954,7 +1191,7 @@ fn spawn_sprite(
         AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
         animatable.clone(),
         Movable {
-            speed: 100.,
+            speed: props.projectile_speed,
             direction: Direction::Custom(direction_translation),
             is_moving: true,
             current_animation_indices: animatable.moving_anim_indices.clone(),
@@ -962,10 +1199,13 @@ fn spawn_sprite(
             is_state_changed: true,
         },
         Projectile {
-            props: weapon.projectile_props.clone(),
+            props: props.clone(),
+            lifetime: Timer::from_seconds(props.projectile_duration, TimerMode::Once),
+            chain_hits,
+            knocked_back: Vec::new(),
         },
         ProjectileDealsDamage {
-            damage: weapon.projectile_props.projectile_base_damage,
+            damage: props.projectile_base_damage,
             is_triggered: false,
         },
     ));
@@ -1065,20 +1305,17 @@ fn spawn_sprite(
 //     ));
 // }
 
+// Everything goes back to the pools, which despawn their entities when gameplay is left.
 pub fn unload(
-    mut projectile_query: Query<Entity, With<Projectile>>,
-    mut explosion_query: Query<Entity, With<Explosion>>,
-    mut damage_effect_query: Query<Entity, With<DamageEffect>>,
-    // mut level_spawns: ResMut<LevelSpawns>,
+    projectile_query: Query<Entity, With<Projectile>>,
+    effect_query: Query<Entity, With<EffectSpriteSheetAnimatable>>,
+    mut pools: ResMut<EntityPools>,
     mut commands: Commands,
 ) {
-    for entity in projectile_query.iter_mut() {
-        commands.entity(entity).despawn_recursive();
-    }
-    for entity in explosion_query.iter_mut() {
-        commands.entity(entity).despawn_recursive();
+    for entity in projectile_query.iter() {
+        pools.release_projectile(&mut commands, entity);
     }
-    for entity in damage_effect_query.iter_mut() {
-        commands.entity(entity).despawn_recursive();
+    for entity in effect_query.iter() {
+        pools.release_effect(&mut commands, entity);
     }
 }
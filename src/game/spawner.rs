@@ -1,5 +1,12 @@
-use super::level::{MAP_HEIGHT, MAP_WIDTH};
-use super::player::{self, Player};
+use super::biomes::{Biome, BiomeBoss, SelectedBiome};
+use super::chests::DropsChest;
+use super::elites::{roll_elite_affixes, Elite, EliteAffix};
+use super::enemy_behaviours::{EnemyBehaviour, MovementPattern};
+use super::pickups::EnemyFreeze;
+use super::level::{MapMode, MAP_HEIGHT, MAP_WIDTH};
+use super::obstacles::ObstacleMap;
+use super::player::{self, get_players_center, Downed, Invulnerable, Player, PlayerDamageEvent};
+use super::projectile_spawner::DamageType;
 use super::GamePlayState;
 
 use crate::game::level;
@@ -8,6 +15,8 @@ use crate::sprite::{
     Movable,
 };
 
+use crate::sprite_sheets::{SheetGrid, SpriteSheets};
+use crate::tiled_map::MapSpawnPoint;
 use crate::GameState;
 use bevy::prelude::*;
 use bevy::reflect::TypeData;
@@ -29,7 +38,77 @@ pub struct GivesExperience {
 pub struct Enemy {
     pub width: f32,
     pub height: f32,
-    pub is_boss: bool,
+    pub rank: EnemyRank,
+    pub kind: EnemyKind,
+}
+
+// Every enemy sheet there is. Which ones show up, and in what order, comes from the biome.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EnemyKind {
+    Basic,
+    Goblin,
+    BrownMushroom,
+    Slime,
+    Bat,
+    BlueMushroom,
+    Boss,
+}
+
+impl EnemyKind {
+    pub const ALL: [EnemyKind; 7] = [
+        EnemyKind::Basic,
+        EnemyKind::Goblin,
+        EnemyKind::BrownMushroom,
+        EnemyKind::Slime,
+        EnemyKind::Bat,
+        EnemyKind::BlueMushroom,
+        EnemyKind::Boss,
+    ];
+
+    pub fn get_sheet_path(&self) -> &'static str {
+        match self {
+            EnemyKind::Basic => "sprites/enemy/basic.png",
+            EnemyKind::Goblin => "sprites/enemy/goblin/goblin_spritesheet_widle.png",
+            EnemyKind::BrownMushroom => "sprites/enemy/mushroom_brown/sheet.png",
+            EnemyKind::Slime => "sprites/enemy/slime/slime_spritesheet.png",
+            EnemyKind::Bat => "sprites/enemy/bat/bat.png",
+            EnemyKind::BlueMushroom => "sprites/enemy/mushroom_blue/sheet.png",
+            EnemyKind::Boss => "sprites/enemy/boss/boss.png",
+        }
+    }
+
+    // Hits of this type do extra damage, see apply_damage_events.
+    pub fn get_weakness(&self) -> Option<DamageType> {
+        match self {
+            EnemyKind::Goblin => Some(DamageType::Psychological),
+            EnemyKind::BrownMushroom => Some(DamageType::Fire),
+            EnemyKind::Slime => Some(DamageType::Lightning),
+            EnemyKind::Bat => Some(DamageType::Water),
+            EnemyKind::BlueMushroom => Some(DamageType::Earth),
+            EnemyKind::Basic | EnemyKind::Boss => None,
+        }
+    }
+}
+
+// How tough a spawned enemy is. Elites and mid-bosses drop a treasure chest when they die.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnemyRank {
+    Normal,
+    Elite,
+    MidBoss,
+    Boss,
+}
+
+impl EnemyRank {
+    // Knockback is divided by this, so the big ones barely budge.
+    pub fn get_mass(&self) -> f32 {
+        match self {
+            EnemyRank::Normal => 1.,
+            EnemyRank::Elite => 2.5,
+            EnemyRank::MidBoss => 6.,
+            EnemyRank::Boss => 20.,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -38,8 +117,81 @@ pub struct SpawnWave {
 }
 
 const SPAWN_DISTANCE: f32 = 500.;
+const BOSS_SPAWN_NAME: &str = "boss";
+// Further than any spawn position, so a freshly spawned enemy is never recycled straight away.
+const RECYCLE_DISTANCE: f32 = 700.;
 const COLLISION_DISTANCE: f32 = 10.;
 
+const ENEMY_HEALTH: f32 = 20.;
+
+// From the second stage on, any wave enemy can roll into an elite - see Biome::get_elite_chance.
+const ELITE_HEALTH_MULTIPLIER: f32 = 8.;
+const ELITE_SCALE: f32 = 1.5;
+// Only used if an elite somehow ends up without affixes, they tint it otherwise.
+const ELITE_COLOR: Color = Color::rgb(1., 0.85, 0.4);
+const ELITE_EXPERIENCE_MULTIPLIER: u64 = 5;
+const ELITE_CHEST_CHANCE: f64 = 0.5;
+const FAST_SPEED_MULTIPLIER: f32 = 1.7;
+
+const KNOCKBACK_STUN_SECONDS: f32 = 0.2;
+
+const SPLIT_SIZE: f32 = 0.6;
+const SPLIT_SPREAD: f32 = 12.;
+
+type EnemySheet = (
+    Handle<TextureAtlas>,
+    EnemySpriteSheetAnimatable,
+    AnimationIndices,
+    AnimationIndices,
+    f32,
+    f32,
+);
+
+// Keeps late stages playable: waves and splitting elites stop spawning once the cap is reached,
+// and enemies the players have left far behind get moved back in front of them instead of
+// piling up. The mid-boss and boss ignore the cap - they always turn up, one at a time.
+#[derive(Resource, Debug)]
+pub struct EnemyLimits {
+    pub max_enemies: usize,
+    // How long an enemy can stay out past RECYCLE_DISTANCE before it's moved.
+    pub recycle_seconds: f32,
+}
+
+impl Default for EnemyLimits {
+    fn default() -> Self {
+        Self {
+            max_enemies: 300,
+            recycle_seconds: 3.,
+        }
+    }
+}
+
+// Time spent too far from the players. Bosses and mid-bosses don't get one, they stay put.
+#[derive(Component, Debug, Default)]
+pub struct Straggler {
+    pub seconds: f32,
+}
+
+// Set by a weapon hit. The enemy slides back along the hit, slowing down, and doesn't chase until
+// the timer runs out.
+#[derive(Component, Debug)]
+pub struct Knockback {
+    pub velocity: Vec3,
+    pub timer: Timer,
+}
+
+impl Knockback {
+    // Heavier enemies are pushed less and shrug it off sooner.
+    pub fn new(knockback: Vec3, rank: EnemyRank) -> Self {
+        let mass = rank.get_mass();
+
+        Self {
+            velocity: knockback / mass,
+            timer: Timer::from_seconds(KNOCKBACK_STUN_SECONDS / mass, TimerMode::Once),
+        }
+    }
+}
+
 #[derive(Resource)]
 pub struct LevelSpawns {
     pub wave_type: SpawnWave,
@@ -47,15 +199,17 @@ pub struct LevelSpawns {
     pub wave_timer: Timer,
     pub stage_timer: Timer,
     pub current_stage: usize,
+    pub biome: Biome,
 }
 
 impl LevelSpawns {
-    pub fn new() -> Self {
+    pub fn new(biome: Biome) -> Self {
         Self {
             global_timer: Stopwatch::new(),
-            stage_timer: Timer::from_seconds(15., TimerMode::Repeating),
-            wave_timer: Timer::from_seconds(10., TimerMode::Repeating),
+            stage_timer: Timer::from_seconds(biome.get_stage_seconds(), TimerMode::Repeating),
+            wave_timer: Timer::from_seconds(biome.get_wave_seconds(), TimerMode::Repeating),
             current_stage: 1,
+            biome,
             wave_type: SpawnWave {
                 enemy: EnemyType::Basic,
             },
@@ -63,11 +217,23 @@ impl LevelSpawns {
             // main_timer: Timer::from_seconds(20.0, TimerMode::Once),
         }
     }
+
+    // None once the boss is out.
+    pub fn get_seconds_to_boss(&self) -> Option<f32> {
+        let stages_left = self
+            .biome
+            .get_waves()
+            .len()
+            .checked_sub(self.current_stage)?;
+
+        Some(stages_left as f32 * self.stage_timer.duration().as_secs_f32()
+            + self.stage_timer.remaining_secs())
+    }
 }
 
 impl Default for LevelSpawns {
     fn default() -> Self {
-        Self::new()
+        Self::new(Biome::default())
     }
 }
 
@@ -76,66 +242,79 @@ pub struct EnemySpawnerPlugin;
 impl Plugin for EnemySpawnerPlugin {
     fn build(&self, app: &mut App) {
         // app.add_systems(Startup, setup);
-        app.init_resource::<LevelSpawns>();
+        app.init_resource::<LevelSpawns>()
+            .init_resource::<EnemyLimits>();
         // app.add_systems(Update, check_for_spawns);
 
         app.add_systems(OnEnter(GamePlayState::Init), setup)
             .add_systems(OnExit(GameState::Gameplay), unload)
-            .add_systems(OnEnter(GamePlayState::Restart), (unload, restart))
+            .add_systems(OnEnter(GamePlayState::Restart), unload)
             .add_systems(OnEnter(GamePlayState::Boss), spawn_boss)
             .add_systems(
                 Update,
-                (
-                    check_for_spawns,
-                    update_enemy_positions_and_sprites,
-                    update_enemy_collisions,
-                )
-                    .run_if(in_state(GamePlayState::Started)),
+                check_for_spawns.run_if(in_state(GamePlayState::Started)),
+            )
+            .add_systems(
+                Update,
+                recycle_stragglers.run_if(
+                    in_state(GamePlayState::Started).or_else(in_state(GamePlayState::Boss)),
+                ),
             )
+            // Enemies stand still and can't hurt anyone while a freeze clock is running.
             .add_systems(
                 Update,
                 (update_enemy_positions_and_sprites, update_enemy_collisions)
-                    .run_if(in_state(GamePlayState::Boss)),
+                    .run_if(in_state(GamePlayState::Started).or_else(in_state(GamePlayState::Boss)))
+                    .run_if(not(resource_exists::<EnemyFreeze>())),
             );
     }
 }
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {}
-
-fn restart(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.init_resource::<LevelSpawns>();
+// Fresh schedule for the picked biome on every run and restart.
+fn setup(mut commands: Commands, selected_biome: Res<SelectedBiome>) {
+    commands.insert_resource(LevelSpawns::new(selected_biome.0));
 }
 
 fn check_for_spawns(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut sheets: SpriteSheets,
     mut level_spawns: ResMut<LevelSpawns>,
     player_query: Query<&Transform, (With<Player>, Without<Enemy>)>,
     time: Res<Time>,
     mut next_state: ResMut<NextState<GamePlayState>>,
     state: Res<State<GamePlayState>>,
+    map_mode: Res<MapMode>,
+    enemy_query: Query<&Enemy>,
+    enemy_limits: Res<EnemyLimits>,
+    spawn_point_query: Query<(&MapSpawnPoint, &Transform)>,
 ) {
-    let transform = player_query.single();
+    let Some(players_center) = get_players_center(player_query.iter()) else {
+        return;
+    };
 
     level_spawns.global_timer.tick(time.delta());
     level_spawns.global_timer.tick(time.delta());
     level_spawns.wave_timer.tick(time.delta());
     level_spawns.stage_timer.tick(time.delta());
 
-    let num_enemies_per_spawn = 10 * level_spawns.current_stage;
+    let waves = level_spawns.biome.get_waves();
 
     if level_spawns.wave_timer.just_finished() {
-        let player_position: Vec2 = Vec2::new(transform.translation.x, transform.translation.y);
+        let player_position: Vec2 = players_center.truncate();
+        let wave = waves[(level_spawns.current_stage - 1).min(waves.len() - 1)];
+        let room = enemy_limits
+            .max_enemies
+            .saturating_sub(enemy_query.iter().count());
 
         spawn_enemies(
-            num_enemies_per_spawn,
-            false,
+            wave.count.min(room),
+            wave.enemy,
+            EnemyRank::Normal,
             &mut commands,
-            &asset_server,
-            &mut texture_atlases,
+            &mut sheets,
             player_position,
             &level_spawns,
+            &map_mode,
         );
     }
 
@@ -144,7 +323,24 @@ fn check_for_spawns(
         println!("Stage finished");
         level_spawns.current_stage = level_spawns.current_stage + 1;
 
-        if level_spawns.current_stage > 5 && state.get() != &GamePlayState::Boss {
+        // Halfway through the schedule.
+        if level_spawns.current_stage == waves.len() / 2 + 1 {
+            println!("Mid-boss");
+            let mid_boss = level_spawns.biome.get_mid_boss();
+
+            spawn_boss_enemy(
+                mid_boss.enemy,
+                EnemyRank::MidBoss,
+                &mut commands,
+                &mut sheets,
+                players_center.truncate(),
+                &level_spawns,
+                &map_mode,
+                &spawn_point_query,
+            );
+        }
+
+        if level_spawns.current_stage > waves.len() && state.get() != &GamePlayState::Boss {
             println!("Boss stage");
             next_state.set(GamePlayState::Boss);
         }
@@ -153,32 +349,86 @@ fn check_for_spawns(
 
 fn spawn_boss(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut sheets: SpriteSheets,
     mut level_spawns: ResMut<LevelSpawns>,
     player_query: Query<&Transform, (With<Player>, Without<Enemy>)>,
     time: Res<Time>,
     mut next_state: ResMut<NextState<GamePlayState>>,
     state: Res<State<GamePlayState>>,
+    map_mode: Res<MapMode>,
+    enemy_query: Query<&Enemy>,
+    spawn_point_query: Query<(&MapSpawnPoint, &Transform)>,
 ) {
-    let transform = player_query.single();
+    // Coming back to the boss fight after a chest or level up pause.
+    if enemy_query.iter().any(|enemy| enemy.rank == EnemyRank::Boss) {
+        return;
+    }
 
-    let player_position: Vec2 = Vec2::new(transform.translation.x, transform.translation.y);
+    let Some(players_center) = get_players_center(player_query.iter()) else {
+        return;
+    };
 
-    spawn_enemies(
-        1,
-        true,
+    let player_position: Vec2 = players_center.truncate();
+
+    let boss = level_spawns.biome.get_boss();
+
+    spawn_boss_enemy(
+        boss.enemy,
+        EnemyRank::Boss,
         &mut commands,
-        &asset_server,
-        &mut texture_atlases,
+        &mut sheets,
         player_position,
         &level_spawns,
+        &map_mode,
+        &spawn_point_query,
     );
 }
 
+// Arena maps can have a `boss` spawn point for the mid-boss and boss to come in at. Endless
+// runs drift away from the map, so they always spawn around the players.
+fn spawn_boss_enemy(
+    kind: EnemyKind,
+    rank: EnemyRank,
+    commands: &mut Commands,
+    sheets: &mut SpriteSheets,
+    player_position: Vec2,
+    level_spawns: &ResMut<LevelSpawns>,
+    map_mode: &MapMode,
+    spawn_point_query: &Query<(&MapSpawnPoint, &Transform)>,
+) {
+    let spawn_point = spawn_point_query
+        .iter()
+        .find(|(spawn_point, _)| spawn_point.name == BOSS_SPAWN_NAME);
+
+    match (map_mode, spawn_point) {
+        (MapMode::Arena, Some((_, transform))) => {
+            spawn_enemy(
+                &get_enemy(kind, sheets),
+                kind,
+                rank,
+                transform.translation.truncate(),
+                1.,
+                commands,
+                level_spawns,
+            );
+        }
+        _ => {
+            spawn_enemies(
+                1,
+                kind,
+                rank,
+                commands,
+                sheets,
+                player_position,
+                level_spawns,
+                map_mode,
+            );
+        }
+    }
+}
+
 fn get_basic_enemy(
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
-    assets: &Res<AssetServer>,
+    sheets: &mut SpriteSheets,
 ) -> (
     Handle<TextureAtlas>,
     EnemySpriteSheetAnimatable,
@@ -198,18 +448,11 @@ fn get_basic_enemy(
         moving_anim_indices: run_animation_indices.clone(),
     });
 
-    let texture_handle = assets.load("sprites/enemy/basic.png");
-    let texture_atlas = TextureAtlas::from_grid(
-        texture_handle,
-        Vec2::new(ENEMY_WIDTH, ENEMY_HEIGHT),
-        3,
-        1,
-        None,
-        None,
+    let texture_atlas_handle = sheets.get_grid(
+        EnemyKind::Basic.get_sheet_path(),
+        SheetGrid::new(Vec2::new(ENEMY_WIDTH, ENEMY_HEIGHT), 3, 1),
     );
 
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
     (
         texture_atlas_handle,
         animatable,
@@ -221,8 +464,7 @@ fn get_basic_enemy(
 }
 
 fn get_brown_mushroom_enemy(
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
-    assets: &Res<AssetServer>,
+    sheets: &mut SpriteSheets,
 ) -> (
     Handle<TextureAtlas>,
     EnemySpriteSheetAnimatable,
@@ -242,18 +484,11 @@ fn get_brown_mushroom_enemy(
         moving_anim_indices: run_animation_indices.clone(),
     });
 
-    let texture_handle = assets.load("sprites/enemy/mushroom_brown/sheet.png");
-    let texture_atlas = TextureAtlas::from_grid(
-        texture_handle,
-        Vec2::new(ENEMY_WIDTH, ENEMY_HEIGHT),
-        7,
-        8,
-        None,
-        None,
+    let texture_atlas_handle = sheets.get_grid(
+        EnemyKind::BrownMushroom.get_sheet_path(),
+        SheetGrid::new(Vec2::new(ENEMY_WIDTH, ENEMY_HEIGHT), 7, 8),
     );
 
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
     (
         texture_atlas_handle,
         animatable,
@@ -265,8 +500,7 @@ fn get_brown_mushroom_enemy(
 }
 
 fn get_blue_mushroom_enemy(
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
-    assets: &Res<AssetServer>,
+    sheets: &mut SpriteSheets,
 ) -> (
     Handle<TextureAtlas>,
     EnemySpriteSheetAnimatable,
@@ -286,18 +520,11 @@ fn get_blue_mushroom_enemy(
         moving_anim_indices: run_animation_indices.clone(),
     });
 
-    let texture_handle = assets.load("sprites/enemy/mushroom_blue/sheet.png");
-    let texture_atlas = TextureAtlas::from_grid(
-        texture_handle,
-        Vec2::new(ENEMY_WIDTH, ENEMY_HEIGHT),
-        7,
-        8,
-        None,
-        None,
+    let texture_atlas_handle = sheets.get_grid(
+        EnemyKind::BlueMushroom.get_sheet_path(),
+        SheetGrid::new(Vec2::new(ENEMY_WIDTH, ENEMY_HEIGHT), 7, 8),
     );
 
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
     (
         texture_atlas_handle,
         animatable,
@@ -309,8 +536,7 @@ fn get_blue_mushroom_enemy(
 }
 
 fn get_slime_enemy(
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
-    assets: &Res<AssetServer>,
+    sheets: &mut SpriteSheets,
 ) -> (
     Handle<TextureAtlas>,
     EnemySpriteSheetAnimatable,
@@ -330,18 +556,11 @@ fn get_slime_enemy(
         moving_anim_indices: run_animation_indices.clone(),
     });
 
-    let texture_handle = assets.load("sprites/enemy/slime/slime_spritesheet.png");
-    let texture_atlas = TextureAtlas::from_grid(
-        texture_handle,
-        Vec2::new(ENEMY_WIDTH, ENEMY_HEIGHT),
-        6,
-        1,
-        None,
-        None,
+    let texture_atlas_handle = sheets.get_grid(
+        EnemyKind::Slime.get_sheet_path(),
+        SheetGrid::new(Vec2::new(ENEMY_WIDTH, ENEMY_HEIGHT), 6, 1),
     );
 
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
     (
         texture_atlas_handle,
         animatable,
@@ -353,8 +572,7 @@ fn get_slime_enemy(
 }
 
 fn get_goblin_enemy(
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
-    assets: &Res<AssetServer>,
+    sheets: &mut SpriteSheets,
 ) -> (
     Handle<TextureAtlas>,
     EnemySpriteSheetAnimatable,
@@ -374,18 +592,11 @@ fn get_goblin_enemy(
         moving_anim_indices: run_animation_indices.clone(),
     });
 
-    let texture_handle = assets.load("sprites/enemy/goblin/goblin_spritesheet_widle.png");
-    let texture_atlas = TextureAtlas::from_grid(
-        texture_handle,
-        Vec2::new(ENEMY_WIDTH, ENEMY_HEIGHT),
-        6,
-        2,
-        None,
-        None,
+    let texture_atlas_handle = sheets.get_grid(
+        EnemyKind::Goblin.get_sheet_path(),
+        SheetGrid::new(Vec2::new(ENEMY_WIDTH, ENEMY_HEIGHT), 6, 2),
     );
 
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
     (
         texture_atlas_handle,
         animatable,
@@ -397,8 +608,7 @@ fn get_goblin_enemy(
 }
 
 fn get_boss_enemy(
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
-    assets: &Res<AssetServer>,
+    sheets: &mut SpriteSheets,
 ) -> (
     Handle<TextureAtlas>,
     EnemySpriteSheetAnimatable,
@@ -421,18 +631,11 @@ fn get_boss_enemy(
         moving_anim_indices: run_animation_indices.clone(),
     });
 
-    let texture_handle = assets.load("sprites/enemy/boss/boss.png");
-    let texture_atlas = TextureAtlas::from_grid(
-        texture_handle,
-        Vec2::new(ENEMY_WIDTH, ENEMY_HEIGHT),
-        12,
-        2,
-        None,
-        None,
+    let texture_atlas_handle = sheets.get_grid(
+        EnemyKind::Boss.get_sheet_path(),
+        SheetGrid::new(Vec2::new(ENEMY_WIDTH, ENEMY_HEIGHT), 12, 2),
     );
 
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
     (
         texture_atlas_handle,
         animatable,
@@ -444,8 +647,7 @@ fn get_boss_enemy(
 }
 
 fn get_bat_enemy(
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
-    assets: &Res<AssetServer>,
+    sheets: &mut SpriteSheets,
 ) -> (
     Handle<TextureAtlas>,
     EnemySpriteSheetAnimatable,
@@ -465,18 +667,11 @@ fn get_bat_enemy(
         moving_anim_indices: run_animation_indices.clone(),
     });
 
-    let texture_handle = assets.load("sprites/enemy/bat/bat.png");
-    let texture_atlas = TextureAtlas::from_grid(
-        texture_handle,
-        Vec2::new(ENEMY_WIDTH, ENEMY_HEIGHT),
-        4,
-        1,
-        None,
-        None,
+    let texture_atlas_handle = sheets.get_grid(
+        EnemyKind::Bat.get_sheet_path(),
+        SheetGrid::new(Vec2::new(ENEMY_WIDTH, ENEMY_HEIGHT), 4, 1),
     );
 
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
     (
         texture_atlas_handle,
         animatable,
@@ -487,15 +682,153 @@ fn get_bat_enemy(
     )
 }
 
+fn get_enemy(
+    kind: EnemyKind,
+    sheets: &mut SpriteSheets,
+) -> EnemySheet {
+    match kind {
+        EnemyKind::Basic => get_basic_enemy(sheets),
+        EnemyKind::Goblin => get_goblin_enemy(sheets),
+        EnemyKind::BrownMushroom => get_brown_mushroom_enemy(sheets),
+        EnemyKind::Slime => get_slime_enemy(sheets),
+        EnemyKind::Bat => get_bat_enemy(sheets),
+        EnemyKind::BlueMushroom => get_blue_mushroom_enemy(sheets),
+        EnemyKind::Boss => get_boss_enemy(sheets),
+    }
+}
+
+// Anything above a normal enemy is the same sheet, scaled up and tinted. Bosses and mid-bosses
+// take their settings from the biome, and any normal enemy of a wave can roll into an elite.
 fn spawn_enemies(
     num_enemies: usize,
-    is_boss: bool,
+    kind: EnemyKind,
+    rank: EnemyRank,
     commands: &mut Commands,
-    assets: &Res<AssetServer>,
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
+    sheets: &mut SpriteSheets,
     player_position: Vec2,
     level_spawns: &ResMut<LevelSpawns>,
+    map_mode: &MapMode,
+) {
+    let sheet = get_enemy(kind, sheets);
+
+    let mut rng: ThreadRng = rand::thread_rng();
+
+    println!("Wave spawn - no enemies - {}", num_enemies);
+
+    for i in 0..num_enemies {
+        let position = get_spawn_position(player_position, map_mode, &mut rng);
+        println!("Spawn enemy no {} at {} x {}", i, position.x, position.y);
+
+        let enemy_rank = if rank == EnemyRank::Normal
+            && level_spawns.current_stage > 1
+            && rng.gen_bool(level_spawns.biome.get_elite_chance())
+        {
+            EnemyRank::Elite
+        } else {
+            rank
+        };
+
+        spawn_enemy(
+            &sheet,
+            kind,
+            enemy_rank,
+            position,
+            1.,
+            commands,
+            level_spawns,
+        );
+    }
+}
+
+// Somewhere off-screen around the players, kept inside the arena walls.
+fn get_spawn_position(player_position: Vec2, map_mode: &MapMode, rng: &mut ThreadRng) -> Vec2 {
+    let rnd_x: f32 = rng.gen_range(0. ..SPAWN_DISTANCE);
+    let rnd_y: f32 = if SPAWN_DISTANCE - rnd_x > 0. {
+        ((SPAWN_DISTANCE - rnd_x) * 0.75).clamp(0., SPAWN_DISTANCE * 0.75)
+    } else {
+        0.
+    };
+
+    // negative and positive x+y axis.
+    let x_pos = player_position.x
+        + if rng.gen_bool(0.5) {
+            rnd_x * -1.
+        } else {
+            rnd_x
+        };
+    let y_pos = player_position.y
+        + if rng.gen_bool(0.5) {
+            rnd_y * -1.
+        } else {
+            rnd_y
+        };
+
+    let (final_x_pos, final_y_pos) = match map_mode {
+        MapMode::Arena => (
+            x_pos.clamp(-1. * MAP_WIDTH / 2., MAP_WIDTH / 2.),
+            y_pos.clamp(-1. * MAP_HEIGHT / 2., MAP_HEIGHT / 2.),
+        ),
+        MapMode::Endless => (x_pos, y_pos),
+    };
+
+    Vec2::new(final_x_pos, final_y_pos)
+}
+
+// Smaller, weaker copies of an enemy, left behind when a splitting elite dies.
+pub fn spawn_split_enemies(
+    num_enemies: usize,
+    kind: EnemyKind,
+    position: Vec2,
+    commands: &mut Commands,
+    sheets: &mut SpriteSheets,
+    level_spawns: &LevelSpawns,
 ) {
+    let sheet = get_enemy(kind, sheets);
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..num_enemies {
+        let offset = Vec2::new(
+            rng.gen_range(-SPLIT_SPREAD..SPLIT_SPREAD),
+            rng.gen_range(-SPLIT_SPREAD..SPLIT_SPREAD),
+        );
+
+        spawn_enemy(
+            &sheet,
+            kind,
+            EnemyRank::Normal,
+            position + offset,
+            SPLIT_SIZE,
+            commands,
+            level_spawns,
+        );
+    }
+}
+
+// Bosses always come straight for the players, everything else moves the way its kind does.
+fn get_movement_pattern(kind: EnemyKind, rank: EnemyRank) -> MovementPattern {
+    if rank == EnemyRank::MidBoss || rank == EnemyRank::Boss {
+        return MovementPattern::Chase;
+    }
+
+    match kind {
+        EnemyKind::Basic | EnemyKind::BrownMushroom | EnemyKind::Boss => MovementPattern::Chase,
+        EnemyKind::Goblin => MovementPattern::OrbitDash,
+        EnemyKind::Slime => MovementPattern::Hop,
+        EnemyKind::Bat => MovementPattern::Swoop,
+        EnemyKind::BlueMushroom => MovementPattern::Flee,
+    }
+}
+
+// Size scales the sprite, health and experience of the enemy on top of its rank.
+fn spawn_enemy(
+    sheet: &EnemySheet,
+    kind: EnemyKind,
+    rank: EnemyRank,
+    position: Vec2,
+    size: f32,
+    commands: &mut Commands,
+    level_spawns: &LevelSpawns,
+) -> Entity {
     let (
         texture_atlas_handle,
         animatable,
@@ -503,87 +836,103 @@ fn spawn_enemies(
         run_animation_indices,
         enemy_width,
         enemy_height,
-    ) = if is_boss {
-        get_boss_enemy(texture_atlases, assets)
-    } else if level_spawns.current_stage == 1 {
-        get_goblin_enemy(texture_atlases, assets)
-    } else if level_spawns.current_stage == 2 {
-        get_brown_mushroom_enemy(texture_atlases, assets)
-    } else if level_spawns.current_stage == 3 {
-        get_slime_enemy(texture_atlases, assets)
-    } else if level_spawns.current_stage == 4 {
-        get_bat_enemy(texture_atlases, assets)
-    } else {
-        // 5
-        get_blue_mushroom_enemy(texture_atlases, assets)
+    ) = sheet;
+
+    let affixes = match rank {
+        EnemyRank::Elite => roll_elite_affixes(kind),
+        _ => vec![],
     };
 
-    let mut rng: ThreadRng = rand::thread_rng();
+    let boss = match rank {
+        EnemyRank::Normal => None,
+        EnemyRank::Elite => Some(BiomeBoss {
+            enemy: kind,
+            health: ENEMY_HEALTH * ELITE_HEALTH_MULTIPLIER,
+            scale: ELITE_SCALE,
+            color: affixes
+                .first()
+                .map(|affix| affix.get_color())
+                .unwrap_or(ELITE_COLOR),
+        }),
+        EnemyRank::MidBoss => Some(level_spawns.biome.get_mid_boss()),
+        EnemyRank::Boss => Some(level_spawns.biome.get_boss()),
+    };
 
-    println!("Wave spawn - no enemies - {}", num_enemies);
+    let scale = boss.map(|boss| boss.scale).unwrap_or(1.) * size;
+    let health = boss.map(|boss| boss.health).unwrap_or(ENEMY_HEALTH) * size;
 
-    for i in 0..num_enemies {
-        let rnd_x: f32 = rng.gen_range(0. ..SPAWN_DISTANCE);
-        let rnd_y: f32 = if SPAWN_DISTANCE - rnd_x > 0. {
-            ((SPAWN_DISTANCE - rnd_x) * 0.75).clamp(0., SPAWN_DISTANCE * 0.75)
-        } else {
-            0.
-        };
+    let mut experience = 10 * (1 + level_spawns.current_stage / 10) as u64;
+    if rank == EnemyRank::Elite {
+        experience *= ELITE_EXPERIENCE_MULTIPLIER;
+    }
 
-        // negative and positive x+y axis.
-        let x_pos = player_position.x
-            + if rng.gen_bool(0.5) {
-                rnd_x * -1.
-            } else {
-                rnd_x
-            };
-        let y_pos = player_position.y
-            + if rng.gen_bool(0.5) {
-                rnd_y * -1.
-            } else {
-                rnd_y
-            };
+    let mut speed = 50.;
+    if affixes.contains(&EliteAffix::Fast) {
+        speed *= FAST_SPEED_MULTIPLIER;
+    }
 
-        let final_x_pos = x_pos.clamp(-1. * MAP_WIDTH / 2., MAP_WIDTH / 2.);
-        let final_y_pos = y_pos.clamp(-1. * MAP_HEIGHT / 2., MAP_HEIGHT / 2.);
-        println!("Spawn enemy no {} at {} x {}", i, final_x_pos, final_y_pos);
-
-        commands.spawn((
-            SpriteSheetBundle {
-                texture_atlas: texture_atlas_handle.clone(),
-                sprite: TextureAtlasSprite::new(run_animation_indices.first),
-                transform: Transform::from_xyz(
-                    final_x_pos,
-                    final_y_pos,
-                    (1 + level_spawns.current_stage) as f32,
-                ),
+    let mut enemy = commands.spawn((
+        SpriteSheetBundle {
+            texture_atlas: texture_atlas_handle.clone(),
+            sprite: TextureAtlasSprite {
+                index: run_animation_indices.first,
+                color: boss.map(|boss| boss.color).unwrap_or(Color::WHITE),
                 ..default()
             },
-            AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
-            animatable.clone(),
-            Movable {
-                speed: 50.,
-                direction: Direction::Right,
-                is_moving: false,
-                current_animation_indices: idle_animation_indices,
-                is_collided: false,
-                is_state_changed: true,
-            },
-            Health {
-                total: if is_boss { 200. } else { 20. },
-            },
-            Enemy {
-                width: enemy_width,
-                height: enemy_height,
-                is_boss: is_boss,
-            },
-            GivesExperience { experience: 10 * (1 + level_spawns.current_stage / 10) as u64 },
-            DealsDamage {
-                damage: (10. + (level_spawns.current_stage as f32)),
-                tick_timer: Timer::from_seconds(1., TimerMode::Once),
-            },
-        ));
+            transform: Transform::from_xyz(
+                position.x,
+                position.y,
+                (1 + level_spawns.current_stage) as f32,
+            )
+            .with_scale(Vec3::new(scale, scale, 1.)),
+            ..default()
+        },
+        AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
+        *animatable,
+        Movable {
+            speed,
+            direction: Direction::Right,
+            is_moving: false,
+            current_animation_indices: *idle_animation_indices,
+            is_collided: false,
+            is_state_changed: true,
+        },
+        Health::new(health),
+        EnemyBehaviour::new(get_movement_pattern(kind, rank), health),
+        Enemy {
+            width: enemy_width * scale,
+            height: enemy_height * scale,
+            rank,
+            kind,
+        },
+        GivesExperience {
+            experience: ((experience as f32) * size).max(1.) as u64,
+        },
+        DealsDamage {
+            damage: (10. + (level_spawns.current_stage as f32)),
+            tick_timer: Timer::from_seconds(1., TimerMode::Once),
+        },
+    ));
+
+    match rank {
+        EnemyRank::Normal => {
+            enemy.insert(Straggler::default());
+        }
+        EnemyRank::Elite => {
+            enemy.insert(Straggler::default());
+            enemy.insert(Elite { affixes });
+
+            if rand::thread_rng().gen_bool(ELITE_CHEST_CHANCE) {
+                enemy.insert(DropsChest::elite());
+            }
+        }
+        EnemyRank::MidBoss => {
+            enemy.insert(DropsChest::mid_boss());
+        }
+        _ => {}
     }
+
+    enemy.id()
 }
 
 pub fn get_indices_for_movable(
@@ -602,15 +951,17 @@ pub fn get_indices_for_movable(
 }
 
 fn update_enemy_collisions(
-    mut player_query: Query<(&Transform, &mut Health), (With<Player>, Without<Enemy>)>,
+    mut player_query: Query<
+        (&Transform, Option<&Invulnerable>, Entity),
+        (With<Player>, Without<Enemy>, Without<Downed>),
+    >,
     mut enemy_query_collision: Query<
         (&Transform, &mut Movable, &mut DealsDamage, &Enemy, Entity),
         (Without<Player>),
     >,
+    mut player_damage_events: EventWriter<PlayerDamageEvent>,
     time: Res<Time>,
 ) {
-    let (player_transform, mut player_health) = player_query.single_mut();
-
     // How I'll do collision:
 
     // Two loops, one for primary entity, one nested, for collision checks.
@@ -637,21 +988,29 @@ fn update_enemy_collisions(
 
         enemy_damage.tick_timer.tick(time.delta());
 
-        // Check for player collision
-        let distance: f32 = enemy_transform
-            .translation
-            .distance(player_transform.translation);
+        // Check for player collision - downed players are left alone.
+        for (player_transform, invulnerable, player_entity) in player_query.iter_mut() {
+            let distance: f32 = enemy_transform
+                .translation
+                .distance(player_transform.translation);
 
-        if distance < collision_distance {
-            println!("COLLIDED WITH PLAYER {}", distance);
-            colliding_enemies.push(ent_original.index());
+            if distance < collision_distance {
+                println!("COLLIDED WITH PLAYER {}", distance);
+                if !collided {
+                    colliding_enemies.push(ent_original.index());
+                }
 
-            if enemy_damage.tick_timer.finished() {
-                enemy_damage.tick_timer.reset();
-                player_health.total -= enemy_damage.damage;
-            }
+                if enemy_damage.tick_timer.finished() && invulnerable.is_none() {
+                    enemy_damage.tick_timer.reset();
+                    player_damage_events.send(PlayerDamageEvent {
+                        entity: player_entity,
+                        damage: enemy_damage.damage,
+                        source: enemy_transform.translation,
+                    });
+                }
 
-            collided = true;
+                collided = true;
+            }
         }
 
         // If no player collision, check for fellow enemy collisions
@@ -702,14 +1061,23 @@ pub fn update_enemy_positions_and_sprites(
             &mut TextureAtlasSprite,
             &mut AnimationTimer,
             &Health,
+            &Enemy,
+            Option<&mut EnemyBehaviour>,
+            Option<&mut Knockback>,
             Entity,
         ),
         (With<Enemy>, Without<Player>),
     >,
-    player_query: Query<&mut Transform, (With<Player>, Without<Enemy>)>,
+    player_query: Query<&Transform, (With<Player>, Without<Enemy>, Without<Downed>)>,
+    obstacle_map: Res<ObstacleMap>,
     mut commands: Commands,
 ) {
-    if let Ok(player_transform) = player_query.get_single() {
+    let player_positions: Vec<Vec3> = player_query
+        .iter()
+        .map(|transform| transform.translation)
+        .collect();
+
+    if !player_positions.is_empty() {
         for (
             mut enemy_transform,
             mut enemy_movable,
@@ -717,21 +1085,66 @@ pub fn update_enemy_positions_and_sprites(
             mut enemy_sprite,
             mut enemy_timer,
             health,
+            enemy,
+            behaviour,
+            knockback,
             entity,
         ) in enemy_query.iter_mut()
         {
             let old_z = enemy_transform.translation.z;
+
+            // Knocked back enemies hold still in their current frame while they slide.
+            if let Some(mut knockback) = knockback {
+                knockback.timer.tick(time.delta());
+
+                if knockback.timer.finished() {
+                    commands.entity(entity).remove::<Knockback>();
+                }
+
+                let slide =
+                    knockback.velocity * (1. - knockback.timer.percent()) * time.delta_seconds();
+                let position = obstacle_map.get_position_around_obstacles(
+                    enemy_transform.translation.truncate(),
+                    (enemy_transform.translation + slide).truncate(),
+                    enemy_transform.scale.x * enemy.width / 4.,
+                );
+
+                enemy_transform.translation = position.extend(old_z);
+                continue;
+            }
+
             // Begin check to MOVE towards player
             let old_is_moving = enemy_movable.is_moving;
 
-            let normalized_translation =
-                Vec3::normalize(player_transform.translation - enemy_transform.translation);
+            // Chase whichever player standing is closest.
+            let target = player_positions
+                .iter()
+                .min_by(|a, b| {
+                    a.distance_squared(enemy_transform.translation)
+                        .total_cmp(&b.distance_squared(enemy_transform.translation))
+                })
+                .unwrap();
+
+            let normalized_translation = match behaviour {
+                Some(mut behaviour) => behaviour.get_movement(
+                    enemy_transform.translation,
+                    *target,
+                    health.total,
+                    time.delta(),
+                ),
+                None => Vec3::normalize(*target - enemy_transform.translation),
+            };
 
             let moving = normalized_translation * enemy_movable.speed * time.delta_seconds();
 
             if !enemy_movable.is_collided {
-                enemy_transform.translation += moving;
-                enemy_transform.translation.z = old_z; // Think its overriding it here, so make sure it stays same as from spawn
+                let position = obstacle_map.get_position_around_obstacles(
+                    enemy_transform.translation.truncate(),
+                    (enemy_transform.translation + moving).truncate(),
+                    enemy_transform.scale.x * enemy.width / 4.,
+                );
+
+                enemy_transform.translation = position.extend(old_z); // Think its overriding it here, so make sure it stays same as from spawn
 
                 if normalized_translation.x > 0. {
                     enemy_sprite.flip_x = false;
@@ -779,6 +1192,38 @@ pub fn update_enemy_positions_and_sprites(
     }
 }
 
+// Enemies the players have outrun for a while are moved back to a fresh spawn position.
+fn recycle_stragglers(
+    mut enemy_query: Query<(&mut Transform, &mut Straggler), (With<Enemy>, Without<Player>)>,
+    player_query: Query<&Transform, (With<Player>, Without<Enemy>)>,
+    enemy_limits: Res<EnemyLimits>,
+    map_mode: Res<MapMode>,
+    time: Res<Time>,
+) {
+    let Some(players_center) = get_players_center(player_query.iter()) else {
+        return;
+    };
+
+    let player_position = players_center.truncate();
+    let mut rng = rand::thread_rng();
+
+    for (mut transform, mut straggler) in enemy_query.iter_mut() {
+        if transform.translation.truncate().distance(player_position) < RECYCLE_DISTANCE {
+            straggler.seconds = 0.;
+            continue;
+        }
+
+        straggler.seconds += time.delta_seconds();
+
+        if straggler.seconds >= enemy_limits.recycle_seconds {
+            let position = get_spawn_position(player_position, &map_mode, &mut rng);
+
+            transform.translation = position.extend(transform.translation.z);
+            straggler.seconds = 0.;
+        }
+    }
+}
+
 pub fn unload(
     mut query: Query<Entity, With<Enemy>>,
     mut level_spawns: ResMut<LevelSpawns>,
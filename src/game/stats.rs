@@ -0,0 +1,136 @@
+use bevy::prelude::*;
+
+use crate::character_select::CharacterStats;
+use crate::meta::MetaProgress;
+
+use super::GamePlayState;
+
+// What a player's weapons fire with. Each weapon brings its own base values (see ProjectileProps)
+// and the player's modifiers go on top - from their character, meta upgrades (see MetaProgress),
+// chest passives and any status effects on them. See PlayerStats::get.
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            expire_status_effects
+                .run_if(in_state(GamePlayState::Started).or_else(in_state(GamePlayState::Boss))),
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stat {
+    Damage,
+    // Scale on how long a weapon takes to fire again - lower is faster.
+    Cooldown,
+    Area,
+    ProjectileSpeed,
+    // Extra projectiles per shot.
+    Amount,
+    // How long a projectile lasts.
+    Duration,
+    CritChance,
+    CritMultiplier,
+    // Health back per second.
+    Regen,
+    // Taken off each hit.
+    Armor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ModifierKind {
+    Add(f32),
+    Multiply(f32),
+}
+
+#[derive(Debug, Clone)]
+pub struct StatModifier {
+    pub stat: Stat,
+    pub kind: ModifierKind,
+    // Status effects wear off when this runs out. Everything else lasts the whole run.
+    pub expires: Option<Timer>,
+}
+
+impl StatModifier {
+    pub fn add(stat: Stat, amount: f32) -> Self {
+        Self {
+            stat,
+            kind: ModifierKind::Add(amount),
+            expires: None,
+        }
+    }
+
+    pub fn multiply(stat: Stat, factor: f32) -> Self {
+        Self {
+            stat,
+            kind: ModifierKind::Multiply(factor),
+            expires: None,
+        }
+    }
+
+    // Makes it a status effect that wears off after this long.
+    pub fn lasting(mut self, seconds: f32) -> Self {
+        self.expires = Some(Timer::from_seconds(seconds, TimerMode::Once));
+        self
+    }
+}
+
+#[derive(Component, Debug, Default)]
+pub struct PlayerStats {
+    modifiers: Vec<StatModifier>,
+}
+
+impl PlayerStats {
+    // Starts from the character's might, cooldown, area, regen and armor, plus whatever meta
+    // upgrades have been bought.
+    pub fn new(character: &CharacterStats, meta: &MetaProgress) -> Self {
+        let mut modifiers = vec![
+            StatModifier::multiply(Stat::Damage, character.might),
+            StatModifier::multiply(Stat::Cooldown, character.cooldown),
+            StatModifier::multiply(Stat::Area, character.area),
+            StatModifier::add(Stat::Regen, character.regen),
+            StatModifier::add(Stat::Armor, character.armor),
+        ];
+
+        modifiers.extend(meta.get_modifiers());
+
+        Self { modifiers }
+    }
+
+    pub fn add_modifier(&mut self, modifier: StatModifier) {
+        self.modifiers.push(modifier);
+    }
+
+    // Every additive modifier is summed onto the base first, then the total is scaled by every
+    // multiplier, so the order they were picked up in doesn't matter.
+    pub fn get(&self, stat: Stat, base: f32) -> f32 {
+        let mut added = 0.;
+        let mut multiplier = 1.;
+
+        for modifier in self
+            .modifiers
+            .iter()
+            .filter(|modifier| modifier.stat == stat)
+        {
+            match modifier.kind {
+                ModifierKind::Add(amount) => added += amount,
+                ModifierKind::Multiply(factor) => multiplier *= factor,
+            }
+        }
+
+        (base + added) * multiplier
+    }
+}
+
+fn expire_status_effects(mut stats_query: Query<&mut PlayerStats>, time: Res<Time>) {
+    for mut stats in stats_query.iter_mut() {
+        stats
+            .modifiers
+            .retain_mut(|modifier| match modifier.expires.as_mut() {
+                Some(timer) => !timer.tick(time.delta()).finished(),
+                None => true,
+            });
+    }
+}
@@ -1,11 +1,20 @@
 // use crate::player::CharacterLife;
 use bevy::prelude::*;
 
-use super::player::{CanLevel, Player};
+use bevy::time::Stopwatch;
+
+use super::abilities::{Dash, Ultimate};
+use super::controls::PlayerControls;
+use super::player::{get_selected_characters, CanLevel, Player};
+use super::projectile_spawner::EnemyKilledEvent;
+use super::spawner::LevelSpawns;
+use super::weapons::{get_passive_icon, get_weapon_sprite, StatUpgrade, WeaponsEnum};
 use crate::{
+    character_select::{CharacterRegistry, SelectedCharacter},
     game::GamePlayState,
-    main_menu::{BORDER_COLOR, DARK_PURPLE, LIGHT_TEAL, PURPLISH},
+    main_menu::{BORDER_COLOR, DARK_PURPLE, LIGHT_BLUE, LIGHT_TEAL, PURPLE, PURPLISH, WHITE},
     sprite::Health,
+    sprite_sheets::SpriteSheets,
     GameState,
 };
 
@@ -14,12 +23,33 @@ pub struct GameUiPlugin;
 impl Plugin for GameUiPlugin {
     fn build(&self, app: &mut App) {
         println!("Load game play ui plugin");
-        app.add_systems(OnEnter(GamePlayState::Init), ui_setup)
+        app.init_resource::<RunStats>()
+            .add_systems(OnEnter(GamePlayState::Init), ui_setup)
             .add_systems(OnEnter(GamePlayState::Restart), unload)
-            .insert_resource(Time::<Fixed>::from_seconds(0.5))
             .add_systems(
-                FixedUpdate,
-                ui_update.run_if(in_state(GamePlayState::Started)),
+                Update,
+                (
+                    update_run_stats,
+                    ability_ui_update,
+                    weapon_cooldown_ui_update,
+                )
+                    .run_if(
+                        in_state(GamePlayState::Started).or_else(in_state(GamePlayState::Boss)),
+                    ),
+            )
+            // Everything else only redraws when what it shows has changed, so these can run in
+            // any state.
+            .add_systems(
+                Update,
+                (
+                    health_ui_update,
+                    level_ui_update,
+                    run_stats_ui_update
+                        .after(update_run_stats)
+                        .run_if(resource_changed::<RunStats>()),
+                    slot_strip_ui_update,
+                )
+                    .run_if(in_state(GameState::Gameplay)),
             )
             .add_systems(OnExit(GameState::Gameplay), unload);
 
@@ -28,8 +58,11 @@ impl Plugin for GameUiPlugin {
     }
 }
 
+// Bars carry the index of the player they belong to, so co-op gets a row of bars each.
 #[derive(Component)]
-struct HealthUiValue;
+struct HealthUiValue {
+    player: usize,
+}
 
 #[derive(Component)]
 struct HealthUiBar;
@@ -43,24 +76,120 @@ struct LvlContainer;
 #[derive(Component)]
 struct LvlText;
 
-fn ui_setup(mut commands: Commands, assets: Res<AssetServer>) {
-    let ui_container = (
+#[derive(Component)]
+struct XpUiValue;
+
+#[derive(Component)]
+struct RunTimerText;
+
+#[derive(Component)]
+struct BossCountdownText;
+
+#[derive(Component)]
+struct KillCountText;
+
+// Weapon and passive slots for one player. Remembers what it was built from, so it's only
+// rebuilt when a weapon or passive is picked up.
+#[derive(Component)]
+struct SlotStrip {
+    player: usize,
+    weapons: Vec<(WeaponsEnum, u32, bool)>,
+    passives: Vec<StatUpgrade>,
+}
+
+// Shade over a weapon slot that shrinks as the weapon comes back off cooldown.
+#[derive(Component)]
+struct WeaponCooldownSweep {
+    player: usize,
+    slot: usize,
+}
+
+// Time and kills for the current run, shown at the top of the screen.
+#[derive(Resource, Default)]
+pub struct RunStats {
+    pub time: Stopwatch,
+    pub kills: u32,
+}
+
+#[derive(Component)]
+struct DashUiValue {
+    player: usize,
+}
+
+#[derive(Component)]
+struct UltimateUiValue {
+    player: usize,
+}
+
+const PLAYER_ROW_HEIGHT: f32 = 50.;
+const XP_BAR_HEIGHT: f32 = 14.;
+const SLOT_SIZE: f32 = 40.;
+// Two pixels of border around a 16px icon drawn at double size.
+const PASSIVE_SLOT_SIZE: f32 = 36.;
+
+// Outlined bar with a fill that grows as the ability comes off cooldown, and a label over the top.
+fn get_ability_bar(
+    left: f32,
+    bottom: f32,
+    label: String,
+    font: Handle<Font>,
+) -> ((NodeBundle, Name), NodeBundle, TextBundle) {
+    let bar = (
         NodeBundle {
             style: Style {
-                //XXX using Px here because UI isn't based on camera size, just window size
-                width: Val::Percent(100.),
-                height: Val::Percent(100.),
-                left: Val::Px(0.),
-                top: Val::Px(0.),
+                width: Val::Px(300.),
+                height: Val::Px(35.),
+                left: Val::Px(left),
+                bottom: Val::Px(bottom),
                 position_type: PositionType::Absolute,
+                border: UiRect::all(Val::Px(2.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
                 ..default()
             },
+            border_color: BORDER_COLOR.into(),
+            background_color: PURPLISH.into(),
             ..default()
         },
-        UiContainer,
-        Name::new("UI Container"),
+        Name::new(format!("{} Bar UI", label)),
+    );
+
+    let fill = NodeBundle {
+        style: Style {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            left: Val::Px(0.),
+            top: Val::Px(0.),
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        background_color: LIGHT_BLUE.into(),
+        ..default()
+    };
+
+    let text = TextBundle::from_section(
+        label,
+        TextStyle {
+            font,
+            font_size: 22.0,
+            color: DARK_PURPLE,
+        },
     );
 
+    (bar, fill, text)
+}
+
+// Health bar plus dash and ultimate bars for one player, stacked a row up per player.
+fn spawn_player_bars(
+    commands: &mut ChildBuilder,
+    index: usize,
+    name: &str,
+    controls: &PlayerControls,
+    ultimate_name: &str,
+    font: Handle<Font>,
+) {
+    let bottom = 40. + index as f32 * PLAYER_ROW_HEIGHT;
+
     // Health bar
     let parent_node = (
         NodeBundle {
@@ -70,7 +199,7 @@ fn ui_setup(mut commands: Commands, assets: Res<AssetServer>) {
                 height: Val::Px(35.),
                 left: Val::Px(40.),
                 right: Val::Auto,
-                bottom: Val::Px(40.),
+                bottom: Val::Px(bottom),
                 position_type: PositionType::Absolute,
                 border: UiRect::all(Val::Px(2.)),
                 ..default()
@@ -80,7 +209,7 @@ fn ui_setup(mut commands: Commands, assets: Res<AssetServer>) {
             ..default()
         },
         HealthUiBar,
-        Name::new("Health Bar UI"),
+        Name::new(format!("{} Health Bar UI", name)),
     );
 
     let health_node = (
@@ -93,15 +222,101 @@ fn ui_setup(mut commands: Commands, assets: Res<AssetServer>) {
             background_color: BORDER_COLOR.into(),
             ..default()
         },
-        HealthUiValue,
+        HealthUiValue { player: index },
         Name::new("Health Bar Filled UI"),
     );
 
-    let lvl_container = (
+    let (dash_bar, dash_fill, dash_text) = get_ability_bar(
+        360.,
+        bottom,
+        format!("Dash [{}]", controls.get_dash_label()),
+        font.clone(),
+    );
+    let (ultimate_bar, ultimate_fill, ultimate_text) = get_ability_bar(
+        680.,
+        bottom,
+        format!("{} [{}]", ultimate_name, controls.get_ultimate_label()),
+        font,
+    );
+
+    commands.spawn(parent_node).with_children(|commands| {
+        commands.spawn(health_node);
+    });
+
+    commands.spawn(dash_bar).with_children(|commands| {
+        commands.spawn((dash_fill, DashUiValue { player: index }));
+        commands.spawn(dash_text);
+    });
+
+    commands.spawn(ultimate_bar).with_children(|commands| {
+        commands.spawn((ultimate_fill, UltimateUiValue { player: index }));
+        commands.spawn(ultimate_text);
+    });
+}
+
+fn ui_setup(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    registry: Res<CharacterRegistry>,
+    selected_character: Res<SelectedCharacter>,
+) {
+    commands.insert_resource(RunStats::default());
+
+    let ui_container = (
         NodeBundle {
             style: Style {
+                //XXX using Px here because UI isn't based on camera size, just window size
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                left: Val::Px(0.),
+                top: Val::Px(0.),
                 position_type: PositionType::Absolute,
+                ..default()
+            },
+            ..default()
+        },
+        UiContainer,
+        Name::new("UI Container"),
+    );
+
+    // XP is shared, so there's one bar right across the top for everyone.
+    let xp_bar = (
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Px(XP_BAR_HEIGHT),
+                left: Val::Px(0.),
                 top: Val::Px(0.),
+                position_type: PositionType::Absolute,
+                border: UiRect::bottom(Val::Px(2.)),
+                ..default()
+            },
+            border_color: BORDER_COLOR.into(),
+            background_color: PURPLISH.into(),
+            ..default()
+        },
+        Name::new("XP Bar UI"),
+    );
+
+    let xp_fill = (
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(0.),
+                height: Val::Percent(100.),
+                ..default()
+            },
+            background_color: LIGHT_TEAL.into(),
+            ..default()
+        },
+        XpUiValue,
+        Name::new("XP Bar Filled UI"),
+    );
+
+    let lvl_container = (
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(XP_BAR_HEIGHT),
                 left: Val::Px(0.),
                 padding: UiRect {
                     top: Val::Px(10.),
@@ -123,70 +338,394 @@ fn ui_setup(mut commands: Commands, assets: Res<AssetServer>) {
 
     let font = assets.load("fonts/patua_one/patuaone.ttf");
 
-    let section = TextSection {
-        value: format!("Level: {}", 0.),
-        style: TextStyle {
-            font: font,
-            font_size: 38.0,
-            color: LIGHT_TEAL.into(),
-        },
+    let text_style = |font_size: f32| TextStyle {
+        font: font.clone(),
+        font_size,
+        color: LIGHT_TEAL,
     };
 
     let lvl = (
-        TextBundle {
-            text: Text {
-                sections: Vec::from([section]),
+        TextBundle::from_section("Level: 1", text_style(38.)),
+        Name::new("Level number"),
+        LvlText,
+    );
+
+    let timer_container = (
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                top: Val::Px(XP_BAR_HEIGHT + 10.),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
                 ..default()
             },
-            ..Default::default()
+            ..default()
         },
-        Name::new("Level number"),
-        LvlText,
+        Name::new("Run Timer Container"),
+    );
+
+    let run_timer = (
+        TextBundle::from_section(get_time_label(0.), text_style(38.)),
+        RunTimerText,
+        Name::new("Run timer"),
+    );
+
+    let boss_countdown = (
+        TextBundle::from_section("", text_style(22.)),
+        BossCountdownText,
+        Name::new("Boss countdown"),
+    );
+
+    let kill_count = (
+        TextBundle::from_section(get_kills_label(0), text_style(30.)).with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(XP_BAR_HEIGHT + 10.),
+            right: Val::Px(20.),
+            ..default()
+        }),
+        KillCountText,
+        Name::new("Kill count"),
     );
 
+    let characters = get_selected_characters(&registry, &selected_character);
+    let player_count = characters.len();
+
     commands.spawn(ui_container).with_children(|commands| {
-        commands.spawn(parent_node).with_children(|commands| {
-            commands.spawn(health_node);
+        for (index, character) in characters.into_iter().enumerate() {
+            spawn_player_bars(
+                commands,
+                index,
+                &character.name,
+                &PlayerControls::for_player(index, player_count),
+                &character.ultimate.name,
+                font.clone(),
+            );
+
+            commands.spawn(get_slot_strip(index));
+        }
+
+        commands.spawn(xp_bar).with_children(|commands| {
+            commands.spawn(xp_fill);
         });
 
         commands.spawn(lvl_container).with_children(|commands| {
             commands.spawn(lvl);
         });
+
+        commands.spawn(timer_container).with_children(|commands| {
+            commands.spawn(run_timer);
+            commands.spawn(boss_countdown);
+        });
+
+        commands.spawn(kill_count);
     });
 }
 
-fn ui_update(
-    // mut commands: Commands,
-    // mut game_state: ResMut<NextState<GameState>>,
-    player_query: Query<(&Health, &CanLevel), With<Player>>,
-    mut ui_health_query: Query<&mut Style, With<HealthUiValue>>,
+// Under the level box, a row per player.
+fn get_slot_strip(index: usize) -> (NodeBundle, SlotStrip, Name) {
+    (
+        NodeBundle {
+            style: Style {
+                left: Val::Px(20.),
+                top: Val::Px(XP_BAR_HEIGHT + 80. + index as f32 * (SLOT_SIZE + 10.)),
+                position_type: PositionType::Absolute,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(6.),
+                ..default()
+            },
+            ..default()
+        },
+        SlotStrip {
+            player: index,
+            weapons: vec![],
+            passives: vec![],
+        },
+        Name::new("Slot Strip UI"),
+    )
+}
+
+fn update_run_stats(
+    mut run_stats: ResMut<RunStats>,
+    mut enemy_killed_events: EventReader<EnemyKilledEvent>,
+    time: Res<Time>,
+) {
+    // The clock ticks every frame, but the HUD only needs to hear about it once a second.
+    let seconds = run_stats.time.elapsed().as_secs();
+    run_stats.bypass_change_detection().time.tick(time.delta());
+
+    if run_stats.time.elapsed().as_secs() != seconds {
+        run_stats.set_changed();
+    }
+
+    let kills = enemy_killed_events.read().count() as u32;
+
+    if kills > 0 {
+        run_stats.kills += kills;
+    }
+}
+
+fn health_ui_update(
+    player_query: Query<(&Health, &Player), Changed<Health>>,
+    mut ui_health_query: Query<(&mut Style, &HealthUiValue)>,
+) {
+    for (health, player) in player_query.iter() {
+        for (mut health_block_style, ui) in ui_health_query.iter_mut() {
+            if ui.player == player.index {
+                health_block_style.width = Val::Percent(health.total.max(0.) / health.max * 100.);
+            }
+        }
+    }
+}
+
+// XP is shared, so every player is on the same level.
+fn level_ui_update(
+    player_query: Query<&CanLevel, Changed<CanLevel>>,
     mut ui_lvl_query: Query<&mut Text, With<LvlText>>,
+    mut ui_xp_query: Query<&mut Style, With<XpUiValue>>,
+) {
+    let Some(lvl) = player_query.iter().next() else {
+        return;
+    };
+
+    for mut text in ui_lvl_query.iter_mut() {
+        text.sections[0].value = format!("Level: {}", lvl.level);
+    }
+
+    for mut style in ui_xp_query.iter_mut() {
+        style.width =
+            Val::Percent((lvl.experience as f32 / lvl.level_step as f32 * 100.).min(100.));
+    }
+}
+
+fn run_stats_ui_update(
+    run_stats: Res<RunStats>,
+    level_spawns: Res<LevelSpawns>,
+    mut timer_query: Query<&mut Text, With<RunTimerText>>,
+    mut countdown_query: Query<&mut Text, (With<BossCountdownText>, Without<RunTimerText>)>,
+    mut kills_query: Query<
+        &mut Text,
+        (
+            With<KillCountText>,
+            Without<RunTimerText>,
+            Without<BossCountdownText>,
+        ),
+    >,
+) {
+    for mut text in timer_query.iter_mut() {
+        text.sections[0].value = get_time_label(run_stats.time.elapsed_secs());
+    }
+
+    for mut text in countdown_query.iter_mut() {
+        text.sections[0].value = match level_spawns.get_seconds_to_boss() {
+            Some(seconds) => format!("Boss in {}", get_time_label(seconds)),
+            None => "Boss!".into(),
+        };
+    }
+
+    for mut text in kills_query.iter_mut() {
+        text.sections[0].value = get_kills_label(run_stats.kills);
+    }
+}
+
+// Rebuilt only when the player's weapons or passives are different from what it shows.
+fn slot_strip_ui_update(
+    mut commands: Commands,
     assets: Res<AssetServer>,
+    mut sheets: SpriteSheets,
+    player_query: Query<&Player>,
+    mut strip_query: Query<(Entity, &mut SlotStrip)>,
 ) {
-    let (health, lvl) = player_query.single();
+    for player in player_query.iter() {
+        let weapons: Vec<(WeaponsEnum, u32, bool)> = player
+            .weapons
+            .iter()
+            .map(|weapon| (weapon.variant, weapon.level, weapon.is_evolved))
+            .collect();
 
-    // Health
-    let mut health_block_style = ui_health_query.single_mut();
+        for (entity, mut strip) in strip_query.iter_mut() {
+            if strip.player != player.index
+                || (strip.weapons == weapons && strip.passives == player.passives)
+            {
+                continue;
+            }
 
-    health_block_style.width = Val::Percent(health.total);
+            strip.weapons = weapons.clone();
+            strip.passives = player.passives.clone();
 
-    // println!("Health: {}", health.0);
+            let font = assets.load("fonts/patua_one/patuaone.ttf");
 
-    // Level
-    let mut text = ui_lvl_query.single_mut();
+            commands.entity(entity).despawn_descendants();
+            commands.entity(entity).with_children(|commands| {
+                for (slot, weapon) in player.weapons.iter().enumerate() {
+                    let (texture_atlas, animatable) = get_weapon_sprite(&mut sheets, weapon);
 
-    let font = assets.load("fonts/patua_one/patuaone.ttf");
+                    commands.spawn(get_slot(SLOT_SIZE)).with_children(|commands| {
+                        commands.spawn(AtlasImageBundle {
+                            style: Style {
+                                width: Val::Percent(100.),
+                                height: Val::Percent(100.),
+                                ..default()
+                            },
+                            texture_atlas,
+                            texture_atlas_image: UiTextureAtlasImage {
+                                index: animatable.moving_anim_indices.first,
+                                ..default()
+                            },
+                            ..default()
+                        });
+
+                        commands.spawn((
+                            NodeBundle {
+                                style: Style {
+                                    width: Val::Percent(100.),
+                                    height: Val::Percent(0.),
+                                    left: Val::Px(0.),
+                                    top: Val::Px(0.),
+                                    position_type: PositionType::Absolute,
+                                    ..default()
+                                },
+                                background_color: Color::rgba(0., 0., 0., 0.6).into(),
+                                ..default()
+                            },
+                            WeaponCooldownSweep {
+                                player: player.index,
+                                slot,
+                            },
+                        ));
+                    });
+                }
+
+                // Passives picked more than once get one slot with a count.
+                let mut passives: Vec<(StatUpgrade, usize)> = Vec::new();
+
+                for passive in player.passives.iter() {
+                    match passives.iter_mut().find(|(owned, _)| owned == passive) {
+                        Some((_, count)) => *count += 1,
+                        None => passives.push((*passive, 1)),
+                    }
+                }
 
-    let section = TextSection {
-        value: format!("Level: {}", lvl.level),
-        style: TextStyle {
-            font: font,
-            font_size: 38.0,
-            color: LIGHT_TEAL.into(),
+                for (passive, count) in passives {
+                    let (texture_atlas, index) = get_passive_icon(&mut sheets, passive);
+
+                    commands
+                        .spawn(get_slot(PASSIVE_SLOT_SIZE))
+                        .with_children(|commands| {
+                            commands.spawn(AtlasImageBundle {
+                                style: Style {
+                                    width: Val::Percent(100.),
+                                    height: Val::Percent(100.),
+                                    ..default()
+                                },
+                                texture_atlas,
+                                texture_atlas_image: UiTextureAtlasImage { index, ..default() },
+                                ..default()
+                            });
+
+                            if count > 1 {
+                                commands.spawn(
+                                    TextBundle::from_section(
+                                        count.to_string(),
+                                        TextStyle {
+                                            font: font.clone(),
+                                            font_size: 14.,
+                                            color: WHITE,
+                                        },
+                                    )
+                                    .with_style(Style {
+                                        position_type: PositionType::Absolute,
+                                        right: Val::Px(1.),
+                                        bottom: Val::Px(-2.),
+                                        ..default()
+                                    }),
+                                );
+                            }
+                        });
+                }
+            });
+        }
+    }
+}
+
+fn get_slot(size: f32) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            width: Val::Px(size),
+            height: Val::Px(size),
+            border: UiRect::all(Val::Px(2.)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
         },
-    };
+        border_color: BORDER_COLOR.into(),
+        background_color: PURPLE.into(),
+        ..default()
+    }
+}
+
+// The weapon timers tick every frame, so the sweeps do too.
+fn weapon_cooldown_ui_update(
+    player_query: Query<&Player>,
+    mut sweep_query: Query<(&mut Style, &WeaponCooldownSweep)>,
+) {
+    for player in player_query.iter() {
+        for (mut style, sweep) in sweep_query.iter_mut() {
+            if sweep.player != player.index {
+                continue;
+            }
 
-    text.sections = Vec::from([section]);
+            if let Some(weapon) = player.weapons.get(sweep.slot) {
+                style.height = Val::Percent((1. - weapon.tick_timer.percent()) * 100.);
+            }
+        }
+    }
+}
+
+fn get_time_label(seconds: f32) -> String {
+    let seconds = seconds.ceil() as u32;
+
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn get_kills_label(kills: u32) -> String {
+    format!("Kills: {}", kills)
+}
+
+fn ability_ui_update(
+    player_query: Query<(&Dash, &Ultimate, &Player)>,
+    mut dash_ui_query: Query<
+        (&mut Style, &mut BackgroundColor, &DashUiValue),
+        Without<UltimateUiValue>,
+    >,
+    mut ultimate_ui_query: Query<
+        (&mut Style, &mut BackgroundColor, &UltimateUiValue),
+        Without<DashUiValue>,
+    >,
+) {
+    for (dash, ultimate, player) in player_query.iter() {
+        for (mut style, mut color, ui) in dash_ui_query.iter_mut() {
+            if ui.player == player.index {
+                style.width = Val::Percent(dash.cooldown_timer.percent() * 100.);
+                *color = get_ability_color(dash.cooldown_timer.finished()).into();
+            }
+        }
+
+        for (mut style, mut color, ui) in ultimate_ui_query.iter_mut() {
+            if ui.player == player.index {
+                style.width = Val::Percent(ultimate.percent() * 100.);
+                *color = get_ability_color(ultimate.is_ready()).into();
+            }
+        }
+    }
+}
+
+fn get_ability_color(is_ready: bool) -> Color {
+    if is_ready {
+        BORDER_COLOR
+    } else {
+        LIGHT_BLUE
+    }
 }
 
 fn unload(mut ui_query: Query<Entity, With<UiContainer>>, mut commands: Commands) {
@@ -1,10 +1,14 @@
-use crate::sprite::{AnimationIndices, ProjectileSpriteSheetAnimatable};
+use crate::character_select::CharacterStats;
+use crate::sprite::{AnimationIndices, Health, Movable, ProjectileSpriteSheetAnimatable};
+use crate::sprite_sheets::{SheetGrid, SpriteSheets};
 use bevy::prelude::*;
 use rand::{rngs::ThreadRng, Rng};
+use serde::Deserialize;
 
 use super::projectile_spawner::{DamageType};
+use super::stats::{PlayerStats, Stat, StatModifier};
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
 pub enum WeaponsEnum {
     #[default]
     Guitar,
@@ -33,6 +37,12 @@ pub enum ProjectileState {
 #[derive(Component)]
 pub struct Projectile {
     pub props: ProjectileProps,
+    // Released when this runs out, if it hasn't hit anything by then.
+    pub lifetime: Timer,
+    // Enemies this bolt's chain has already struck, so it never jumps back to one.
+    pub chain_hits: Vec<Entity>,
+    // Enemies this projectile has already shoved, see update_projectile_collisions.
+    pub knocked_back: Vec<Entity>,
 }
 
 #[derive(States, PartialEq, Eq, Default, Debug, Clone, Hash)]
@@ -88,7 +98,20 @@ pub struct ProjectileProps {
     pub projectile_aoe_damage_scale: f32,
     pub projectile_rotation_offset: f32,
     pub projectile_sprite_anchor: bevy::sprite::Anchor,
-    
+    // Fired together each tick, fanned out by the spread angle (radians) when aimed.
+    pub projectile_count: usize,
+    pub projectile_spread: f32,
+    // Base values the player's stats build on, see PlayerStats.
+    pub projectile_speed: f32,
+    pub projectile_duration: f32,
+    pub projectile_crit_chance: f32,
+    pub projectile_crit_multiplier: f32,
+    // How hard a hit shoves an enemy back, before its mass is taken into account.
+    pub projectile_knockback: f32,
+    // Instant hits jump on to the nearest enemy within chain range that this bolt's chain hasn't
+    // struck yet, up to this many more times.
+    pub projectile_chain_count: usize,
+    pub projectile_chain_range: f32,
 }
 
 #[derive(Debug, Clone)]
@@ -96,38 +119,228 @@ pub struct Weapon {
     pub name: String,
     pub desc: String,
     pub variant: WeaponsEnum,
+    pub level: u32,
+    pub is_evolved: bool,
     pub tick_timer: Timer,
     pub projectile_props: ProjectileProps,
 }
 
-pub fn get_available_weapons(player_weapons: &Vec<Weapon>, num_weapons: usize) -> Vec<Weapon> {
+impl Weapon {
+    // Name plus level, for the level up and chest screens.
+    pub fn get_title(&self) -> String {
+        if self.is_evolved {
+            format!("{} (Evolved)", self.name)
+        } else if self.level > 1 {
+            format!("{} (Lv {})", self.name, self.level)
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+pub const WEAPON_MAX_LEVEL: u32 = 5;
+
+// Bought with chests as well as weapons. Each one a player owns is kept on their Player, which is
+// what evolution recipes check for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatUpgrade {
+    Might,
+    Speed,
+    Cooldown,
+    Area,
+    MaxHealth,
+    Amount,
+    CritChance,
+    Armor,
+    Regen,
+}
+
+impl StatUpgrade {
+    pub const ALL: [StatUpgrade; 9] = [
+        StatUpgrade::Might,
+        StatUpgrade::Speed,
+        StatUpgrade::Cooldown,
+        StatUpgrade::Area,
+        StatUpgrade::MaxHealth,
+        StatUpgrade::Amount,
+        StatUpgrade::CritChance,
+        StatUpgrade::Armor,
+        StatUpgrade::Regen,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            StatUpgrade::Might => "Might",
+            StatUpgrade::Speed => "Swiftness",
+            StatUpgrade::Cooldown => "Haste",
+            StatUpgrade::Area => "Reach",
+            StatUpgrade::MaxHealth => "Vigour",
+            StatUpgrade::Amount => "Duplicator",
+            StatUpgrade::CritChance => "Precision",
+            StatUpgrade::Armor => "Bulwark",
+            StatUpgrade::Regen => "Lifeblood",
+        }
+    }
+
+    pub fn get_desc(&self) -> &'static str {
+        match self {
+            StatUpgrade::Might => "Weapons deal 10% more damage.",
+            StatUpgrade::Speed => "Move 10% faster.",
+            StatUpgrade::Cooldown => "Weapons fire 8% more often.",
+            StatUpgrade::Area => "Weapon areas are 10% bigger.",
+            StatUpgrade::MaxHealth => "20 more max health, and heals as much.",
+            StatUpgrade::Amount => "Weapons fire one more projectile.",
+            StatUpgrade::CritChance => "5% more chance of a critical hit.",
+            StatUpgrade::Armor => "Take 1 less damage from every hit.",
+            StatUpgrade::Regen => "Regenerate 0.5 more health a second.",
+        }
+    }
+
+    // Cell in the passive icon sheet, see get_passive_icon.
+    fn get_icon_index(&self) -> usize {
+        match self {
+            StatUpgrade::Might => 0,
+            StatUpgrade::Speed => 1,
+            StatUpgrade::Cooldown => 2,
+            StatUpgrade::Area => 3,
+            StatUpgrade::MaxHealth => 4,
+            StatUpgrade::Amount => 5,
+            StatUpgrade::CritChance => 6,
+            StatUpgrade::Armor => 7,
+            StatUpgrade::Regen => 8,
+        }
+    }
+
+    pub fn apply(
+        &self,
+        player_stats: &mut PlayerStats,
+        stats: &mut CharacterStats,
+        health: &mut Health,
+        movable: &mut Movable,
+    ) {
+        match self {
+            StatUpgrade::Might => {
+                player_stats.add_modifier(StatModifier::multiply(Stat::Damage, 1.1))
+            }
+            StatUpgrade::Speed => {
+                stats.speed *= 1.1;
+                movable.speed = stats.speed;
+            }
+            StatUpgrade::Cooldown => {
+                player_stats.add_modifier(StatModifier::multiply(Stat::Cooldown, 0.92))
+            }
+            StatUpgrade::Area => player_stats.add_modifier(StatModifier::multiply(Stat::Area, 1.1)),
+            StatUpgrade::MaxHealth => {
+                health.max += 20.;
+                health.heal(20.);
+            }
+            StatUpgrade::Amount => player_stats.add_modifier(StatModifier::add(Stat::Amount, 1.)),
+            StatUpgrade::CritChance => {
+                player_stats.add_modifier(StatModifier::add(Stat::CritChance, 0.05))
+            }
+            StatUpgrade::Armor => player_stats.add_modifier(StatModifier::add(Stat::Armor, 1.)),
+            StatUpgrade::Regen => player_stats.add_modifier(StatModifier::add(Stat::Regen, 0.5)),
+        }
+    }
+}
+
+// A max level weapon evolves in the next chest its player opens while owning the passive.
+pub struct EvolutionRecipe {
+    pub weapon: WeaponsEnum,
+    pub passive: StatUpgrade,
+    pub evolved: fn() -> Weapon,
+}
+
+pub const EVOLUTION_RECIPES: [EvolutionRecipe; 2] = [
+    EvolutionRecipe {
+        weapon: WeaponsEnum::Guitar,
+        passive: StatUpgrade::Cooldown,
+        evolved: get_guitar_barrage_weapon,
+    },
+    EvolutionRecipe {
+        weapon: WeaponsEnum::Lightning,
+        passive: StatUpgrade::Area,
+        evolved: get_spaghetti_storm_weapon,
+    },
+];
+
+// First weapon ready to evolve with the passives the player has, if any.
+pub fn get_weapon_evolution(
+    player_weapons: &[Weapon],
+    passives: &[StatUpgrade],
+) -> Option<Weapon> {
+    EVOLUTION_RECIPES.iter().find_map(|recipe| {
+        let is_ready = player_weapons.iter().any(|weapon| {
+            weapon.variant == recipe.weapon
+                && weapon.level >= WEAPON_MAX_LEVEL
+                && !weapon.is_evolved
+        });
+
+        (is_ready && passives.contains(&recipe.passive)).then(|| (recipe.evolved)())
+    })
+}
+
+// Offers for a level up or chest: weapons the player doesn't have yet, and the next level of the
+// ones they do. Evolved weapons are done levelling.
+pub fn get_available_weapons(player_weapons: &[Weapon], num_weapons: usize) -> Vec<Weapon> {
     let mut new_weapons: Vec<Weapon> = vec![];
     let mut rng = rand::thread_rng();
 
     let player_weapon_types = get_collated_weapons_for_player(&player_weapons);
-    let mut available_weapon_types = get_filtered_weapons(&player_weapon_types);
+    let mut available_weapons: Vec<Weapon> =
+        get_vector_with_weapons_for_types(get_filtered_weapons(&player_weapon_types));
 
-    if player_weapon_types.len() >= WeaponsEnum::VALUES.len() || available_weapon_types.len() == 0 {
-        return new_weapons;
+    for weapon in player_weapons {
+        if weapon.level < WEAPON_MAX_LEVEL && !weapon.is_evolved {
+            available_weapons.push(get_weapon_level_up(weapon));
+        }
     }
 
     // Get random weapon and add to array
-    for i in 0..num_weapons {
-        if available_weapon_types.len() == 0 {
+    for _ in 0..num_weapons {
+        if available_weapons.is_empty() {
             break;
         }
 
-        let random_index = rng.gen_range(0..available_weapon_types.len());
-        let selected = available_weapon_types.get(random_index).expect("Can't get weapon type at index for some reason - possibly due to available_weapon_types mutation issue.");
-        new_weapons.push(get_weapon_for_type(selected));
-
-        available_weapon_types.remove(random_index);
+        let random_index = rng.gen_range(0..available_weapons.len());
+        new_weapons.push(available_weapons.remove(random_index));
     }
 
     new_weapons
 }
 
-fn get_collated_weapons_for_player(weapons: &Vec<Weapon>) -> Vec<WeaponsEnum> {
+pub fn has_available_weapons(player_weapons: &[Weapon]) -> bool {
+    !get_available_weapons(player_weapons, 1).is_empty()
+}
+
+// Swaps in the new level of a weapon the player already has, or adds it if it's new.
+pub fn add_player_weapon(player_weapons: &mut Vec<Weapon>, weapon: Weapon) {
+    match player_weapons.iter_mut().find(|owned| owned.variant == weapon.variant) {
+        Some(owned) => *owned = weapon,
+        None => player_weapons.push(weapon),
+    }
+}
+
+// Each level hits a quarter harder and fires a little more often.
+pub fn get_weapon_level_up(weapon: &Weapon) -> Weapon {
+    let mut weapon = weapon.clone();
+
+    weapon.level += 1;
+    weapon.projectile_props.projectile_base_damage *= 1.25;
+    weapon.tick_timer = Timer::from_seconds(
+        weapon.tick_timer.duration().as_secs_f32() * 0.9,
+        TimerMode::Repeating,
+    );
+
+    weapon
+}
+
+// Levelled all the way up, which is where evolutions start from.
+fn get_max_level_weapon(weapon: Weapon) -> Weapon {
+    (weapon.level..WEAPON_MAX_LEVEL).fold(weapon, |weapon, _| get_weapon_level_up(&weapon))
+}
+
+fn get_collated_weapons_for_player(weapons: &[Weapon]) -> Vec<WeaponsEnum> {
     let mut collated_weapons: Vec<WeaponsEnum> = Vec::new();
 
     for weapon in weapons {
@@ -137,6 +350,16 @@ fn get_collated_weapons_for_player(weapons: &Vec<Weapon>) -> Vec<WeaponsEnum> {
     collated_weapons
 }
 
+// Every projectile sheet a run can need, evolutions included.
+pub fn get_weapon_sprite_paths() -> Vec<&'static str> {
+    WeaponsEnum::VALUES
+        .iter()
+        .map(get_weapon_for_type)
+        .chain(EVOLUTION_RECIPES.iter().map(|recipe| (recipe.evolved)()))
+        .map(|weapon| weapon.projectile_props.projectile_sprite)
+        .collect()
+}
+
 fn get_filtered_weapons(weapons: &Vec<WeaponsEnum>) -> Vec<WeaponsEnum> {
     let mut filtered_weapons: Vec<WeaponsEnum> = Vec::new();
 
@@ -183,7 +406,7 @@ fn get_random_weapon(rng: &mut ThreadRng) -> WeaponsEnum {
     WeaponsEnum::VALUES[random_index]
 }
 
-fn get_weapon_for_type(weapon_type: &WeaponsEnum) -> Weapon {
+pub fn get_weapon_for_type(weapon_type: &WeaponsEnum) -> Weapon {
     match weapon_type {
         WeaponsEnum::Guitar => get_guitar_weapon(),
         WeaponsEnum::Horse => get_horse_weapon(),
@@ -214,6 +437,8 @@ pub fn get_guitar_weapon() -> Weapon {
         desc: "It hits hard. But throwing guitars takes a while...".into(),
         tick_timer: Timer::from_seconds(1.5, TimerMode::Repeating),
         variant: WeaponsEnum::Guitar,
+        level: 1,
+        is_evolved: false,
         projectile_props: ProjectileProps {
             // Pass to projectile for duration of lifetime
             projectile_sprite: "sprites/weapons/guitar_pixelated_small.png",
@@ -231,7 +456,16 @@ pub fn get_guitar_weapon() -> Weapon {
             projectile_rotation_offset: 0.,
             projectile_aoe_damage_scale: 0.5,
             projectile_aim_range: 150.,
-            projectile_sprite_anchor: bevy::sprite::Anchor::Center
+            projectile_sprite_anchor: bevy::sprite::Anchor::Center,
+            projectile_count: 1,
+            projectile_spread: 0.,
+            projectile_speed: 100.,
+            projectile_duration: 20.,
+            projectile_crit_chance: 0.05,
+            projectile_crit_multiplier: 1.5,
+            projectile_knockback: 320.,
+            projectile_chain_count: 0,
+            projectile_chain_range: 0.,
         },
     }
 }
@@ -242,6 +476,8 @@ pub fn get_horse_weapon() -> Weapon {
         desc: "Devastating area of attack ability due to, you guessed it, flatulence. \n\n\"Never underestimate horses.\" - Lisa".into(),
         tick_timer: Timer::from_seconds(2.5, TimerMode::Repeating),
         variant: WeaponsEnum::Horse,
+        level: 1,
+        is_evolved: false,
         projectile_props: ProjectileProps {
             projectile_sprite: "sprites/weapons/horse.png",
             projectile_sprite_scale: 0.4,
@@ -258,7 +494,16 @@ pub fn get_horse_weapon() -> Weapon {
             projectile_rotation_offset: std::f32::consts::FRAC_PI_2,
             projectile_aoe_damage_scale: 0.8,
             projectile_aim_range: 150.,
-            projectile_sprite_anchor: bevy::sprite::Anchor::Center
+            projectile_sprite_anchor: bevy::sprite::Anchor::Center,
+            projectile_count: 1,
+            projectile_spread: 0.,
+            projectile_speed: 100.,
+            projectile_duration: 20.,
+            projectile_crit_chance: 0.05,
+            projectile_crit_multiplier: 1.5,
+            projectile_knockback: 160.,
+            projectile_chain_count: 0,
+            projectile_chain_range: 0.,
         },
     }
 }
@@ -269,6 +514,8 @@ pub fn get_energy_weapon() -> Weapon {
         desc: "By merely frowning, your most taxing anxieties are instantly transferred to the nearest enemy, dealing psychological damage. It's also great for toning the face :)".into(),
         tick_timer: Timer::from_seconds(1.5, TimerMode::Repeating),
         variant: WeaponsEnum::NegativVibes,
+        level: 1,
+        is_evolved: false,
         projectile_props: ProjectileProps {
             projectile_sprite: "sprites/weapons/energy.png",
             projectile_sprite_scale: 0.4,
@@ -285,7 +532,16 @@ pub fn get_energy_weapon() -> Weapon {
             projectile_rotation_offset: 0.,
             projectile_aoe_damage_scale: 0.5,
             projectile_aim_range: 150.,
-            projectile_sprite_anchor: bevy::sprite::Anchor::Center
+            projectile_sprite_anchor: bevy::sprite::Anchor::Center,
+            projectile_count: 1,
+            projectile_spread: 0.,
+            projectile_speed: 100.,
+            projectile_duration: 20.,
+            projectile_crit_chance: 0.05,
+            projectile_crit_multiplier: 1.5,
+            projectile_knockback: 60.,
+            projectile_chain_count: 0,
+            projectile_chain_range: 0.,
         },
     }
 }
@@ -296,6 +552,8 @@ pub fn get_lightning_weapon() -> Weapon {
         desc: "The Spaghetti Monster has this strange tendency to get involved at the wrong times. And, incidentally, a strange fascination with Zeus. This is the result.".into(),
         tick_timer: Timer::from_seconds(3., TimerMode::Repeating),
         variant: WeaponsEnum::Lightning,
+        level: 1,
+        is_evolved: false,
         projectile_props: ProjectileProps {
             projectile_sprite: "sprites/weapons/thunder.png",
             projectile_sprite_scale: 0.8,
@@ -313,6 +571,15 @@ pub fn get_lightning_weapon() -> Weapon {
             projectile_aoe_damage_scale: 0.,
             projectile_aim_range: 150.,
             projectile_sprite_anchor: bevy::sprite::Anchor::BottomCenter,
+            projectile_count: 1,
+            projectile_spread: 0.,
+            projectile_speed: 100.,
+            projectile_duration: 20.,
+            projectile_crit_chance: 0.05,
+            projectile_crit_multiplier: 1.5,
+            projectile_knockback: 20.,
+            projectile_chain_count: 0,
+            projectile_chain_range: 0.,
         },
     }
 }
@@ -323,6 +590,8 @@ pub fn get_splash_weapon() -> Weapon {
         desc: "Before the fight started, you snuck onto the lawn and overclocked the water sprinklers. This skill lets you take advantage of your preparedness.".into(),
         tick_timer: Timer::from_seconds(4., TimerMode::Repeating),
         variant: WeaponsEnum::Splash,
+        level: 1,
+        is_evolved: false,
         projectile_props: ProjectileProps {
             projectile_sprite: "sprites/weapons/splash.png",
             projectile_sprite_scale: 0.6,
@@ -340,34 +609,91 @@ pub fn get_splash_weapon() -> Weapon {
             projectile_aoe_damage_scale: 0.8,
             projectile_aim_range: 150.,
             projectile_sprite_anchor: bevy::sprite::Anchor::BottomCenter,
+            projectile_count: 1,
+            projectile_spread: 0.,
+            projectile_speed: 100.,
+            projectile_duration: 20.,
+            projectile_crit_chance: 0.05,
+            projectile_crit_multiplier: 1.5,
+            projectile_knockback: 120.,
+            projectile_chain_count: 0,
+            projectile_chain_range: 0.,
         },
     }
 }
 
+// Evolved Guitar of death, with Haste. Five maxed out guitars per throw.
+pub fn get_guitar_barrage_weapon() -> Weapon {
+    let mut weapon = get_max_level_weapon(get_guitar_weapon());
+
+    weapon.name = "Guitar Barrage".into();
+    weapon.desc =
+        "Why throw one guitar when you can throw the whole band's? Five at once, in a fan.".into();
+    weapon.is_evolved = true;
+    weapon.projectile_props.projectile_count = 5;
+    weapon.projectile_props.projectile_spread = 0.25;
+
+    weapon
+}
+
+// Evolved Spaghetti Lightning, with Reach. Maxed out bolts that chain from enemy to enemy.
+pub fn get_spaghetti_storm_weapon() -> Weapon {
+    let mut weapon = get_max_level_weapon(get_lightning_weapon());
+
+    weapon.name = "Spaghetti Storm".into();
+    weapon.desc =
+        "Zeus has given up and handed over the keys. Bolts fork from enemy to enemy.".into();
+    weapon.is_evolved = true;
+    weapon.projectile_props.projectile_aim_range = 300.;
+    weapon.projectile_props.projectile_count = 2;
+    weapon.projectile_props.projectile_chain_count = 6;
+    weapon.projectile_props.projectile_chain_range = 150.;
+
+    weapon
+}
+
 pub fn get_weapon_sprite(
-    assets: &Res<AssetServer>,
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
+    sheets: &mut SpriteSheets,
     weapon: &Weapon,
+) -> (Handle<TextureAtlas>, ProjectileSpriteSheetAnimatable) {
+    get_projectile_sprite(sheets, &weapon.projectile_props)
+}
+
+// One 16x16 icon per passive, in a single row.
+pub const PASSIVE_ICONS_PATH: &str = "sprites/ui/passive_icons.png";
+const PASSIVE_ICON_SIZE: f32 = 16.;
+
+pub fn get_passive_icon(
+    sheets: &mut SpriteSheets,
+    passive: StatUpgrade,
+) -> (Handle<TextureAtlas>, usize) {
+    let texture_atlas_handle = sheets.get_grid(
+        PASSIVE_ICONS_PATH,
+        SheetGrid::new(Vec2::splat(PASSIVE_ICON_SIZE), StatUpgrade::ALL.len(), 1),
+    );
+
+    (texture_atlas_handle, passive.get_icon_index())
+}
+
+pub fn get_projectile_sprite(
+    sheets: &mut SpriteSheets,
+    props: &ProjectileProps,
 ) -> (Handle<TextureAtlas>, ProjectileSpriteSheetAnimatable) {
     let animatable: ProjectileSpriteSheetAnimatable = ProjectileSpriteSheetAnimatable {
-        moving_anim_indices: weapon.projectile_props.projectile_sprite_indices.clone(),
+        moving_anim_indices: props.projectile_sprite_indices,
     };
 
-    let texture_handle = assets.load(weapon.projectile_props.projectile_sprite);
-
-    let texture_atlas = TextureAtlas::from_grid(
-        texture_handle,
-        Vec2::new(
-            weapon.projectile_props.projectile_sprite_width,
-            weapon.projectile_props.projectile_sprite_height,
+    let texture_atlas_handle = sheets.get_grid(
+        props.projectile_sprite,
+        SheetGrid::new(
+            Vec2::new(
+                props.projectile_sprite_width,
+                props.projectile_sprite_height,
+            ),
+            props.projectile_sprite_cols,
+            props.projectile_sprite_rows,
         ),
-        weapon.projectile_props.projectile_sprite_cols,
-        weapon.projectile_props.projectile_sprite_rows,
-        None,
-        None,
     );
 
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
     (texture_atlas_handle, animatable)
 }
@@ -2,8 +2,11 @@ use bevy::app::Plugin;
 
 use bevy::prelude::*;
 
+use crate::loading::LoadingScene;
 use crate::main_menu::{BLACK, BORDER_COLOR, DARK_PURPLE, LIGHT_TEAL};
 
+use crate::GameState;
+
 use super::GameWonState;
 
 pub struct CongratsPlugin;
@@ -13,10 +16,17 @@ impl Plugin for CongratsPlugin {
         app.add_systems(
             OnEnter(GameWonState::Congrats),
             (reset_camera, spawn_message),
-        );
+        )
+        .add_systems(
+            Update,
+            return_to_corridor.run_if(in_state(GameWonState::Congrats)),
+        )
+        .add_systems(OnExit(GameState::GameWon), despawn_congrats_ui);
     }
 }
 
+const RETURN_KEY: KeyCode = KeyCode::Return;
+
 #[derive(Component)]
 pub struct CongratsUi;
 
@@ -166,6 +176,15 @@ All the best, Ross.";
         ..Default::default()
     };
 
+    let return_hint = TextBundle::from_section(
+        "Press Enter to pick another stage",
+        TextStyle {
+            font: assets.load("fonts/spectral/spectral_medium.ttf"),
+            font_size: 28.0,
+            color: LIGHT_TEAL,
+        },
+    );
+
     commands.spawn(menu_parent).with_children(|commands| {
         commands
             .spawn(image_top_container)
@@ -174,12 +193,33 @@ All the best, Ross.";
                 commands.spawn(panel).with_children(|commands| {
                     commands.spawn(panel_inner).with_children(|commands| {
                         commands.spawn(text_msg);
+                        commands.spawn(return_hint);
                     });
                 });
             });
     });
 }
 
+// Back to the corridor doors, where beating this stage's boss has opened the next one.
+fn return_to_corridor(
+    keys: Res<Input<KeyCode>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut loading_scene: ResMut<LoadingScene>,
+    mut next_won_state: ResMut<NextState<GameWonState>>,
+) {
+    if keys.just_pressed(RETURN_KEY) {
+        next_won_state.set(GameWonState::Unloaded);
+        loading_scene.0 = GameState::Corridor;
+        next_game_state.set(GameState::Loading);
+    }
+}
+
+fn despawn_congrats_ui(mut commands: Commands, ui_query: Query<Entity, With<CongratsUi>>) {
+    for ui in &ui_query {
+        commands.entity(ui).despawn_recursive();
+    }
+}
+
 pub fn reset_camera(
     // query: Query<(&Transform) /*(With<Player>)*/>,
     mut camera_query: Query<&mut Transform, With<Camera> /*, Without<Player>*/>,
@@ -2,7 +2,6 @@ use bevy::app::Plugin;
 use bevy::audio::{PlaybackMode, Volume, VolumeLevel};
 use bevy::prelude::*;
 
-use crate::character_select::SelectedCharacterState;
 use crate::game_won::level::GameWonLevelState;
 use crate::game_won::player::GameWonPlayerState;
 use crate::main_menu::MyMusic;
@@ -37,6 +36,7 @@ impl Plugin for GameWonPlugin {
     fn build(&self, app: &mut App) {
         app.add_state::<GameWonState>()
             .add_systems(OnEnter(GameState::GameWon), (reset_camera, spawn_game_won))
+            .add_systems(OnExit(GameState::GameWon), despawn_game_won)
             .add_plugins(LevelPlugin)
             .add_plugins(NpcPlugin)
             .add_plugins(PlayerPlugin)
@@ -59,14 +59,13 @@ pub fn reset_camera(
 fn spawn_game_won(
     mut commands: Commands,
     assets: Res<AssetServer>,
-    mut next_character_state: ResMut<NextState<SelectedCharacterState>>,
     mut next_player_state: ResMut<NextState<GameWonPlayerState>>,
     mut next_level_state: ResMut<NextState<GameWonLevelState>>,
     mut next_npc_state: ResMut<NextState<GameWonNpcState>>,
 ) {
     println!("spawn game won");
     // TESTING PURPOSES - TODO: REMOVE
-    // next_character_state.set(SelectedCharacterState::Ailsa);
+    // selected_character.id = "ailsa".into();
 
     commands.spawn((
         AudioBundle {
@@ -87,8 +86,19 @@ fn spawn_game_won(
 }
 
 // fn despawn_corridor(mut commands: Commands, asset_server: Res<AssetServer>) {}
-fn despawn_game_won(mut commands: Commands, entities: Query<Entity>) {
-    // for entity in &entities {
-    //     commands.entity(entity).despawn_recursive();
-    // }
+// Scene entities unload in their own plugins, this just resets the states for the next win.
+fn despawn_game_won(
+    mut commands: Commands,
+    music_query: Query<Entity, With<MyMusic>>,
+    mut next_player_state: ResMut<NextState<GameWonPlayerState>>,
+    mut next_level_state: ResMut<NextState<GameWonLevelState>>,
+    mut next_npc_state: ResMut<NextState<GameWonNpcState>>,
+) {
+    for music in &music_query {
+        commands.entity(music).despawn_recursive();
+    }
+
+    next_player_state.set(GameWonPlayerState::Unloaded);
+    next_level_state.set(GameWonLevelState::Unloaded);
+    next_npc_state.set(GameWonNpcState::Unloaded);
 }
@@ -1,10 +1,10 @@
 use bevy::prelude::*;
 
-use crate::character_select::{get_character_sprite, SelectedCharacterState};
+use crate::character_select::{get_character_sprite, CharacterRegistry, SelectedCharacter};
 
 use crate::corridor::player::get_character_block;
-use crate::game_won::level::HORIZONTAL_OFFSET_FOR_DOOR;
 use crate::sprite::{AnimationTimer, Direction, Movable};
+use crate::sprite_sheets::SpriteSheets;
 
 #[derive(States, PartialEq, Eq, Default, Debug, Clone, Hash)]
 pub enum GameWonNpcState {
@@ -17,10 +17,12 @@ pub enum GameWonNpcState {
 use crate::GameState;
 
 use super::level::GameWonLevelState;
-use super::level::House;
+use crate::tiled_map::MapSpawnPoint;
 
 pub struct NpcPlugin;
 
+const NPC_SPAWN_NAME: &str = "npc";
+
 #[derive(Component)]
 pub struct Npc;
 
@@ -72,34 +74,32 @@ pub fn update_character(
 
 fn setup(
     mut commands: Commands,
-    mut assets: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut sheets: SpriteSheets,
     mut next_state: ResMut<NextState<GameWonNpcState>>,
-    state: Res<State<SelectedCharacterState>>,
-    mut house_query: Query<&Transform, With<House>>,
+    selected_character: Res<SelectedCharacter>,
+    registry: Res<CharacterRegistry>,
+    spawn_query: Query<(&MapSpawnPoint, &Transform)>,
     // mut meshes: ResMut<Assets<Mesh>>,
     // mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
     println!("Spawning gamewon player plugin");
 
-    let house_transform = house_query.single_mut();
-    let npc_state: SelectedCharacterState;
-
-    if state.get() == &SelectedCharacterState::Lisa {
-        npc_state = SelectedCharacterState::Ailsa;
-    } else {
-        npc_state = SelectedCharacterState::Lisa;
-    }
-
-    let (character, animatable) = get_character_block(&npc_state);
-    let texture_atlas_handle = get_character_sprite(&character, &mut texture_atlases, &mut assets);
+    let Some((character, animatable)) = registry
+        .get_partner(&selected_character.id)
+        .and_then(|partner| get_character_block(&registry, &partner.id))
+    else {
+        return;
+    };
+    let texture_atlas_handle = get_character_sprite(&character, &mut sheets);
 
     let idle_anims = animatable.idle_anim_indices.clone();
 
-    // Spawn character 30 px left of door pos.
-    let spawn_x = -1. * HORIZONTAL_OFFSET_FOR_DOOR + 15.;
-
-    let spawn_y = house_transform.translation.y + 10.;
+    // Waits by the house door, at the map's npc spawn point.
+    let spawn = spawn_query
+        .iter()
+        .find(|(spawn, _)| spawn.name == NPC_SPAWN_NAME)
+        .map(|(_, transform)| transform.translation)
+        .unwrap_or_default();
 
     // println!("NPC POSITION: {} {}", spawn_x, spawn_y);
 
@@ -107,7 +107,7 @@ fn setup(
         SpriteSheetBundle {
             texture_atlas: texture_atlas_handle,
             sprite: TextureAtlasSprite::new(idle_anims.first),
-            transform: Transform::from_xyz(spawn_x, spawn_y, 2.),
+            transform: Transform::from_xyz(spawn.x, spawn.y, 2.),
             ..default()
         },
         AnimationTimer(Timer::from_seconds(0.3, TimerMode::Repeating)),
@@ -1,19 +1,15 @@
 use bevy::prelude::*;
 
 use crate::character_select::{
-    get_ailsa_character, get_character_sprite, get_lisa_character, CharacterBlock,
-    SelectedCharacterState, PLAYER_HEIGHT, PLAYER_WIDTH,
-};
-use crate::corridor::player::get_indices_for_movable_direction;
-use crate::game_won::level::{CLAMP_HEIGHT, CLAMP_WIDTH};
-use crate::sprite::{
-    AnimationIndices, AnimationTimer, Direction, Movable, PlayerSpriteSheetAnimatable,
+    get_character_sprite, CharacterRegistry, SelectedCharacter, PLAYER_HEIGHT, PLAYER_WIDTH,
 };
+use crate::corridor::player::{get_character_block, get_indices_for_movable_direction};
+use crate::sprite::{AnimationTimer, Direction, Movable, PlayerSpriteSheetAnimatable};
+use crate::sprite_sheets::SpriteSheets;
 
+use crate::tiled_map::{move_around_colliders, MapCollider};
 use crate::GameState;
 
-use super::level::CLAMP_OFFSET;
-
 #[derive(States, PartialEq, Eq, Default, Debug, Clone, Hash)]
 pub enum GameWonPlayerState {
     #[default]
@@ -22,8 +18,6 @@ pub enum GameWonPlayerState {
     Started,
 }
 
-pub const PLAYER_SPEED_DEFAULT: f32 = 100.;
-
 pub struct PlayerPlugin;
 
 #[derive(Component)]
@@ -83,6 +77,7 @@ pub fn player_movement(
         ),
         With<Player>,
     >,
+    collider_query: Query<&MapCollider>,
     input: Res<Input<KeyCode>>,
     time: Res<Time>,
     mut next_state: ResMut<NextState<GameWonPlayerState>>,
@@ -102,6 +97,7 @@ pub fn player_movement(
 
     let old_direction = movable.direction.clone();
     let old_is_moving = movable.is_moving.clone();
+    let old_translation = transform.translation;
 
     // Top and bottom with checks for diagonal.
     if input.pressed(KeyCode::W) {
@@ -149,14 +145,12 @@ pub fn player_movement(
         movable.direction = Direction::Right;
     }
 
-    transform.translation.x = transform.translation.x.clamp(
-        -1. * CLAMP_WIDTH / 2. + PLAYER_WIDTH / 2.,
-        CLAMP_WIDTH / 2. - PLAYER_WIDTH / 2.,
-    );
-
-    transform.translation.y = transform.translation.y.clamp(
-        -1. * CLAMP_OFFSET + CLAMP_HEIGHT / 2.,
-        CLAMP_HEIGHT - CLAMP_OFFSET - PLAYER_HEIGHT / 2.,
+    // Keeps to the path, going by the map's collision layer.
+    move_around_colliders(
+        &collider_query,
+        old_translation,
+        &mut transform.translation,
+        Vec2::new(PLAYER_WIDTH, PLAYER_HEIGHT),
     );
 
     movable.is_moving = key_pressed;
@@ -186,10 +180,10 @@ pub fn player_movement(
 
 fn setup(
     mut commands: Commands,
-    mut assets: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut sheets: SpriteSheets,
     mut next_state: ResMut<NextState<GameWonPlayerState>>,
-    state: Res<State<SelectedCharacterState>>,
+    selected_character: Res<SelectedCharacter>,
+    registry: Res<CharacterRegistry>,
     // mut meshes: ResMut<Assets<Mesh>>,
     // mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
@@ -198,36 +192,14 @@ fn setup(
     // let texture_handle_run = asset_server.load("player/knight_run_spritesheet.png");
     // println!("Loading player spritesheet");
 
-    let character: CharacterBlock;
-
-    if state.get() == &SelectedCharacterState::Ailsa {
-        character = get_ailsa_character();
-    } else {
-        character = get_lisa_character();
-    }
-
-    let texture_atlas_handle = get_character_sprite(&character, &mut texture_atlases, &mut assets);
-
-    // Use only the subset of sprites in the sheet that make up the run animation
-    let idle_animation_indices = AnimationIndices { first: 0, last: 1 };
-    let run_down_animation_indices = AnimationIndices { first: 9, last: 17 };
-    let run_horizontal_animation_indices = AnimationIndices {
-        first: 27,
-        last: 35,
-    };
-    let run_up_animation_indices = AnimationIndices {
-        first: 45,
-        last: 53,
+    let Some((character, animatable)) = get_character_block(&registry, &selected_character.id)
+    else {
+        return;
     };
 
-    let run_up_horiz_animation_indices = AnimationIndices {
-        first: 36,
-        last: 44,
-    };
-    let run_down_horiz_animation_indices = AnimationIndices {
-        first: 18,
-        last: 26,
-    };
+    let texture_atlas_handle = get_character_sprite(&character, &mut sheets);
+
+    let idle_animation_indices = animatable.idle_anim_indices;
 
     // Spawn Level
     // let map_bottom_y_pos = -1. * (MAP_HEIGHT / 2.) + MAP_VERTICAL_OFFSET;
@@ -241,16 +213,9 @@ fn setup(
         },
         AnimationTimer(Timer::from_seconds(0.3, TimerMode::Repeating)),
         Player,
-        PlayerSpriteSheetAnimatable {
-            idle_anim_indices: idle_animation_indices,
-            moving_horizontal_anim_indices: run_horizontal_animation_indices,
-            moving_up_anim_indices: run_up_animation_indices,
-            moving_down_anim_indices: run_down_animation_indices,
-            moving_up_horiz_anim_indices: run_up_horiz_animation_indices,
-            moving_down_horiz_anim_indices: run_down_horiz_animation_indices,
-        },
+        animatable,
         Movable {
-            speed: PLAYER_SPEED_DEFAULT,
+            speed: character.stats.speed,
             direction: Direction::Down,
             is_moving: false,
             current_animation_indices: idle_animation_indices.clone(),
@@ -0,0 +1,264 @@
+use bevy::asset::{LoadState, LoadedUntypedAsset, RecursiveDependencyLoadState};
+use bevy::prelude::*;
+
+use crate::character_select::CharacterRegistry;
+use crate::corridor::get_corridor_asset_paths;
+use crate::game::biomes::SelectedBiome;
+use crate::game::get_gameplay_asset_paths;
+use crate::main_menu::{BORDER_COLOR, DARK_PURPLE, LIGHT_TEAL, PURPLE, WHITE};
+use crate::GameState;
+
+// Sits between the menus and the Corridor / Gameplay scenes. Everything the next scene uses is
+// fetched here first, so nothing pops in late once it starts. To change scene, set LoadingScene
+// and go to GameState::Loading.
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadingScene>()
+            .init_resource::<SceneAssets>()
+            .add_systems(
+                OnEnter(GameState::Loading),
+                (start_loading, spawn_loading_ui),
+            )
+            .add_systems(
+                Update,
+                (update_loading, continue_past_missing.after(update_loading))
+                    .run_if(in_state(GameState::Loading)),
+            )
+            .add_systems(OnExit(GameState::Loading), despawn_loading_ui);
+    }
+}
+
+const CONTINUE_KEY: KeyCode = KeyCode::Return;
+
+// Used by the menus and the loading screen itself, so every scene gets them.
+const UI_FONTS: [&str; 3] = [
+    "fonts/patua_one/patuaone.ttf",
+    "fonts/spectral/spectral_medium.ttf",
+    "fonts/spectral/spectral_bold.ttf",
+];
+
+// The scene to go to once loading is done.
+#[derive(Resource, Debug)]
+pub struct LoadingScene(pub GameState);
+
+impl Default for LoadingScene {
+    fn default() -> Self {
+        Self(GameState::Corridor)
+    }
+}
+
+// Handles for everything the current scene uses. They're only swapped out on the next load, so
+// nothing gets unloaded while the scene still needs it.
+#[derive(Resource, Default)]
+struct SceneAssets {
+    handles: Vec<(String, Handle<LoadedUntypedAsset>)>,
+    missing: Vec<String>,
+    is_finished: bool,
+}
+
+#[derive(Component)]
+struct LoadingUi;
+
+#[derive(Component)]
+struct ProgressBarFill;
+
+#[derive(Component)]
+struct LoadingStatusText;
+
+fn start_loading(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    loading_scene: Res<LoadingScene>,
+    selected_biome: Res<SelectedBiome>,
+    registry: Res<CharacterRegistry>,
+) {
+    let mut paths = match loading_scene.0 {
+        GameState::Corridor => get_corridor_asset_paths(),
+        GameState::Gameplay => get_gameplay_asset_paths(selected_biome.0),
+        _ => Vec::new(),
+    };
+
+    paths.extend(UI_FONTS.iter().map(|path| path.to_string()));
+
+    // Either player can be any character, and the corridor shows them all.
+    for character in registry.characters.iter() {
+        paths.push(character.sheet.sprite.clone());
+        paths.push(character.portrait.sprite.clone());
+    }
+
+    paths.sort();
+    paths.dedup();
+
+    println!("Loading {} assets for {:?}", paths.len(), loading_scene.0);
+
+    commands.insert_resource(SceneAssets {
+        handles: paths
+            .into_iter()
+            .map(|path| {
+                let handle = assets.load_untyped(path.clone());
+                (path, handle)
+            })
+            .collect(),
+        ..default()
+    });
+}
+
+fn spawn_loading_ui(mut commands: Commands, assets: Res<AssetServer>) {
+    let font = assets.load("fonts/spectral/spectral_medium.ttf");
+
+    let parent = (
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            background_color: DARK_PURPLE.into(),
+            z_index: ZIndex::Global(100),
+            ..default()
+        },
+        LoadingUi,
+        Name::new("Loading UI"),
+    );
+
+    let bar = NodeBundle {
+        style: Style {
+            width: Val::Percent(50.0),
+            height: Val::Px(24.),
+            margin: UiRect::vertical(Val::Px(20.)),
+            border: UiRect::all(Val::Px(2.)),
+            ..default()
+        },
+        background_color: PURPLE.into(),
+        border_color: BORDER_COLOR.into(),
+        ..default()
+    };
+
+    let bar_fill = (
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(0.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            background_color: LIGHT_TEAL.into(),
+            ..default()
+        },
+        ProgressBarFill,
+    );
+
+    let status_text = (
+        TextBundle::from_section(
+            "Loading...",
+            TextStyle {
+                font,
+                font_size: 28.0,
+                color: WHITE,
+            },
+        )
+        .with_text_alignment(TextAlignment::Center),
+        LoadingStatusText,
+    );
+
+    commands.spawn(parent).with_children(|commands| {
+        commands.spawn(bar).with_children(|commands| {
+            commands.spawn(bar_fill);
+        });
+        commands.spawn(status_text);
+    });
+}
+
+// A file counts as done once it and everything it pulls in (like a map's tilesets) are in.
+fn update_loading(
+    assets: Res<AssetServer>,
+    untyped_assets: Res<Assets<LoadedUntypedAsset>>,
+    loading_scene: Res<LoadingScene>,
+    mut scene_assets: ResMut<SceneAssets>,
+    mut fill_query: Query<&mut Style, With<ProgressBarFill>>,
+    mut text_query: Query<&mut Text, With<LoadingStatusText>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+) {
+    if scene_assets.is_finished {
+        return;
+    }
+
+    let mut loaded = 0;
+    let mut missing = Vec::new();
+
+    for (path, handle) in scene_assets.handles.iter() {
+        let dependency_state = untyped_assets
+            .get(handle)
+            .and_then(|untyped| assets.get_recursive_dependency_load_state(untyped.handle.id()));
+
+        match (assets.get_load_state(handle), dependency_state) {
+            (Some(LoadState::Failed), _) | (_, Some(RecursiveDependencyLoadState::Failed)) => {
+                missing.push(path.clone())
+            }
+            (_, Some(RecursiveDependencyLoadState::Loaded)) => loaded += 1,
+            _ => {}
+        }
+    }
+
+    let total = scene_assets.handles.len();
+    let done = loaded + missing.len();
+
+    for mut style in fill_query.iter_mut() {
+        style.width = Val::Percent(if total == 0 {
+            100.
+        } else {
+            done as f32 / total as f32 * 100.
+        });
+    }
+
+    if done < total {
+        for mut text in text_query.iter_mut() {
+            text.sections[0].value = format!("Loading... {} / {}", done, total);
+        }
+        return;
+    }
+
+    scene_assets.is_finished = true;
+
+    if missing.is_empty() {
+        next_game_state.set(loading_scene.0.clone());
+        return;
+    }
+
+    // Don't carry on as if nothing happened - say which files are missing and let the player
+    // decide whether to go on without them.
+    for path in missing.iter() {
+        println!("Missing asset: assets/{}", path);
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!(
+            "Some files could not be loaded:\n{}\n\nPress Enter to continue anyway",
+            missing.join("\n")
+        );
+    }
+
+    scene_assets.missing = missing;
+}
+
+fn continue_past_missing(
+    keys: Res<Input<KeyCode>>,
+    scene_assets: Res<SceneAssets>,
+    loading_scene: Res<LoadingScene>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+) {
+    if !scene_assets.missing.is_empty() && keys.just_pressed(CONTINUE_KEY) {
+        next_game_state.set(loading_scene.0.clone());
+    }
+}
+
+fn despawn_loading_ui(mut commands: Commands, ui_query: Query<Entity, With<LoadingUi>>) {
+    for ui in &ui_query {
+        commands.entity(ui).despawn_recursive();
+    }
+}
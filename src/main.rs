@@ -4,31 +4,44 @@ use bevy::prelude::*;
 use bevy::{core_pipeline::clear_color::ClearColorConfig, window::WindowMode};
 
 use bevy::input::common_conditions::input_toggle_active;
+use asset_source::AssetSourcePlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
-use character_select::{CharacterSelectPlugin, SelectedCharacterState};
+use character_select::CharacterSelectPlugin;
 use corridor::CorridorPlugin;
 use game::GameplayPlugin;
 use game_won::GameWonPlugin;
+use loading::LoadingPlugin;
 use main_menu::MainMenuPlugin;
+use meta::MetaPlugin;
+use sprite_sheets::SpriteSheetsPlugin;
+use tiled_map::TiledMapPlugin;
 use util_fade::FadePlugin;
 
+mod asset_source;
 mod character_select;
 mod corridor;
 mod game;
 mod util_fade;
 // mod game_over;
 mod game_won;
+mod loading;
 mod main_menu;
+mod meta;
 mod sprite;
+mod sprite_sheets;
+mod tiled_map;
 
 const WIDTH: f32 = 1600.0;
 const HEIGHT: f32 = 900.0;
+pub const CAMERA_SCALE: f32 = 0.35;
 
 #[derive(States, PartialEq, Eq, Default, Debug, Clone, Hash)]
 pub enum GameState {
     #[default]
     MainMenu,
     CharacterSelect,
+    // Preloads the assets for LoadingScene, then moves on to it.
+    Loading,
     Corridor,
     Gameplay,
     GameWon,
@@ -37,6 +50,7 @@ pub enum GameState {
 fn main() {
     App::new()
         .insert_resource(Msaa::Off)
+        .add_plugins(AssetSourcePlugin)
         // .insert_resource(Msaa { samples: 1 })
         .add_plugins(
             DefaultPlugins
@@ -53,16 +67,19 @@ fn main() {
                 })
                 .build(),
         )
-        .add_state::<SelectedCharacterState>()
         .add_state::<GameState>()
         .add_systems(Startup, setup)
         .add_plugins((
+            SpriteSheetsPlugin,
             FadePlugin,
+            TiledMapPlugin,
             MainMenuPlugin,
             CharacterSelectPlugin,
+            LoadingPlugin,
             CorridorPlugin,
             GameWonPlugin,
             GameplayPlugin,
+            MetaPlugin,
             /*GamePlugin, GameOver*/
         ))
         // .add_plugins(
@@ -82,7 +99,7 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         projection: OrthographicProjection {
             // far: 1000.,
             near: -1000.,
-            scale: 0.35,
+            scale: CAMERA_SCALE,
             ..default()
         },
         ..default()
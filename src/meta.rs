@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::game::biomes::Biome;
+use crate::game::stats::{Stat, StatModifier};
+use crate::game::ui::RunStats;
+use crate::game::GamePlayState;
+use crate::GameState;
+
+// Upgrades bought on the character select screen with gold earned in runs, and which stages
+// have been opened. They're saved to a file so they carry over from one session to the next.
+pub struct MetaPlugin;
+
+impl Plugin for MetaPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MetaProgress::load())
+            .add_systems(OnEnter(GamePlayState::GameOver), bank_run_gold)
+            .add_systems(OnEnter(GameState::GameWon), bank_run_gold);
+    }
+}
+
+const SAVE_PATH: &str = "wed_save.ron";
+const SAVE_ENV_VAR: &str = "WED_SAVE";
+
+pub const META_MAX_LEVEL: u32 = 5;
+const KILLS_PER_GOLD: u32 = 10;
+const WIN_GOLD: u32 = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MetaUpgrade {
+    Might,
+    Haste,
+    Reach,
+    Precision,
+}
+
+impl MetaUpgrade {
+    pub const ALL: [MetaUpgrade; 4] = [
+        MetaUpgrade::Might,
+        MetaUpgrade::Haste,
+        MetaUpgrade::Reach,
+        MetaUpgrade::Precision,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            MetaUpgrade::Might => "Might",
+            MetaUpgrade::Haste => "Haste",
+            MetaUpgrade::Reach => "Reach",
+            MetaUpgrade::Precision => "Precision",
+        }
+    }
+
+    pub fn get_key(&self) -> KeyCode {
+        match self {
+            MetaUpgrade::Might => KeyCode::Key1,
+            MetaUpgrade::Haste => KeyCode::Key2,
+            MetaUpgrade::Reach => KeyCode::Key3,
+            MetaUpgrade::Precision => KeyCode::Key4,
+        }
+    }
+
+    // Gold for the next level, from whatever level is owned now.
+    pub fn get_cost(&self, level: u32) -> u32 {
+        25 * (level + 1)
+    }
+
+    fn get_modifier(&self, level: u32) -> StatModifier {
+        let level = level as f32;
+
+        match self {
+            MetaUpgrade::Might => StatModifier::multiply(Stat::Damage, 1. + 0.05 * level),
+            MetaUpgrade::Haste => StatModifier::multiply(Stat::Cooldown, 1. - 0.04 * level),
+            MetaUpgrade::Reach => StatModifier::multiply(Stat::Area, 1. + 0.05 * level),
+            MetaUpgrade::Precision => StatModifier::add(Stat::CritChance, 0.02 * level),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetaProgress {
+    pub gold: u32,
+    levels: HashMap<MetaUpgrade, u32>,
+    // How many biomes are open, counting the first. Beating a biome's boss opens the next one.
+    unlocked_biomes: usize,
+}
+
+impl Default for MetaProgress {
+    fn default() -> Self {
+        Self {
+            gold: 0,
+            levels: HashMap::new(),
+            unlocked_biomes: 1,
+        }
+    }
+}
+
+impl MetaProgress {
+    // A missing save is a fresh start. One that can't be parsed is reported, then also treated
+    // as a fresh start.
+    fn load() -> Self {
+        let path = get_save_path();
+
+        let Ok(contents) = std::fs::read_to_string(&path) else {
+            return Self::default();
+        };
+
+        match ron::de::from_str(&contents) {
+            Ok(progress) => progress,
+            Err(error) => {
+                println!("Could not read save {}: {}", path.display(), error);
+                Self::default()
+            }
+        }
+    }
+
+    fn save(&self) {
+        let path = get_save_path();
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| error.to_string())
+            .and_then(|contents| {
+                std::fs::write(&path, contents).map_err(|error| error.to_string())
+            });
+
+        if let Err(error) = result {
+            println!("Could not write save {}: {}", path.display(), error);
+        }
+    }
+
+    pub fn get_level(&self, upgrade: MetaUpgrade) -> u32 {
+        self.levels.get(&upgrade).copied().unwrap_or(0)
+    }
+
+    // Returns false if it's maxed out or there isn't enough gold.
+    pub fn buy(&mut self, upgrade: MetaUpgrade) -> bool {
+        let level = self.get_level(upgrade);
+        let cost = upgrade.get_cost(level);
+
+        if level >= META_MAX_LEVEL || self.gold < cost {
+            return false;
+        }
+
+        self.gold -= cost;
+        self.levels.insert(upgrade, level + 1);
+        self.save();
+
+        true
+    }
+
+    pub fn is_biome_unlocked(&self, biome: Biome) -> bool {
+        biome.get_index() < self.unlocked_biomes
+    }
+
+    // Opens every biome up to and including the one after this one.
+    pub fn unlock_biome_after(&mut self, biome: Biome) {
+        let unlocked = (biome.get_index() + 2).min(Biome::ALL.len());
+
+        if unlocked <= self.unlocked_biomes {
+            return;
+        }
+
+        self.unlocked_biomes = unlocked;
+        self.save();
+
+        println!("Unlocked biome {}", Biome::ALL[unlocked - 1].get_name());
+    }
+
+    // Added to every player's PlayerStats when they spawn.
+    pub fn get_modifiers(&self) -> Vec<StatModifier> {
+        MetaUpgrade::ALL
+            .iter()
+            .filter(|upgrade| self.get_level(**upgrade) > 0)
+            .map(|upgrade| upgrade.get_modifier(self.get_level(*upgrade)))
+            .collect()
+    }
+}
+
+// Next to where the game is run from, unless WED_SAVE points somewhere else.
+fn get_save_path() -> PathBuf {
+    std::env::var(SAVE_ENV_VAR)
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(SAVE_PATH))
+}
+
+fn bank_run_gold(
+    mut progress: ResMut<MetaProgress>,
+    run_stats: Res<RunStats>,
+    game_state: Res<State<GameState>>,
+) {
+    let mut gold = run_stats.kills / KILLS_PER_GOLD;
+
+    if *game_state.get() == GameState::GameWon {
+        gold += WIN_GOLD;
+    }
+
+    println!("Banked {} gold", gold);
+
+    progress.gold += gold;
+    progress.save();
+}
@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use serde::Deserialize;
 
 use crate::game::weapons::WeaponsEnum;
 
@@ -63,7 +64,7 @@ pub struct EffectSpriteSheetAnimatable {
     pub anim_indices: AnimationIndices,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
 pub struct AnimationIndices {
     pub first: usize,
     pub last: usize,
@@ -75,6 +76,18 @@ pub struct AnimationTimer(pub Timer);
 #[derive(Component, Debug)]
 pub struct Health {
     pub total: f32,
+    pub max: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self { total: max, max }
+    }
+
+    // Every heal goes through here, so nothing tops a bar up past its max.
+    pub fn heal(&mut self, amount: f32) {
+        self.total = (self.total + amount).min(self.max);
+    }
 }
 
 pub fn get_translation_for_direction(direction: Direction, default_z: f32) -> Vec3 {
@@ -0,0 +1,116 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+// Every sprite sheet gets its TextureAtlas made once, the first time something asks for it,
+// and everyone after that shares the same handle. Ask through SpriteSheets rather than adding
+// atlases to Assets<TextureAtlas> directly.
+pub struct SpriteSheetsPlugin;
+
+impl Plugin for SpriteSheetsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AtlasRegistry>();
+    }
+}
+
+// Atlases by sheet id. They're kept for the whole run, there's only ever a few dozen.
+#[derive(Resource, Default)]
+pub struct AtlasRegistry {
+    atlases: HashMap<String, Handle<TextureAtlas>>,
+}
+
+// How an image is cut into an even grid of frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SheetGrid {
+    pub tile_size: Vec2,
+    pub columns: usize,
+    pub rows: usize,
+    pub padding: Option<Vec2>,
+    pub offset: Option<Vec2>,
+}
+
+impl SheetGrid {
+    pub fn new(tile_size: Vec2, columns: usize, rows: usize) -> Self {
+        Self {
+            tile_size,
+            columns,
+            rows,
+            padding: None,
+            offset: None,
+        }
+    }
+
+    // The same image cut two different ways is two different sheets.
+    fn get_id(&self, path: &str) -> String {
+        format!(
+            "{}#{}x{}/{}x{}/{:?}/{:?}",
+            path,
+            self.tile_size.x,
+            self.tile_size.y,
+            self.columns,
+            self.rows,
+            self.padding,
+            self.offset
+        )
+    }
+}
+
+#[derive(SystemParam)]
+pub struct SpriteSheets<'w> {
+    registry: ResMut<'w, AtlasRegistry>,
+    assets: Res<'w, AssetServer>,
+    texture_atlases: ResMut<'w, Assets<TextureAtlas>>,
+}
+
+impl<'w> SpriteSheets<'w> {
+    pub fn get_grid(&mut self, path: &str, grid: SheetGrid) -> Handle<TextureAtlas> {
+        let id = grid.get_id(path);
+
+        self.get_or_add(id, |assets| {
+            TextureAtlas::from_grid(
+                assets.load(path.to_string()),
+                grid.tile_size,
+                grid.columns,
+                grid.rows,
+                grid.padding,
+                grid.offset,
+            )
+        })
+    }
+
+    // Same as get_grid, for an image that's already loading.
+    pub fn get_grid_for_texture(
+        &mut self,
+        texture: &Handle<Image>,
+        grid: SheetGrid,
+    ) -> Handle<TextureAtlas> {
+        let id = grid.get_id(&format!("{:?}", texture.id()));
+
+        self.get_or_add(id, |_| {
+            TextureAtlas::from_grid(
+                texture.clone(),
+                grid.tile_size,
+                grid.columns,
+                grid.rows,
+                grid.padding,
+                grid.offset,
+            )
+        })
+    }
+
+    // For atlases that aren't an even grid. Build only runs the first time the id is asked for.
+    pub fn get_or_add(
+        &mut self,
+        id: String,
+        build: impl FnOnce(&AssetServer) -> TextureAtlas,
+    ) -> Handle<TextureAtlas> {
+        if let Some(handle) = self.registry.atlases.get(&id) {
+            return handle.clone();
+        }
+
+        let handle = self.texture_atlases.add(build(&self.assets));
+        self.registry.atlases.insert(id, handle.clone());
+
+        handle
+    }
+}
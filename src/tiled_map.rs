@@ -0,0 +1,478 @@
+use bevy::app::Plugin;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext, ParseAssetPathError};
+use bevy::prelude::*;
+use bevy::reflect::TypePath;
+use bevy::sprite::Anchor;
+use bevy::utils::BoxedFuture;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::sprite_sheets::{SheetGrid, SpriteSheets};
+
+// Scenes laid out in Tiled (https://www.mapeditor.org) and saved as JSON maps (.tmj), so the
+// corridor, the house at the end and the walls of each stage's arena can be edited without
+// touching code.
+//
+// Tile layers become sprites, and objects are picked up by their class (or type in older Tiled):
+// - `collider` rectangles block player movement (and enemies and projectiles in the arena).
+// - `trigger` rectangles are named areas that scenes react to, like the door or the house.
+// - `spawn` points mark where things get placed. The map is positioned so the spawn named
+//   `player` sits at the world origin.
+// - Tile objects (anything placed with a tile) become sprites and keep their object name, so
+//   scenes can find and animate them.
+//
+// Layers draw in order, or at the layer's float `z` property when it has one.
+pub struct TiledMapPlugin;
+
+impl Plugin for TiledMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<TiledMap>()
+            .init_asset_loader::<TiledMapLoader>();
+    }
+}
+
+const PLAYER_SPAWN_NAME: &str = "player";
+
+// Tiled keeps flip flags in the top bits of each tile id.
+const FLIPPED_HORIZONTALLY_FLAG: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY_FLAG: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY_FLAG: u32 = 0x2000_0000;
+const TILE_ID_MASK: u32 =
+    !(FLIPPED_HORIZONTALLY_FLAG | FLIPPED_VERTICALLY_FLAG | FLIPPED_DIAGONALLY_FLAG);
+
+#[derive(Asset, TypePath, Debug, Deserialize)]
+pub struct TiledMap {
+    pub width: u32,
+    pub height: u32,
+    #[serde(rename = "tilewidth")]
+    pub tile_width: f32,
+    #[serde(rename = "tileheight")]
+    pub tile_height: f32,
+    pub layers: Vec<TiledLayer>,
+    pub tilesets: Vec<TiledTileset>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TiledLayer {
+    TileLayer(TiledTileLayer),
+    ObjectGroup(TiledObjectLayer),
+    // Image layers and groups aren't used by any scene yet.
+    #[serde(other)]
+    Unsupported,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TiledTileLayer {
+    pub name: String,
+    pub width: u32,
+    pub data: Vec<u32>,
+    #[serde(default = "get_default_visible")]
+    pub visible: bool,
+    #[serde(default)]
+    pub properties: Vec<TiledProperty>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TiledObjectLayer {
+    pub objects: Vec<TiledObject>,
+    #[serde(default = "get_default_visible")]
+    pub visible: bool,
+    #[serde(default)]
+    pub properties: Vec<TiledProperty>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TiledObject {
+    #[serde(default)]
+    pub name: String,
+    // Tiled 1.9 briefly wrote this as `class`.
+    #[serde(default, rename = "type", alias = "class")]
+    pub class: String,
+    pub x: f32,
+    pub y: f32,
+    #[serde(default)]
+    pub width: f32,
+    #[serde(default)]
+    pub height: f32,
+    #[serde(default)]
+    pub gid: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TiledTileset {
+    #[serde(rename = "firstgid")]
+    pub first_gid: u32,
+    // Only set for tilesets saved to their own file, which the loader doesn't follow.
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub image: String,
+    #[serde(default, rename = "tilewidth")]
+    pub tile_width: f32,
+    #[serde(default, rename = "tileheight")]
+    pub tile_height: f32,
+    #[serde(default)]
+    pub columns: usize,
+    #[serde(default, rename = "tilecount")]
+    pub tile_count: usize,
+    #[serde(default)]
+    pub margin: f32,
+    #[serde(default)]
+    pub spacing: f32,
+    #[serde(skip)]
+    pub texture: Handle<Image>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TiledProperty {
+    pub name: String,
+    pub value: serde_json::Value,
+}
+
+fn get_default_visible() -> bool {
+    true
+}
+
+fn get_float_property(properties: &[TiledProperty], name: &str) -> Option<f32> {
+    properties
+        .iter()
+        .find(|property| property.name == name)
+        .and_then(|property| property.value.as_f64())
+        .map(|value| value as f32)
+}
+
+#[derive(Default)]
+pub struct TiledMapLoader;
+
+#[derive(Debug, Error)]
+pub enum TiledMapLoaderError {
+    #[error("Could not read map: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse map: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Could not resolve tileset image path: {0}")]
+    ImagePath(#[from] ParseAssetPathError),
+    #[error("Tileset {0} is saved in its own file - embed it in the map instead")]
+    ExternalTileset(String),
+}
+
+impl AssetLoader for TiledMapLoader {
+    type Asset = TiledMap;
+    type Settings = ();
+    type Error = TiledMapLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+
+            let mut map = serde_json::from_slice::<TiledMap>(&bytes)?;
+
+            for tileset in map.tilesets.iter_mut() {
+                if let Some(source) = &tileset.source {
+                    return Err(TiledMapLoaderError::ExternalTileset(source.clone()));
+                }
+
+                // Image paths are relative to the map file.
+                let image_path = load_context.asset_path().resolve_embed(&tileset.image)?;
+                tileset.texture = load_context.load(image_path);
+            }
+
+            Ok(map)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tmj"]
+    }
+}
+
+// Everything spawned from a map, so a scene can clear it out in one go.
+#[derive(Debug, Component)]
+pub struct MapEntity;
+
+#[derive(Debug, Component)]
+pub struct MapCollider {
+    pub rect: Rect,
+}
+
+#[derive(Debug, Component)]
+pub struct MapTrigger {
+    pub name: String,
+    pub rect: Rect,
+}
+
+#[derive(Debug, Component)]
+pub struct MapSpawnPoint {
+    pub name: String,
+}
+
+// Sprite placed as a tile object, e.g. the door or the house.
+#[derive(Debug, Component)]
+pub struct MapObject {
+    pub name: String,
+    pub class: String,
+}
+
+impl MapTrigger {
+    pub fn contains(&self, position: Vec3) -> bool {
+        self.rect.contains(position.truncate())
+    }
+}
+
+impl TiledMap {
+    fn get_pixel_size(&self) -> Vec2 {
+        Vec2::new(
+            self.width as f32 * self.tile_width,
+            self.height as f32 * self.tile_height,
+        )
+    }
+
+    // World position of the map's top left corner, lined up so the player spawn ends up at the
+    // origin. Maps without one are centred instead.
+    fn get_origin(&self) -> Vec2 {
+        let spawn = self
+            .layers
+            .iter()
+            .filter_map(|layer| match layer {
+                TiledLayer::ObjectGroup(layer) => Some(layer.objects.iter()),
+                _ => None,
+            })
+            .flatten()
+            .find(|object| object.class == "spawn" && object.name == PLAYER_SPAWN_NAME);
+
+        match spawn {
+            Some(spawn) => Vec2::new(-spawn.x, spawn.y),
+            None => Vec2::new(-self.get_pixel_size().x / 2., self.get_pixel_size().y / 2.),
+        }
+    }
+
+    // Tiled's y runs down the map, world y runs up.
+    fn to_world(&self, origin: Vec2, x: f32, y: f32) -> Vec2 {
+        origin + Vec2::new(x, -y)
+    }
+
+    fn get_tileset(&self, gid: u32) -> Option<(usize, &TiledTileset)> {
+        self.tilesets
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, tileset)| tileset.first_gid <= gid)
+    }
+}
+
+fn get_tileset_atlases(map: &TiledMap, sheets: &mut SpriteSheets) -> Vec<Handle<TextureAtlas>> {
+    map.tilesets
+        .iter()
+        .map(|tileset| {
+            let columns = tileset.columns.max(1);
+            let rows = tileset.tile_count.div_ceil(columns);
+
+            sheets.get_grid_for_texture(
+                &tileset.texture,
+                SheetGrid {
+                    tile_size: Vec2::new(tileset.tile_width, tileset.tile_height),
+                    columns,
+                    rows: rows.max(1),
+                    padding: Some(Vec2::splat(tileset.spacing)),
+                    offset: Some(Vec2::splat(tileset.margin)),
+                },
+            )
+        })
+        .collect()
+}
+
+// Sprite for one tile id, anchored on its bottom left corner like Tiled draws them.
+fn get_tile_sprite(
+    map: &TiledMap,
+    atlases: &[Handle<TextureAtlas>],
+    raw_gid: u32,
+    position: Vec3,
+    size: Option<Vec2>,
+) -> Option<SpriteSheetBundle> {
+    let gid = raw_gid & TILE_ID_MASK;
+
+    if gid == 0 {
+        return None;
+    }
+
+    let (tileset_index, tileset) = map.get_tileset(gid)?;
+
+    Some(SpriteSheetBundle {
+        texture_atlas: atlases[tileset_index].clone(),
+        sprite: TextureAtlasSprite {
+            index: (gid - tileset.first_gid) as usize,
+            anchor: Anchor::BottomLeft,
+            flip_x: raw_gid & FLIPPED_HORIZONTALLY_FLAG != 0,
+            flip_y: raw_gid & FLIPPED_VERTICALLY_FLAG != 0,
+            custom_size: size,
+            ..default()
+        },
+        transform: Transform::from_translation(position),
+        ..default()
+    })
+}
+
+pub fn spawn_tiled_map(map: &TiledMap, commands: &mut Commands, sheets: &mut SpriteSheets) {
+    let atlases = get_tileset_atlases(map, sheets);
+    let origin = map.get_origin();
+
+    for (layer_index, layer) in map.layers.iter().enumerate() {
+        match layer {
+            TiledLayer::TileLayer(layer) => {
+                if !layer.visible {
+                    continue;
+                }
+
+                let z = get_float_property(&layer.properties, "z")
+                    .unwrap_or(-0.4 + layer_index as f32 * 0.1);
+
+                for (index, raw_gid) in layer.data.iter().enumerate() {
+                    let col = (index % layer.width as usize) as f32;
+                    let row = (index / layer.width as usize) as f32;
+
+                    let bottom_left =
+                        map.to_world(origin, col * map.tile_width, (row + 1.) * map.tile_height);
+
+                    let Some(sprite) =
+                        get_tile_sprite(map, &atlases, *raw_gid, bottom_left.extend(z), None)
+                    else {
+                        continue;
+                    };
+
+                    commands.spawn((sprite, MapEntity, Name::new(layer.name.clone())));
+                }
+            }
+            TiledLayer::ObjectGroup(layer) => {
+                let z = get_float_property(&layer.properties, "z")
+                    .unwrap_or(-0.4 + layer_index as f32 * 0.1);
+
+                for object in layer.objects.iter() {
+                    spawn_object(map, &atlases, origin, object, z, layer.visible, commands);
+                }
+            }
+            TiledLayer::Unsupported => {}
+        }
+    }
+}
+
+fn spawn_object(
+    map: &TiledMap,
+    atlases: &[Handle<TextureAtlas>],
+    origin: Vec2,
+    object: &TiledObject,
+    z: f32,
+    visible: bool,
+    commands: &mut Commands,
+) {
+    let name = Name::new(if object.name.is_empty() {
+        object.class.clone()
+    } else {
+        object.name.clone()
+    });
+
+    // Tile objects sit on their bottom left corner, everything else hangs off the top left.
+    if let Some(raw_gid) = object.gid {
+        let bottom_left = map.to_world(origin, object.x, object.y);
+        let size = Vec2::new(object.width, object.height);
+
+        if let Some(mut sprite) = get_tile_sprite(
+            map,
+            atlases,
+            raw_gid,
+            bottom_left.extend(z),
+            (size.x > 0. && size.y > 0.).then_some(size),
+        ) {
+            if !visible {
+                sprite.visibility = Visibility::Hidden;
+            }
+
+            commands.spawn((
+                sprite,
+                MapObject {
+                    name: object.name.clone(),
+                    class: object.class.clone(),
+                },
+                MapEntity,
+                name,
+            ));
+        }
+
+        return;
+    }
+
+    let top_left = map.to_world(origin, object.x, object.y);
+    let rect = Rect::from_corners(top_left, top_left + Vec2::new(object.width, -object.height));
+
+    match object.class.as_str() {
+        "collider" => {
+            commands.spawn((MapCollider { rect }, MapEntity, name));
+        }
+        "trigger" => {
+            commands.spawn((
+                MapTrigger {
+                    name: object.name.clone(),
+                    rect,
+                },
+                MapEntity,
+                name,
+            ));
+        }
+        "spawn" => {
+            commands.spawn((
+                TransformBundle::from_transform(Transform::from_translation(top_left.extend(0.))),
+                MapSpawnPoint {
+                    name: object.name.clone(),
+                },
+                MapEntity,
+                name,
+            ));
+        }
+        _ => {
+            println!("Skipping map object {} with unknown class", object.name);
+        }
+    }
+}
+
+pub fn unload_tiled_map(commands: &mut Commands, map_query: &Query<Entity, With<MapEntity>>) {
+    for entity in map_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// Redoes a move from `old` to `translation` for a body of the given size, keeping whichever
+// axis doesn't run into a collider so walking into a wall at an angle slides along it.
+pub fn move_around_colliders(
+    collider_query: &Query<&MapCollider>,
+    old: Vec3,
+    translation: &mut Vec3,
+    size: Vec2,
+) {
+    let is_blocked = |position: Vec2| {
+        let body = Rect::from_center_size(position, size);
+
+        collider_query
+            .iter()
+            .any(|collider| !collider.rect.intersect(body).is_empty())
+    };
+
+    if !is_blocked(translation.truncate()) {
+        return;
+    }
+
+    if !is_blocked(Vec2::new(translation.x, old.y)) {
+        translation.y = old.y;
+    } else if !is_blocked(Vec2::new(old.x, translation.y)) {
+        translation.x = old.x;
+    } else {
+        translation.x = old.x;
+        translation.y = old.y;
+    }
+}